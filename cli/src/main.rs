@@ -3,13 +3,16 @@
 //
 // SPDX-License-Identifier: MIT
 
+use std::fs;
+use std::io::{self, Write};
 use std::result::Result::Ok;
 
 use anyhow::Result;
-use clap::{Args, Parser, Subcommand};
-use log::LevelFilter;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use log::{LevelFilter, warn};
 use serde::{Deserialize, Serialize};
 
+use compute_pcrs_lib::tpmevents::combine;
 use compute_pcrs_lib::*;
 
 #[derive(Parser, Debug)]
@@ -23,6 +26,10 @@ struct Cli {
     #[arg(short = 'v', long, action = clap::ArgAction::Count, global = true)]
     verbose: u8,
 
+    /// Include the raw data hashed into each event in the computed PCRs
+    #[arg(long = "event-data", default_value_t = false, global = true)]
+    event_data: bool,
+
     #[command(subcommand)]
     command: Command,
 }
@@ -34,6 +41,61 @@ struct SecureBootVarStores {
     efivars: Option<String>,
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum TreeFormat {
+    Dot,
+    Text,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum SbatPolicy {
+    /// The previous policy of the shim .sbatlevel section
+    Previous,
+    /// The latest policy of the shim .sbatlevel section
+    Latest,
+    /// Derived from the SbatPolicy and SbatLevelRT variables of the efivars
+    /// directory, as shim does on boot
+    Efivars,
+}
+
+impl From<SbatPolicy> for shim::SbatLevelSelection {
+    fn from(policy: SbatPolicy) -> Self {
+        match policy {
+            SbatPolicy::Previous => {
+                shim::SbatLevelSelection::Policy(shim::SbatLevelPolicyType::PREVIOUS)
+            }
+            SbatPolicy::Latest => {
+                shim::SbatLevelSelection::Policy(shim::SbatLevelPolicyType::LATEST)
+            }
+            SbatPolicy::Efivars => shim::SbatLevelSelection::Efivars,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum UpdateMode {
+    Append,
+    Replace,
+}
+
+impl From<UpdateMode> for uefi::authvar::UpdateMode {
+    fn from(mode: UpdateMode) -> Self {
+        match mode {
+            UpdateMode::Append => uefi::authvar::UpdateMode::Append,
+            UpdateMode::Replace => uefi::authvar::UpdateMode::Replace,
+        }
+    }
+}
+
+impl From<TreeFormat> for combine::TreeFormat {
+    fn from(format: TreeFormat) -> Self {
+        match format {
+            TreeFormat::Dot => combine::TreeFormat::Dot,
+            TreeFormat::Text => combine::TreeFormat::Text,
+        }
+    }
+}
+
 #[derive(Subcommand, Debug)]
 enum Command {
     /// Compute all possible PCR values from the binaries available in the current environment
@@ -59,6 +121,13 @@ enum Command {
             help = "Compute PCRs as if secure boot was disabled in the system"
         )]
         no_secureboot: bool,
+        #[arg(
+            long = "sbat-policy",
+            value_enum,
+            default_value = "previous",
+            help = "SbatLevel policy shim measures into PCR 7"
+        )]
+        sbat_policy: SbatPolicy,
         #[arg(
             long = "mok-variables",
             required = true,
@@ -97,6 +166,12 @@ enum Command {
             help = "Path to the target container image root filesystem"
         )]
         rootfs: String,
+        #[arg(
+            long,
+            default_value_t = false,
+            help = "Indicates that the linux image is an UKI image (e.g. is not vmlinuz))"
+        )]
+        uki: bool,
         #[command(flatten)]
         secureboot_variables: SecureBootVarStores,
         #[arg(
@@ -105,6 +180,18 @@ enum Command {
             help = "Compute PCRs as if secure boot was disabled in the system"
         )]
         no_secureboot: bool,
+        #[arg(
+            long = "sbat-policy",
+            value_enum,
+            default_value = "previous",
+            help = "SbatLevel policy shim measures into PCR 7"
+        )]
+        sbat_policy: SbatPolicy,
+        #[arg(
+            long = "mok-variables",
+            help = "Path to directory storing MokListRT. Only shim's vendor_cert is used if not given"
+        )]
+        mok_variables: Option<String>,
     },
     /// Compute PCR 11
     Pcr11 {
@@ -120,6 +207,275 @@ enum Command {
         )]
         mok_variables: String,
     },
+    /// Export a Keylime measured boot reference state (mb_refstate)
+    KeylimeRefstate {
+        #[arg(
+            long,
+            short,
+            default_value = "/",
+            help = "Path to the target container image root filesystem"
+        )]
+        rootfs: String,
+        #[command(flatten)]
+        secureboot_variables: SecureBootVarStores,
+        #[arg(
+            long,
+            default_value_t = false,
+            help = "Indicates that the linux image is an UKI image (e.g. is not vmlinuz))"
+        )]
+        uki: bool,
+        #[arg(
+            long = "secureboot-disabled",
+            default_value_t = false,
+            help = "Compute PCRs as if secure boot was disabled in the system"
+        )]
+        no_secureboot: bool,
+        #[arg(
+            long = "sbat-policy",
+            value_enum,
+            default_value = "previous",
+            help = "SbatLevel policy shim measures into PCR 7"
+        )]
+        sbat_policy: SbatPolicy,
+        #[arg(
+            long = "mok-variables",
+            required = true,
+            help = "Path to directory storing MokListRT, MokListTrustedRT and MokListXRT"
+        )]
+        mok_variables: String,
+        #[arg(
+            long,
+            required = true,
+            help = "Path to the initrd the kernel boots with"
+        )]
+        initrd: String,
+        #[arg(
+            long = "kernel-cmdline",
+            required = true,
+            help = "Regular expression the kernel command line has to match"
+        )]
+        kernel_cmdline: String,
+    },
+    /// Combine the PCRs computed for several images into every PCR set a
+    /// system updating between them could boot with
+    Combine {
+        /// Paths to the output of the all command for each image. Paths are
+        /// used as image labels
+        #[arg(required = true)]
+        images: Vec<String>,
+        #[arg(
+            long = "max-branches",
+            help = "Fail if combining the images walks more event tree branches than this"
+        )]
+        max_branches: Option<usize>,
+        #[arg(
+            long = "dump-tree",
+            value_enum,
+            help = "Dump the event trees walked to combine the images to stderr"
+        )]
+        dump_tree: Option<TreeFormat>,
+    },
+    /// Generate the clevis tpm2 pin configuration sealing to the PCRs of an image
+    ClevisPin {
+        /// Path to the output of the all command for the image
+        image: String,
+        #[arg(
+            long = "pcr-ids",
+            value_delimiter = ',',
+            default_value = "4,7,14",
+            help = "Comma separated list of the PCRs to seal to"
+        )]
+        pcr_ids: Vec<u64>,
+    },
+    /// Export the events computed for an image as a TCG Canonical Event Log
+    Cel {
+        /// Path to the output of the all command for the image
+        image: String,
+        #[arg(
+            long,
+            default_value_t = false,
+            help = "Write the log in its CBOR encoding instead of JSON"
+        )]
+        cbor: bool,
+    },
+    /// Write the events computed for an image as a binary TCG2 event log
+    EventLog {
+        /// Path to the output of the all command for the image, computed
+        /// with --event-data
+        image: String,
+    },
+    /// Convert tpm2_eventlog YAML output into the output of the all command
+    ImportEventlog {
+        /// Path to the YAML printed by tpm2_eventlog
+        log: String,
+    },
+    /// Recover the Secure Boot and MOK variables from a binary TCG2 event log
+    /// recorded on the target platform
+    RecoverVariables {
+        /// Path to the event log, e.g. a copy of
+        /// /sys/kernel/security/tpm0/binary_bios_measurements
+        log: String,
+        #[arg(long, required = true, help = "Directory to write the EFIVar files to")]
+        efivars: String,
+        #[arg(
+            long = "mok-variables",
+            required = true,
+            help = "Directory to write MokListRT, MokListTrustedRT and MokListXRT to"
+        )]
+        mok_variables: String,
+    },
+    /// Build the Secure Boot variables of a key set from PEM or DER
+    /// certificates and compute the resulting PCR 7
+    SecureBootKeys {
+        #[arg(
+            long,
+            short,
+            default_value = "/",
+            help = "Path to the target container image root filesystem"
+        )]
+        rootfs: String,
+        #[arg(
+            long,
+            default_value_t = false,
+            help = "Indicates that the linux image is an UKI image (e.g. is not vmlinuz))"
+        )]
+        uki: bool,
+        #[arg(long, required = true, help = "Path to the PK certificate")]
+        pk: String,
+        #[arg(long, help = "Path to a KEK certificate file. Can be repeated")]
+        kek: Vec<String>,
+        #[arg(long, help = "Path to a db certificate file. Can be repeated")]
+        db: Vec<String>,
+        #[arg(
+            long = "dbx-hash",
+            value_parser = parse_sha256,
+            help = "Hex encoded sha256 Authenticode hash to forbid in dbx. Can be repeated"
+        )]
+        dbx_hashes: Vec<Vec<u8>>,
+        #[arg(long, required = true, help = "GUID owning the signatures")]
+        owner: uuid::Uuid,
+        #[arg(long, required = true, help = "Directory to write the EFIVar files to")]
+        output: String,
+        #[arg(
+            long = "sbat-policy",
+            value_enum,
+            default_value = "previous",
+            help = "SbatLevel policy shim measures into PCR 7"
+        )]
+        sbat_policy: SbatPolicy,
+    },
+    /// Apply authenticated Secure Boot variable updates (.auth files) and
+    /// compute the resulting PCR 7
+    ApplyAuth {
+        #[arg(
+            long,
+            short,
+            default_value = "/",
+            help = "Path to the target container image root filesystem"
+        )]
+        rootfs: String,
+        #[arg(
+            long,
+            default_value_t = false,
+            help = "Indicates that the linux image is an UKI image (e.g. is not vmlinuz))"
+        )]
+        uki: bool,
+        #[arg(
+            long,
+            required = true,
+            help = "Path to the directory storing EFIVar files"
+        )]
+        efivars: String,
+        #[arg(
+            long = "update",
+            required = true,
+            value_parser = parse_update,
+            help = "Variable and .auth file of an update, as NAME=PATH[:append|:replace]. Updates are appended unless :replace is given, and are applied in order"
+        )]
+        updates: Vec<(String, String, UpdateMode)>,
+        #[arg(
+            long,
+            required = true,
+            help = "Directory to write the updated EFIVar files to"
+        )]
+        output: String,
+        #[arg(
+            long = "sbat-policy",
+            value_enum,
+            default_value = "previous",
+            help = "SbatLevel policy shim measures into PCR 7"
+        )]
+        sbat_policy: SbatPolicy,
+    },
+    /// Check the boot chain images against dbx, MokListX and shim's
+    /// vendor_dbx, and report the revoked ones
+    Revocations {
+        #[arg(
+            long,
+            short,
+            default_value = "/",
+            help = "Path to the target container image root filesystem"
+        )]
+        rootfs: String,
+        #[command(flatten)]
+        secureboot_variables: SecureBootVarStores,
+        #[arg(
+            long,
+            default_value_t = false,
+            help = "Indicates that the linux image is an UKI image (e.g. is not vmlinuz))"
+        )]
+        uki: bool,
+        #[arg(
+            long = "mok-variables",
+            help = "Path to directory storing MokListXRT. MokListX is assumed empty if not given"
+        )]
+        mok_variables: Option<String>,
+    },
+    /// Check the SBAT sections of the boot chain images against SbatLevel,
+    /// and report the images shim would refuse to load
+    Sbat {
+        #[arg(
+            long,
+            short,
+            default_value = "/",
+            help = "Path to the target container image root filesystem"
+        )]
+        rootfs: String,
+        #[arg(
+            long,
+            default_value_t = false,
+            help = "Indicates that the linux image is an UKI image (e.g. is not vmlinuz))"
+        )]
+        uki: bool,
+        #[arg(
+            long = "sbat-policy",
+            value_enum,
+            default_value = "previous",
+            help = "SbatLevel policy to check the images against"
+        )]
+        sbat_policy: SbatPolicy,
+        #[arg(
+            long,
+            required_if_eq("sbat_policy", "efivars"),
+            help = "Path to the directory storing EFIVar files"
+        )]
+        efivars: Option<String>,
+    },
+    /// Verify a TPM2 quote and find which predicted PCR set it attests
+    VerifyQuote {
+        #[arg(long, help = "Path to the quoted TPMS_ATTEST (tpm2_quote -m)")]
+        message: String,
+        #[arg(long, help = "Path to the quote TPMT_SIGNATURE (tpm2_quote -s)")]
+        signature: String,
+        #[arg(long = "ak-public", help = "Path to the PEM public key of the AK")]
+        ak_public: String,
+        #[arg(long, help = "Hex encoded nonce expected in the quote extraData")]
+        nonce: Option<String>,
+        /// Paths to the output of the all or combine commands holding the
+        /// predicted PCRs
+        #[arg(required = true)]
+        candidates: Vec<String>,
+    },
 }
 
 #[derive(Serialize, Deserialize)]
@@ -127,6 +483,102 @@ struct Output {
     pcrs: Vec<Pcr>,
 }
 
+/// Predicted PCRs, from either the all or the combine commands
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Predicted {
+    Image(Output),
+    Combined(Vec<combine::CombinedPcrs>),
+}
+
+/// Predicted PCR set attested by a quote
+#[derive(Serialize)]
+struct QuoteMatchOutput<'a> {
+    file: &'a str,
+    /// Index of the PCR set in the output of the combine command
+    #[serde(skip_serializing_if = "Option::is_none")]
+    index: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    provenance: Option<&'a combine::Provenance>,
+    pcr_ids: Vec<u64>,
+}
+
+fn output_pcr(pcr: Pcr, event_data: bool) -> Pcr {
+    if event_data {
+        pcr
+    } else {
+        pcr.without_event_data()
+    }
+}
+
+/// Loads the events of the images from files holding the output of the all
+/// command
+fn load_images(paths: &[String]) -> Result<Vec<combine::LabeledImage>> {
+    paths
+        .iter()
+        .map(|path| {
+            let output: Output = serde_json::from_str(&fs::read_to_string(path)?)?;
+            let events = output.pcrs.into_iter().flat_map(|p| p.events).collect();
+            Ok(combine::LabeledImage::new(path, events))
+        })
+        .collect()
+}
+
+/// Reads the certificates of PEM or DER files, DER encoded
+fn read_certificates(paths: &[String]) -> Result<Vec<Vec<u8>>> {
+    let mut certs = vec![];
+    for path in paths {
+        certs.extend(
+            certs::read_certificates(&fs::read(path)?)
+                .map_err(|e| anyhow::anyhow!("{path}: {e}"))?,
+        );
+    }
+    Ok(certs)
+}
+
+/// Parses a hex encoded sha256 digest argument
+fn parse_sha256(arg: &str) -> Result<Vec<u8>, String> {
+    let digest = hex::decode(arg).map_err(|e| format!("{arg}: {e}"))?;
+    if digest.len() != 32 {
+        return Err(format!(
+            "{arg} is {} bytes long, a sha256 digest is 32",
+            digest.len()
+        ));
+    }
+    Ok(digest)
+}
+
+/// Parses a NAME=PATH[:MODE] variable update argument. A suffix that is
+/// not an update mode is part of the path.
+fn parse_update(arg: &str) -> Result<(String, String, UpdateMode), String> {
+    let (name, path) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("{arg} is not NAME=PATH[:MODE]"))?;
+    let (path, mode) = path
+        .rsplit_once(':')
+        .and_then(|(path, mode)| Some((path, UpdateMode::from_str(mode, true).ok()?)))
+        .unwrap_or((path, UpdateMode::Append));
+    Ok((name.to_string(), path.to_string(), mode))
+}
+
+/// Finds the boot chain images that would be refused by the Secure Boot
+/// forbidden signature databases
+fn revoked_images(
+    rfs: &rootfs::RootFSTree,
+    efivars: Option<&str>,
+    uki: bool,
+    mok_variables: Option<&str>,
+) -> Result<Vec<revocation::RevokedImage>> {
+    let efivars = efivars.expect("No efivars directory path provided");
+    let kernels_dir = (!uki).then(|| rfs.vmlinuz());
+    Ok(revocation::check_boot_chain(
+        rfs.esp(),
+        kernels_dir,
+        efivars,
+        mok_variables,
+    )?)
+}
+
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
@@ -148,19 +600,40 @@ fn main() -> Result<()> {
             secureboot_variables,
             uki,
             no_secureboot,
+            sbat_policy,
             mok_variables,
         } => {
             let rfs = rootfs::RootFSTree::new(rootfs).unwrap();
+            if !no_secureboot {
+                let revoked = revoked_images(
+                    &rfs,
+                    secureboot_variables.efivars.as_deref(),
+                    *uki,
+                    Some(mok_variables),
+                )?;
+                for r in &revoked {
+                    warn!("{} is revoked by {}: {:?}", r.image, r.database, r.reason);
+                }
+                if !revoked.is_empty() {
+                    anyhow::bail!("The boot chain contains revoked images");
+                }
+            }
             let pcrs = vec![
-                compute_pcr4(rfs.vmlinuz(), rfs.esp(), *uki, !no_secureboot),
+                compute_pcr4(rfs.vmlinuz(), rfs.esp(), *uki, !no_secureboot)?,
                 compute_pcr7(
                     secureboot_variables.efivars.as_deref(),
+                    Some(mok_variables),
                     rfs.esp(),
+                    (!uki).then(|| rfs.vmlinuz()),
                     !no_secureboot,
-                ),
+                    &(*sbat_policy).into(),
+                )?,
                 /* compute_pcr11(), */
                 compute_pcr14(mok_variables),
-            ];
+            ]
+            .into_iter()
+            .map(|pcr| output_pcr(pcr, cli.event_data))
+            .collect();
             println!(
                 "{}",
                 serde_json::to_string_pretty(&Output { pcrs }).unwrap()
@@ -173,33 +646,300 @@ fn main() -> Result<()> {
             no_secureboot,
         } => {
             let rfs = rootfs::RootFSTree::new(rootfs).unwrap();
-            let pcr = compute_pcr4(rfs.vmlinuz(), rfs.esp(), *uki, !no_secureboot);
+            let pcr = output_pcr(
+                compute_pcr4(rfs.vmlinuz(), rfs.esp(), *uki, !no_secureboot)?,
+                cli.event_data,
+            );
             println!("{}", serde_json::to_string_pretty(&pcr).unwrap());
             Ok(())
         }
         Command::Pcr7 {
             rootfs,
+            uki,
             secureboot_variables,
             no_secureboot,
+            sbat_policy,
+            mok_variables,
         } => {
             let rfs = rootfs::RootFSTree::new(rootfs).unwrap();
-            let pcr = compute_pcr7(
-                secureboot_variables.efivars.as_deref(),
-                rfs.esp(),
-                !no_secureboot,
+            let pcr = output_pcr(
+                compute_pcr7(
+                    secureboot_variables.efivars.as_deref(),
+                    mok_variables.as_deref(),
+                    rfs.esp(),
+                    (!uki).then(|| rfs.vmlinuz()),
+                    !no_secureboot,
+                    &(*sbat_policy).into(),
+                )?,
+                cli.event_data,
             );
             println!("{}", serde_json::to_string_pretty(&pcr).unwrap());
             Ok(())
         }
         Command::Pcr11 { uki } => {
-            let pcr = compute_pcr11(uki);
+            let pcr = output_pcr(compute_pcr11(uki), cli.event_data);
             println!("{}", serde_json::to_string_pretty(&pcr).unwrap());
             Ok(())
         }
         Command::Pcr14 { mok_variables } => {
-            let pcr = compute_pcr14(mok_variables);
+            let pcr = output_pcr(compute_pcr14(mok_variables), cli.event_data);
             println!("{}", serde_json::to_string_pretty(&pcr).unwrap());
             Ok(())
         }
+        Command::KeylimeRefstate {
+            rootfs,
+            secureboot_variables,
+            uki,
+            no_secureboot,
+            sbat_policy,
+            mok_variables,
+            initrd,
+            kernel_cmdline,
+        } => {
+            let rfs = rootfs::RootFSTree::new(rootfs).unwrap();
+            let efivars = secureboot_variables
+                .efivars
+                .as_deref()
+                .expect("No efivars directory path provided");
+            let events = [
+                tpmevents::compute::pcr4_events(rfs.vmlinuz(), rfs.esp(), *uki, !no_secureboot)?,
+                tpmevents::compute::pcr7_events(
+                    efivars,
+                    Some(mok_variables),
+                    rfs.esp(),
+                    (!uki).then(|| rfs.vmlinuz()),
+                    !no_secureboot,
+                    &(*sbat_policy).into(),
+                )?,
+                tpmevents::compute::pcr14_events(mok_variables),
+            ]
+            .concat();
+            let initrd_sha256 = openssl::sha::sha256(&fs::read(initrd)?);
+            let refstate = keylime::mb_refstate(&events, efivars, &initrd_sha256, kernel_cmdline)?;
+            println!("{}", serde_json::to_string_pretty(&refstate).unwrap());
+            Ok(())
+        }
+        Command::Combine {
+            images,
+            max_branches,
+            dump_tree,
+        } => {
+            let images = load_images(images)?;
+            let options = combine::CombineOptions {
+                max_branches: *max_branches,
+                ..Default::default()
+            };
+            if let Some(format) = dump_tree {
+                eprint!(
+                    "{}",
+                    combine::render_event_trees(&images, &options, (*format).into())?
+                );
+            }
+            let combined = combine::combine_labeled_images(&images, &options)?;
+            println!("{}", serde_json::to_string_pretty(&combined).unwrap());
+            Ok(())
+        }
+        Command::ClevisPin { image, pcr_ids } => {
+            let output: Output = serde_json::from_str(&fs::read_to_string(image)?)?;
+            let pin = clevis::tpm2_pin(&output.pcrs, pcr_ids)?;
+            println!("{}", serde_json::to_string(&pin).unwrap());
+            Ok(())
+        }
+        Command::Cel { image, cbor } => {
+            let output: Output = serde_json::from_str(&fs::read_to_string(image)?)?;
+            let events: Vec<_> = output.pcrs.into_iter().flat_map(|p| p.events).collect();
+            if *cbor {
+                io::stdout().write_all(&tpmevents::cel::to_cel_cbor(&events)?)?;
+            } else {
+                println!("{}", tpmevents::cel::to_cel_json(&events)?);
+            }
+            Ok(())
+        }
+        Command::EventLog { image } => {
+            let output: Output = serde_json::from_str(&fs::read_to_string(image)?)?;
+            let events: Vec<_> = output.pcrs.into_iter().flat_map(|p| p.events).collect();
+            io::stdout().write_all(&tpmevents::eventlog::event_log(&events)?)?;
+            Ok(())
+        }
+        Command::ImportEventlog { log } => {
+            let events = tpmevents::tpm2_eventlog::from_tpm2_eventlog(&fs::read_to_string(log)?)?;
+            let mut pcrs = pcrs::compile_pcrs(&events);
+            pcrs.sort_by_key(|p| p.id);
+            let pcrs = pcrs
+                .into_iter()
+                .map(|pcr| output_pcr(pcr, cli.event_data))
+                .collect();
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&Output { pcrs }).unwrap()
+            );
+            Ok(())
+        }
+        Command::RecoverVariables {
+            log,
+            efivars,
+            mok_variables,
+        } => {
+            let entries = tpmevents::eventlog::parse_event_log(&fs::read(log)?)?;
+            let recovered = uefi::recover::recover_variables(&entries);
+            for var in recovered.missing() {
+                warn!("{var} could not be recovered from the event log");
+            }
+            recovered.write(efivars.as_ref(), mok_variables.as_ref())?;
+            Ok(())
+        }
+        Command::SecureBootKeys {
+            rootfs,
+            uki,
+            pk,
+            kek,
+            db,
+            dbx_hashes,
+            owner,
+            output,
+            sbat_policy,
+        } => {
+            let rfs = rootfs::RootFSTree::new(rootfs).unwrap();
+            let [pk] = read_certificates(std::slice::from_ref(pk))?
+                .try_into()
+                .map_err(|certs: Vec<_>| {
+                    anyhow::anyhow!("{pk}: expected one certificate, found {}", certs.len())
+                })?;
+            let variables = uefi::efivars::SecureBootVariables::from_keys(
+                *owner,
+                &pk,
+                &read_certificates(kek)?,
+                &read_certificates(db)?,
+                dbx_hashes,
+            )?;
+            variables.write(output.as_ref())?;
+            let pcr = output_pcr(
+                compute_pcr7(
+                    Some(output),
+                    None,
+                    rfs.esp(),
+                    (!uki).then(|| rfs.vmlinuz()),
+                    true,
+                    &(*sbat_policy).into(),
+                )?,
+                cli.event_data,
+            );
+            println!("{}", serde_json::to_string_pretty(&pcr).unwrap());
+            Ok(())
+        }
+        Command::ApplyAuth {
+            rootfs,
+            uki,
+            efivars,
+            updates,
+            output,
+            sbat_policy,
+        } => {
+            let rfs = rootfs::RootFSTree::new(rootfs).unwrap();
+            let mut variables = uefi::efivars::SecureBootVariables::load(efivars);
+            for (name, path, mode) in updates {
+                uefi::authvar::AuthenticatedUpdate::parse(&fs::read(path)?)
+                    .and_then(|update| variables.apply(name, &update, (*mode).into()))
+                    .map_err(|e| anyhow::anyhow!("{path}: {e}"))?;
+            }
+            uefi::efivars::copy_efivars(efivars.as_ref(), output.as_ref())?;
+            variables.write(output.as_ref())?;
+            let pcr = output_pcr(
+                compute_pcr7(
+                    Some(output),
+                    None,
+                    rfs.esp(),
+                    (!uki).then(|| rfs.vmlinuz()),
+                    true,
+                    &(*sbat_policy).into(),
+                )?,
+                cli.event_data,
+            );
+            println!("{}", serde_json::to_string_pretty(&pcr).unwrap());
+            Ok(())
+        }
+        Command::Revocations {
+            rootfs,
+            secureboot_variables,
+            uki,
+            mok_variables,
+        } => {
+            let rfs = rootfs::RootFSTree::new(rootfs).unwrap();
+            let revoked = revoked_images(
+                &rfs,
+                secureboot_variables.efivars.as_deref(),
+                *uki,
+                mok_variables.as_deref(),
+            )?;
+            println!("{}", serde_json::to_string_pretty(&revoked).unwrap());
+            if !revoked.is_empty() {
+                anyhow::bail!("{} revoked boot chain images found", revoked.len());
+            }
+            Ok(())
+        }
+        Command::Sbat {
+            rootfs,
+            uki,
+            sbat_policy,
+            efivars,
+        } => {
+            let rfs = rootfs::RootFSTree::new(rootfs).unwrap();
+            let sbat_level =
+                sbat::shim_sbat_level(rfs.esp(), efivars.as_deref(), &(*sbat_policy).into())?;
+            let kernels_dir = (!uki).then(|| rfs.vmlinuz());
+            let rejected = sbat::check_boot_chain(rfs.esp(), kernels_dir, &sbat_level)?;
+            println!("{}", serde_json::to_string_pretty(&rejected).unwrap());
+            if !rejected.is_empty() {
+                anyhow::bail!(
+                    "{} boot chain images would be rejected by SBAT",
+                    rejected.len()
+                );
+            }
+            Ok(())
+        }
+        Command::VerifyQuote {
+            message,
+            signature,
+            ak_public,
+            nonce,
+            candidates,
+        } => {
+            let ak = openssl::pkey::PKey::public_key_from_pem(&fs::read(ak_public)?)?;
+            let nonce = nonce.as_deref().map(hex::decode).transpose()?;
+            // Flatten the PCR sets of all files, remembering where each one
+            // came from
+            let mut origins = vec![];
+            let mut pcr_sets = vec![];
+            for path in candidates {
+                match serde_json::from_str(&fs::read_to_string(path)?)? {
+                    Predicted::Image(output) => {
+                        origins.push((path.as_str(), None, None));
+                        pcr_sets.push(output.pcrs);
+                    }
+                    Predicted::Combined(combined) => {
+                        for (index, c) in combined.into_iter().enumerate() {
+                            origins.push((path.as_str(), Some(index), Some(c.provenance)));
+                            pcr_sets.push(c.pcrs);
+                        }
+                    }
+                }
+            }
+            let quote_match = quote::verify_quote(
+                &fs::read(message)?,
+                &fs::read(signature)?,
+                &ak,
+                nonce.as_deref(),
+                &pcr_sets,
+            )?;
+            let (file, index, provenance) = &origins[quote_match.candidate];
+            let output = QuoteMatchOutput {
+                file,
+                index: *index,
+                provenance: provenance.as_ref(),
+                pcr_ids: quote_match.pcr_ids,
+            };
+            println!("{}", serde_json::to_string_pretty(&output).unwrap());
+            Ok(())
+        }
     }
 }
@@ -0,0 +1,387 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+
+//! Authenticode hashing of PE/COFF images, as firmware and shim measure
+//! them into PCR 4.
+//!
+//! The image hash follows the Windows Authenticode PE specification: the
+//! headers without the checksum and the certificate table data directory,
+//! then the sections ordered by their file offset, then any data between
+//! the last section and the certificate table. The digest signed in each
+//! PKCS#7 signature of the certificate table is read as well, so the
+//! computed hash can be checked against what the signer hashed, and the
+//! signatures can be verified against the certificates firmware and shim
+//! trust.
+use openssl::hash::{Hasher, MessageDigest};
+use openssl::pkcs7::{Pkcs7, Pkcs7Flags};
+use openssl::stack::Stack;
+use openssl::x509::store::X509StoreBuilder;
+use openssl::x509::verify::X509VerifyFlags;
+use openssl::x509::{X509PurposeId, X509Ref};
+use std::fmt;
+
+use crate::der::{
+    Der, TAG_CONTEXT_0, TAG_INTEGER, TAG_OCTET_STRING, TAG_OID, TAG_SEQUENCE, TAG_SET,
+};
+
+pub(crate) const PE_SIGNATURE: &[u8; 4] = b"PE\0\0";
+pub(crate) const PE_OFFSET_OFFSET: usize = 0x3c;
+const COFF_HEADER_SIZE: usize = 20;
+pub(crate) const SECTION_HEADER_SIZE: usize = 40;
+const OPTIONAL_HEADER_MAGIC_PE32: u16 = 0x10b;
+pub(crate) const OPTIONAL_HEADER_MAGIC_PE32_PLUS: u16 = 0x20b;
+const CERTIFICATE_TABLE_DIRECTORY: usize = 4;
+pub(crate) const WIN_CERT_TYPE_PKCS_SIGNED_DATA: u16 = 0x0002;
+pub(crate) const WIN_CERTIFICATE_HEADER_SIZE: usize = 8;
+
+// DER encoded OIDs of the digest algorithms of Authenticode signatures
+const OID_SHA1: &[u8] = &[0x2b, 0x0e, 0x03, 0x02, 0x1a];
+pub(crate) const OID_SHA256: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+const OID_SHA384: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02];
+const OID_SHA512: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AuthenticodeError {
+    /// The image is not a well formed PE/COFF image
+    Parse(String),
+    /// The signature is not a well formed Authenticode signature
+    Signature(String),
+    /// The signature uses a digest algorithm that is not supported
+    UnsupportedAlgorithm,
+    /// The digest embedded in a signature is not the hash of the image
+    DigestMismatch {
+        signed: Vec<u8>,
+        computed: Vec<u8>,
+    },
+    Openssl(String),
+}
+
+impl fmt::Display for AuthenticodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AuthenticodeError::Parse(e) => write!(f, "Error parsing PE image: {e}"),
+            AuthenticodeError::Signature(e) => {
+                write!(f, "Error parsing Authenticode signature: {e}")
+            }
+            AuthenticodeError::UnsupportedAlgorithm => {
+                write!(f, "Unsupported Authenticode digest algorithm")
+            }
+            AuthenticodeError::DigestMismatch { signed, computed } => write!(
+                f,
+                "Signed digest {} does not match the image hash {}",
+                hex::encode(signed),
+                hex::encode(computed)
+            ),
+            AuthenticodeError::Openssl(e) => write!(f, "OpenSSL error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for AuthenticodeError {}
+
+impl From<openssl::error::ErrorStack> for AuthenticodeError {
+    fn from(e: openssl::error::ErrorStack) -> Self {
+        AuthenticodeError::Openssl(e.to_string())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DigestAlgorithm {
+    Sha1,
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl DigestAlgorithm {
+    fn from_oid(oid: &[u8]) -> Option<DigestAlgorithm> {
+        match oid {
+            OID_SHA1 => Some(DigestAlgorithm::Sha1),
+            OID_SHA256 => Some(DigestAlgorithm::Sha256),
+            OID_SHA384 => Some(DigestAlgorithm::Sha384),
+            OID_SHA512 => Some(DigestAlgorithm::Sha512),
+            _ => None,
+        }
+    }
+
+    fn message_digest(&self) -> MessageDigest {
+        match self {
+            DigestAlgorithm::Sha1 => MessageDigest::sha1(),
+            DigestAlgorithm::Sha256 => MessageDigest::sha256(),
+            DigestAlgorithm::Sha384 => MessageDigest::sha384(),
+            DigestAlgorithm::Sha512 => MessageDigest::sha512(),
+        }
+    }
+}
+
+/// Digest of the image embedded in an Authenticode signature
+#[derive(Clone, Debug, PartialEq)]
+pub struct SignedDigest {
+    pub algorithm: DigestAlgorithm,
+    pub digest: Vec<u8>,
+}
+
+/// Offsets of the parts of a PE image Authenticode hashes
+struct PeLayout {
+    checksum_offset: usize,
+    cert_directory_offset: Option<usize>,
+    size_of_headers: usize,
+    /// File offset and size of the raw data of each section
+    sections: Vec<(usize, usize)>,
+    /// File offset and size of the certificate table, if any
+    cert_table: Option<(usize, usize)>,
+}
+
+fn parse_error(msg: &str) -> AuthenticodeError {
+    AuthenticodeError::Parse(msg.into())
+}
+
+fn u16_at(image: &[u8], offset: usize) -> Result<u16, AuthenticodeError> {
+    image
+        .get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| parse_error("truncated headers"))
+}
+
+fn u32_at(image: &[u8], offset: usize) -> Result<usize, AuthenticodeError> {
+    image
+        .get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()) as usize)
+        .ok_or_else(|| parse_error("truncated headers"))
+}
+
+impl PeLayout {
+    fn parse(image: &[u8]) -> Result<PeLayout, AuthenticodeError> {
+        let pe_offset = u32_at(image, PE_OFFSET_OFFSET)?;
+        if image.get(pe_offset..pe_offset + 4) != Some(PE_SIGNATURE) {
+            return Err(parse_error("no PE signature"));
+        }
+        let coff = pe_offset + 4;
+        let number_of_sections = u16_at(image, coff + 2)? as usize;
+        let size_of_optional_header = u16_at(image, coff + 16)? as usize;
+        let optional = coff + COFF_HEADER_SIZE;
+
+        // CheckSum is at the same offset for PE32 and PE32+, the data
+        // directories start after the larger PE32+ stack and heap fields
+        let (number_of_rva_offset, directories) = match u16_at(image, optional)? {
+            OPTIONAL_HEADER_MAGIC_PE32 => (optional + 92, optional + 96),
+            OPTIONAL_HEADER_MAGIC_PE32_PLUS => (optional + 108, optional + 112),
+            _ => return Err(parse_error("unknown optional header magic")),
+        };
+        // Images with fewer data directories have no certificate table, so
+        // they are unsigned
+        let cert_directory_offset = (u32_at(image, number_of_rva_offset)?
+            > CERTIFICATE_TABLE_DIRECTORY)
+            .then_some(directories + CERTIFICATE_TABLE_DIRECTORY * 8);
+        let cert_table = match cert_directory_offset {
+            Some(directory) => match (u32_at(image, directory)?, u32_at(image, directory + 4)?) {
+                (_, 0) => None,
+                (offset, size) if offset.checked_add(size).is_some_and(|e| e <= image.len()) => {
+                    Some((offset, size))
+                }
+                _ => return Err(parse_error("certificate table out of the image")),
+            },
+            None => None,
+        };
+
+        let section_table = optional + size_of_optional_header;
+        let mut sections = vec![];
+        for i in 0..number_of_sections {
+            let header = section_table + i * SECTION_HEADER_SIZE;
+            let size = u32_at(image, header + 16)?;
+            let offset = u32_at(image, header + 20)?;
+            if size == 0 {
+                continue;
+            }
+            if offset.checked_add(size).is_none_or(|e| e > image.len()) {
+                return Err(parse_error("section data out of the image"));
+            }
+            sections.push((offset, size));
+        }
+        sections.sort();
+
+        let size_of_headers = u32_at(image, optional + 60)?;
+        let checksum_offset = optional + 64;
+        let headers_end = cert_directory_offset.unwrap_or(checksum_offset) + 8;
+        if size_of_headers > image.len() || size_of_headers < headers_end {
+            return Err(parse_error("invalid SizeOfHeaders"));
+        }
+
+        Ok(PeLayout {
+            checksum_offset,
+            cert_directory_offset,
+            size_of_headers,
+            sections,
+            cert_table,
+        })
+    }
+}
+
+/// Computes the Authenticode hash of a PE image
+pub fn authenticode_digest(
+    image: &[u8],
+    algorithm: DigestAlgorithm,
+) -> Result<Vec<u8>, AuthenticodeError> {
+    let layout = PeLayout::parse(image)?;
+    let mut hasher = Hasher::new(algorithm.message_digest())?;
+    hasher.update(&image[..layout.checksum_offset])?;
+    match layout.cert_directory_offset {
+        Some(directory) => {
+            hasher.update(&image[layout.checksum_offset + 4..directory])?;
+            hasher.update(&image[directory + 8..layout.size_of_headers])?;
+        }
+        None => hasher.update(&image[layout.checksum_offset + 4..layout.size_of_headers])?,
+    }
+    let mut sum_of_bytes_hashed = layout.size_of_headers;
+    for (offset, size) in &layout.sections {
+        hasher.update(&image[*offset..offset + size])?;
+        sum_of_bytes_hashed += size;
+    }
+    // Data after the sections is hashed, up to the certificate table
+    let end = layout
+        .cert_table
+        .map_or(image.len(), |(_, size)| image.len() - size);
+    if end > sum_of_bytes_hashed {
+        hasher.update(&image[sum_of_bytes_hashed..end])?;
+    }
+
+    Ok(hasher.finish()?.to_vec())
+}
+
+/// PKCS#7 SignedData blobs of the image certificate table
+pub fn signatures(image: &[u8]) -> Result<Vec<&[u8]>, AuthenticodeError> {
+    let Some((mut offset, size)) = PeLayout::parse(image)?.cert_table else {
+        return Ok(vec![]);
+    };
+    let end = offset + size;
+    let mut signatures = vec![];
+    // WIN_CERTIFICATE entries are 8 byte aligned
+    while offset + WIN_CERTIFICATE_HEADER_SIZE <= end {
+        let length = u32_at(image, offset)?;
+        if length < WIN_CERTIFICATE_HEADER_SIZE || offset + length > end {
+            return Err(parse_error("invalid WIN_CERTIFICATE length"));
+        }
+        if u16_at(image, offset + 6)? == WIN_CERT_TYPE_PKCS_SIGNED_DATA {
+            signatures.push(&image[offset + WIN_CERTIFICATE_HEADER_SIZE..offset + length]);
+        }
+        offset += length.next_multiple_of(8);
+    }
+    Ok(signatures)
+}
+
+/// Content of the SpcIndirectDataContent of an Authenticode PKCS#7
+/// SignedData, without its SEQUENCE header. That is what the signature
+/// signs.
+fn indirect_data_content(signature: &[u8]) -> Result<&[u8], AuthenticodeError> {
+    let err = |msg: &str| AuthenticodeError::Signature(msg.into());
+    // ContentInfo { contentType, [0] SignedData }
+    let content_info = Der::new(signature)
+        .next_if(TAG_SEQUENCE)
+        .ok_or_else(|| err("no ContentInfo"))?;
+    let mut content_info = content_info.children();
+    content_info
+        .next_if(TAG_OID)
+        .ok_or_else(|| err("no ContentInfo type"))?;
+    let signed_data = content_info
+        .next_if(TAG_CONTEXT_0)
+        .and_then(|c| c.children().next_if(TAG_SEQUENCE))
+        .ok_or_else(|| err("no SignedData"))?;
+    // SignedData { version, digestAlgorithms, contentInfo, ... }
+    let mut signed_data = signed_data.children();
+    signed_data
+        .next_if(TAG_INTEGER)
+        .ok_or_else(|| err("no SignedData version"))?;
+    signed_data
+        .next_if(TAG_SET)
+        .ok_or_else(|| err("no SignedData digest algorithms"))?;
+    Ok(signed_data
+        .next_if(TAG_SEQUENCE)
+        .and_then(|c| {
+            let mut c = c.children();
+            c.next_if(TAG_OID)?;
+            c.next_if(TAG_CONTEXT_0)
+        })
+        .and_then(|c| c.children().next_if(TAG_SEQUENCE))
+        .ok_or_else(|| err("no SpcIndirectDataContent"))?
+        .content)
+}
+
+/// Reads the image digest of the SpcIndirectDataContent of an Authenticode
+/// PKCS#7 SignedData
+pub fn signed_digest(signature: &[u8]) -> Result<SignedDigest, AuthenticodeError> {
+    let err = |msg: &str| AuthenticodeError::Signature(msg.into());
+    // SpcIndirectDataContent { data, messageDigest DigestInfo }
+    let mut indirect_data = Der::new(indirect_data_content(signature)?);
+    indirect_data
+        .next_if(TAG_SEQUENCE)
+        .ok_or_else(|| err("no SpcAttributeTypeAndOptionalValue"))?;
+    let mut digest_info = indirect_data
+        .next_if(TAG_SEQUENCE)
+        .ok_or_else(|| err("no DigestInfo"))?
+        .children();
+    let oid = digest_info
+        .next_if(TAG_SEQUENCE)
+        .and_then(|alg| alg.children().next_if(TAG_OID))
+        .ok_or_else(|| err("no digest algorithm"))?;
+    let digest = digest_info
+        .next_if(TAG_OCTET_STRING)
+        .ok_or_else(|| err("no digest"))?;
+
+    Ok(SignedDigest {
+        algorithm: DigestAlgorithm::from_oid(oid.content)
+            .ok_or(AuthenticodeError::UnsupportedAlgorithm)?,
+        digest: digest.content.to_vec(),
+    })
+}
+
+/// Checks that the signature was made by a certificate chaining up to the
+/// trusted one, as firmware and shim do when checking an image against a
+/// db, vendor_db or vendor certificate entry.
+///
+/// Like EDK2 and shim, the trusted certificate does not need to be a root
+/// (partial chains are accepted), and certificate validity periods and key
+/// usages are not enforced.
+pub fn verify_signature(signature: &[u8], trusted: &X509Ref) -> Result<bool, AuthenticodeError> {
+    verify_detached(signature, indirect_data_content(signature)?, trusted)
+}
+
+/// Checks a PKCS#7 SignedData over the content against a trusted
+/// certificate, the way EDK2 verifies Authenticode signatures and
+/// authenticated variable updates
+pub(crate) fn verify_detached(
+    signature: &[u8],
+    content: &[u8],
+    trusted: &X509Ref,
+) -> Result<bool, AuthenticodeError> {
+    let p7 = Pkcs7::from_der(signature).map_err(|e| AuthenticodeError::Signature(e.to_string()))?;
+    let mut store = X509StoreBuilder::new()?;
+    store.add_cert(trusted.to_owned())?;
+    store.set_flags(X509VerifyFlags::PARTIAL_CHAIN | X509VerifyFlags::NO_CHECK_TIME)?;
+    store.set_purpose(X509PurposeId::ANY)?;
+    let store = store.build();
+    // The signer and intermediate certificates are taken from the signature
+    let certs = Stack::new()?;
+    Ok(p7
+        .verify(&certs, &store, Some(content), None, Pkcs7Flags::BINARY)
+        .is_ok())
+}
+
+/// Computes the sha256 Authenticode hash of the image and checks that it
+/// matches the digest embedded in each of its signatures
+pub fn verified_authenticode(image: &[u8]) -> Result<Vec<u8>, AuthenticodeError> {
+    for signature in signatures(image)? {
+        let signed = signed_digest(signature)?;
+        let computed = authenticode_digest(image, signed.algorithm)?;
+        if computed != signed.digest {
+            return Err(AuthenticodeError::DigestMismatch {
+                signed: signed.digest,
+                computed,
+            });
+        }
+    }
+    authenticode_digest(image, DigestAlgorithm::Sha256)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,131 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+use super::*;
+use crate::test_support::{
+    CERT_DIRECTORY, CHECKSUM, NUMBER_OF_RVA_AND_SIZES, authenticode_signature, cert,
+    expected_digest, image, key, put, signature,
+};
+use sha2::{Digest, Sha256};
+
+#[test]
+fn test_authenticode_digest() {
+    let unsigned = image(None);
+    let digest = authenticode_digest(&unsigned, DigestAlgorithm::Sha256).unwrap();
+    assert_eq!(digest, expected_digest(&unsigned));
+
+    // The checksum and the certificate table are not hashed
+    let signed = image(Some(&signature(&digest)));
+    assert_eq!(
+        authenticode_digest(&signed, DigestAlgorithm::Sha256).unwrap(),
+        digest
+    );
+    let mut patched = unsigned.clone();
+    put(&mut patched, CHECKSUM, &[0xff; 4]);
+    assert_eq!(
+        authenticode_digest(&patched, DigestAlgorithm::Sha256).unwrap(),
+        digest
+    );
+    put(&mut patched, 0x300, &[0xff; 4]);
+    assert_ne!(
+        authenticode_digest(&patched, DigestAlgorithm::Sha256).unwrap(),
+        digest
+    );
+
+    // Without a certificate table data directory, the image is unsigned and
+    // only the checksum is skipped
+    let mut few_directories = unsigned.clone();
+    put(
+        &mut few_directories,
+        NUMBER_OF_RVA_AND_SIZES,
+        &4u32.to_le_bytes(),
+    );
+    put(&mut few_directories, CERT_DIRECTORY, &[0xff; 8]);
+    assert_eq!(
+        authenticode_digest(&few_directories, DigestAlgorithm::Sha256).unwrap(),
+        Sha256::digest(
+            [
+                &few_directories[..CHECKSUM],
+                &few_directories[CHECKSUM + 4..]
+            ]
+            .concat()
+        )
+        .to_vec()
+    );
+    assert!(signatures(&few_directories).unwrap().is_empty());
+
+    assert!(matches!(
+        authenticode_digest(&unsigned[..0x100], DigestAlgorithm::Sha256),
+        Err(AuthenticodeError::Parse(_))
+    ));
+    let mut not_pe = unsigned.clone();
+    put(&mut not_pe, 0x40, b"MZ\0\0");
+    assert!(matches!(
+        authenticode_digest(&not_pe, DigestAlgorithm::Sha256),
+        Err(AuthenticodeError::Parse(_))
+    ));
+}
+
+#[test]
+fn test_verified_authenticode() {
+    let digest = expected_digest(&image(None));
+    let signed = image(Some(&signature(&digest)));
+
+    assert_eq!(signatures(&signed).unwrap(), vec![signature(&digest)]);
+    assert_eq!(
+        signed_digest(&signature(&digest)),
+        Ok(SignedDigest {
+            algorithm: DigestAlgorithm::Sha256,
+            digest: digest.clone(),
+        })
+    );
+    assert_eq!(verified_authenticode(&signed), Ok(digest.clone()));
+    assert_eq!(verified_authenticode(&image(None)), Ok(digest.clone()));
+
+    let tampered = image(Some(&signature(&[0; 32])));
+    assert_eq!(
+        verified_authenticode(&tampered),
+        Err(AuthenticodeError::DigestMismatch {
+            signed: vec![0; 32],
+            computed: digest,
+        })
+    );
+    assert!(matches!(
+        signed_digest(&[TAG_SEQUENCE, 0]),
+        Err(AuthenticodeError::Signature(_))
+    ));
+}
+
+#[test]
+fn test_verify_signature() {
+    let (ca_key, signer_key, other_key) = (key(), key(), key());
+    let ca = cert("Secure Boot CA", &ca_key, None);
+    let signer = cert("Secure Boot Signer", &signer_key, Some((&ca, &ca_key)));
+    // Same subject as the CA, different key
+    let look_alike = cert("Secure Boot CA", &other_key, None);
+
+    let digest = expected_digest(&image(None));
+    let signed = image(Some(&authenticode_signature(&digest, &signer, &signer_key)));
+    assert_eq!(verified_authenticode(&signed), Ok(digest));
+    let signature = signatures(&signed).unwrap()[0];
+
+    assert_eq!(verify_signature(signature, &ca), Ok(true));
+    // Trusting the signer itself is a partial chain
+    assert_eq!(verify_signature(signature, &signer), Ok(true));
+    assert_eq!(verify_signature(signature, &look_alike), Ok(false));
+
+    // The signature does not cover a modified SpcIndirectDataContent
+    let forged = authenticode_signature(&[0; 32], &signer, &signer_key);
+    let mut tampered = signature.to_vec();
+    let at = tampered
+        .windows(32)
+        .position(|w| w == expected_digest(&image(None)))
+        .unwrap();
+    tampered[at..at + 32].copy_from_slice(&[0; 32]);
+    assert_eq!(verify_signature(&tampered, &ca), Ok(false));
+    // A valid signature over another image digest
+    assert!(matches!(
+        verified_authenticode(&image(Some(&forged))),
+        Err(AuthenticodeError::DigestMismatch { .. })
+    ));
+}
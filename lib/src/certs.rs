@@ -3,14 +3,19 @@
 //
 // SPDX-License-Identifier: MIT
 
-use crate::uefi::{EFI_CERT_TYPE_X509_GUID, guid_to_le_bytes};
+use crate::uefi::sigdb::{SignatureDatabase, SignatureDatabaseError, SignatureType};
 use std::fmt;
 
+const SHA256_SIZE: usize = 32;
+
 #[derive(Debug)]
 pub struct X509Cert {
     pub issuer: String,
     pub subject: String,
+    /// Bytes logged when the certificate authorizes an image: the whole
+    /// EFI_SIGNATURE_DATA for certificates of signature databases
     pub raw: Vec<u8>,
+    pub cert: openssl::x509::X509,
 }
 
 impl X509Cert {
@@ -20,11 +25,25 @@ impl X509Cert {
             issuer: cert_issuer(&cert),
             subject: cert_subject(&cert),
             raw: data.to_vec(),
+            cert,
         })
     }
 }
 
-#[derive(Clone, Debug)]
+/// Reads the certificates of a PEM file, or the certificate of a DER file.
+/// Returns them DER encoded.
+pub fn read_certificates(data: &[u8]) -> Result<Vec<Vec<u8>>, openssl::error::ErrorStack> {
+    if data.starts_with(b"-----BEGIN") {
+        openssl::x509::X509::stack_from_pem(data)?
+            .iter()
+            .map(|cert| cert.to_der())
+            .collect()
+    } else {
+        Ok(vec![openssl::x509::X509::from_der(data)?.to_der()?])
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct CertDbParsingError {
     string: String,
 }
@@ -43,6 +62,24 @@ impl fmt::Display for CertDbParsingError {
     }
 }
 
+impl std::error::Error for CertDbParsingError {}
+
+impl From<SignatureDatabaseError> for CertDbParsingError {
+    fn from(e: SignatureDatabaseError) -> Self {
+        CertDbParsingError::new(&e.to_string())
+    }
+}
+
+/// Finds the first certificate of the db that verifies one of the
+/// signatures, as firmware and shim pick the authority they log in PCR 7.
+/// The signatures must have been checked to be over the image.
+pub(crate) fn find_signer<'a>(signatures: &[&[u8]], db: &'a [X509Cert]) -> Option<&'a X509Cert> {
+    signatures.iter().find_map(|signature| {
+        db.iter()
+            .find(|cert| crate::authenticode::verify_signature(signature, &cert.cert) == Ok(true))
+    })
+}
+
 /// Tries formatting openssl name entries into the entry format that lief
 /// uses for subject and issuer strings.
 fn entry_to_string(entry: &openssl::x509::X509NameEntryRef) -> Option<String> {
@@ -94,55 +131,18 @@ fn cert_issuer(cert: &openssl::x509::X509) -> String {
 }
 
 /// Finds the certificates that UEFI db contains given its raw representation
-/// Returns X509 structures and their raw representation
+/// Returns X509 structures and the raw EFI_SIGNATURE_DATA holding them
 fn get_db_certs_raw(
     data: &[u8],
 ) -> Result<Vec<(openssl::x509::X509, Vec<u8>)>, CertDbParsingError> {
-    let mut certs = Vec::new();
-    let mut offset = 0;
-
-    if data.is_empty() {
-        return Ok(vec![]);
-    }
-
-    while offset < data.len() - 28 {
-        let list_type = &data[offset..offset + 16];
-        let list_size = u32::from_le_bytes(
-            data[offset + 16..offset + 20]
-                .try_into()
-                .expect("Badly hardcoded section size"),
-        ) as usize;
-        let head_size = u32::from_le_bytes(
-            data[offset + 20..offset + 24]
-                .try_into()
-                .expect("Badly hardcoded section size"),
-        ) as usize;
-        let item_size = u32::from_le_bytes(
-            data[offset + 24..offset + 28]
-                .try_into()
-                .expect("Badly hardcoded section size"),
-        ) as usize;
-
-        if offset + list_size > data.len() {
-            return Err(CertDbParsingError::new("Invalid list size"));
-        }
-
-        offset += 28 + head_size;
-        let mut item_offset = 0;
-
-        if list_type == guid_to_le_bytes(&EFI_CERT_TYPE_X509_GUID) {
-            while item_offset < list_size - (head_size + 28) {
-                let item = &data[offset + item_offset..offset + item_offset + item_size];
-                if let Ok(c) = openssl::x509::X509::from_der(&item[16..]) {
-                    certs.push((c, item.into()));
-                }
-                item_offset += item_size;
-            }
-        }
-        offset += list_size - (28 + head_size)
-    }
-
-    Ok(certs)
+    Ok(SignatureDatabase::decode(data)?
+        .signatures()
+        .filter(|(t, _)| *t == SignatureType::X509)
+        .filter_map(|(_, s)| {
+            let cert = openssl::x509::X509::from_der(&s.data).ok()?;
+            Some((cert, s.encode()))
+        })
+        .collect())
 }
 
 // Given the raw representation of a certificate db, it returns a vector
@@ -154,6 +154,48 @@ pub fn get_db_certs(data: &[u8]) -> Result<Vec<X509Cert>, CertDbParsingError> {
             subject: cert_subject(c),
             issuer: cert_issuer(c),
             raw: r.clone(),
+            cert: c.clone(),
         })
         .collect())
 }
+
+/// An entry of a forbidden signature database (dbx, MokListX or shim's
+/// vendor_dbx)
+#[derive(Debug)]
+pub enum Revocation {
+    /// EFI_CERT_SHA256: Authenticode hash of a forbidden image
+    ImageHash(Vec<u8>),
+    /// EFI_CERT_X509: forbidden signing certificate
+    Certificate(X509Cert),
+    /// EFI_CERT_X509_SHA256: sha256 of the TBSCertificate of a forbidden
+    /// certificate. The time of revocation is ignored, as shim does.
+    CertificateTbsHash(Vec<u8>),
+}
+
+/// Parses the entries of a forbidden signature database. Signature types
+/// that cannot forbid a binary, or that are not supported, are skipped.
+pub fn get_revocations(data: &[u8]) -> Result<Vec<Revocation>, CertDbParsingError> {
+    let mut revocations = vec![];
+    for (signature_type, signature) in SignatureDatabase::decode(data)?.signatures() {
+        let revocation = match signature_type {
+            SignatureType::Sha256 => Revocation::ImageHash(signature.data.clone()),
+            SignatureType::X509Sha256 => {
+                let hash = signature
+                    .data
+                    .get(..SHA256_SIZE)
+                    .ok_or_else(|| CertDbParsingError::new("Truncated X509 sha256 entry"))?;
+                Revocation::CertificateTbsHash(hash.to_vec())
+            }
+            SignatureType::X509 => match X509Cert::from_der(&signature.data) {
+                Ok(mut cert) => {
+                    cert.raw = signature.encode();
+                    Revocation::Certificate(cert)
+                }
+                Err(_) => continue,
+            },
+            _ => continue,
+        };
+        revocations.push(revocation);
+    }
+    Ok(revocations)
+}
@@ -0,0 +1,45 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use serde::{Deserialize, Serialize};
+
+use crate::pcrs::{MissingPcrError, Pcr, select_pcrs};
+
+#[cfg(test)]
+mod tests;
+
+const PCR_BANK_SHA256: &str = "sha256";
+
+/// Configuration of the clevis tpm2 pin, as passed to
+/// `clevis luks bind -d <dev> tpm2 '<config>'`
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct Tpm2Pin {
+    pub pcr_bank: String,
+    pub pcr_ids: String,
+    pub pcr_digest: String,
+}
+
+/// Builds the clevis tpm2 pin configuration that seals a secret to the
+/// given PCR values.
+///
+/// Clevis hands the decoded pcr_digest to `tpm2_createpolicy --pcr`, which
+/// expects the values of the selected PCRs concatenated in ascending PCR
+/// order, as `tpm2_pcrread -o` writes them. It is encoded in base64url, as
+/// clevis decodes it with `jose b64 dec`.
+pub fn tpm2_pin(pcrs: &[Pcr], pcr_ids: &[u64]) -> Result<Tpm2Pin, MissingPcrError> {
+    let selected = select_pcrs(pcrs, pcr_ids)?;
+    let values: Vec<u8> = selected.iter().flat_map(|p| p.value.clone()).collect();
+
+    Ok(Tpm2Pin {
+        pcr_bank: PCR_BANK_SHA256.into(),
+        pcr_ids: selected
+            .iter()
+            .map(|p| p.id.to_string())
+            .collect::<Vec<_>>()
+            .join(","),
+        pcr_digest: URL_SAFE_NO_PAD.encode(values),
+    })
+}
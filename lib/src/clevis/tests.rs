@@ -0,0 +1,30 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+use super::*;
+
+#[test]
+fn test_tpm2_pin() {
+    let pcrs = vec![
+        Pcr {
+            id: 7,
+            value: vec![0xfb; 32],
+            events: vec![],
+        },
+        Pcr {
+            id: 4,
+            value: vec![0xff; 32],
+            events: vec![],
+        },
+    ];
+    let pin = tpm2_pin(&pcrs, &[7, 4]).unwrap();
+
+    assert_eq!(pin.pcr_bank, "sha256");
+    assert_eq!(pin.pcr_ids, "4,7");
+    assert_eq!(
+        URL_SAFE_NO_PAD.decode(&pin.pcr_digest).unwrap(),
+        [vec![0xff; 32], vec![0xfb; 32]].concat()
+    );
+    assert!(pin.pcr_digest.starts_with("_____"));
+    assert_eq!(tpm2_pin(&pcrs, &[14]), Err(MissingPcrError { id: 14 }));
+}
@@ -0,0 +1,98 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+
+//! Minimal DER reader, enough to walk the PKCS#7 structures embedded in
+//! signed PE images and authenticated variables.
+
+pub(crate) const TAG_INTEGER: u8 = 0x02;
+pub(crate) const TAG_OCTET_STRING: u8 = 0x04;
+pub(crate) const TAG_OID: u8 = 0x06;
+pub(crate) const TAG_SEQUENCE: u8 = 0x30;
+pub(crate) const TAG_SET: u8 = 0x31;
+pub(crate) const TAG_CONTEXT_0: u8 = 0xa0;
+
+/// A DER encoded tag, length, value triplet
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Tlv<'a> {
+    pub tag: u8,
+    pub content: &'a [u8],
+    /// The whole encoding, including the tag and the length
+    pub raw: &'a [u8],
+}
+
+impl<'a> Tlv<'a> {
+    /// Reader over the elements of a constructed value
+    pub fn children(&self) -> Der<'a> {
+        Der::new(self.content)
+    }
+}
+
+/// Encodes a value with a definite length
+pub(crate) fn encode(tag: u8, content: &[u8]) -> Vec<u8> {
+    let len = content.len().to_be_bytes();
+    let len = &len[len.iter().position(|b| *b != 0).unwrap_or(len.len() - 1)..];
+    let header = if content.len() < 0x80 {
+        vec![tag, content.len() as u8]
+    } else {
+        [&[tag, 0x80 | len.len() as u8], len].concat()
+    };
+    [header.as_slice(), content].concat()
+}
+
+/// Reader over a sequence of DER encoded values
+#[derive(Clone)]
+pub(crate) struct Der<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Der<'a> {
+    pub fn new(buf: &'a [u8]) -> Der<'a> {
+        Der { buf }
+    }
+
+    /// Reads the next value if it has the given tag. Other values are left
+    /// to be read.
+    pub fn next_if(&mut self, tag: u8) -> Option<Tlv<'a>> {
+        let mut rest = self.clone();
+        let tlv = rest.next().filter(|tlv| tlv.tag == tag)?;
+        *self = rest;
+        Some(tlv)
+    }
+}
+
+impl<'a> Iterator for Der<'a> {
+    type Item = Tlv<'a>;
+
+    /// Reads the next value. Only single byte tags and definite lengths of
+    /// up to 4 bytes are supported, as DER and PKCS#7 need no more.
+    fn next(&mut self) -> Option<Tlv<'a>> {
+        let tag = *self.buf.first()?;
+        let first = *self.buf.get(1)? as usize;
+        let (len, header) = if first & 0x80 == 0 {
+            (first, 2)
+        } else {
+            let n = first & 0x7f;
+            if n == 0 || n > 4 {
+                return None;
+            }
+            let len = self
+                .buf
+                .get(2..2 + n)?
+                .iter()
+                .fold(0usize, |acc, b| (acc << 8) | *b as usize);
+            (len, 2 + n)
+        };
+        let end = header.checked_add(len)?;
+        let raw = self.buf.get(..end)?;
+        self.buf = &self.buf[end..];
+        Some(Tlv {
+            tag,
+            content: &raw[header..],
+            raw,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,48 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+use super::*;
+
+#[test]
+fn test_der_reader() {
+    let long = [vec![TAG_OCTET_STRING, 0x81, 0x80], vec![7; 0x80]].concat();
+    let buf = [
+        vec![TAG_SEQUENCE, 0x05, TAG_INTEGER, 0x01, 0x2a, TAG_SET, 0x00],
+        long.clone(),
+    ]
+    .concat();
+    let mut der = Der::new(&buf);
+
+    // A mismatching tag leaves the value to be read
+    assert!(der.next_if(TAG_SET).is_none());
+    let seq = der.next_if(TAG_SEQUENCE).unwrap();
+    let mut children = seq.children();
+    assert_eq!(children.next_if(TAG_INTEGER).unwrap().content, [0x2a]);
+    assert!(children.next_if(TAG_SET).unwrap().content.is_empty());
+    assert!(children.next().is_none());
+    let octets = der.next().unwrap();
+    assert_eq!(octets.content, [7; 0x80]);
+    assert_eq!(octets.raw, long);
+    assert!(der.next().is_none());
+
+    // Truncated
+    assert!(Der::new(&[TAG_SEQUENCE, 0x05, 0x00]).next().is_none());
+    assert!(Der::new(&[TAG_SEQUENCE, 0x80]).next().is_none());
+
+    // A value with another tag is left to be read
+    let mut der = Der::new(&buf);
+    assert!(der.next_if(TAG_SET).is_none());
+    assert_eq!(der.next_if(TAG_SEQUENCE).unwrap().content.len(), 5);
+}
+
+#[test]
+fn test_der_encode() {
+    assert_eq!(encode(TAG_INTEGER, &[0x2a]), [TAG_INTEGER, 0x01, 0x2a]);
+    assert_eq!(encode(TAG_SET, &[]), [TAG_SET, 0x00]);
+    for len in [0x7f, 0x80, 0xff, 0x100, 0x10000] {
+        let encoded = encode(TAG_OCTET_STRING, &vec![7; len]);
+        let tlv = Der::new(&encoded).next().unwrap();
+        assert_eq!(tlv.content.len(), len);
+        assert_eq!(tlv.raw, encoded);
+    }
+}
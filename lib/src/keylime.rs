@@ -0,0 +1,140 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+
+//! Export of computed events as a Keylime measured boot reference state
+//! (mb_refstate), as consumed by Keylime's example measured boot policy.
+//!
+//! The S-CRTM and firmware measurements (PCR 0) are not computed, and have to
+//! be added to the reference state by other means if the policy requires
+//! them. The initrd digest and the kernel command line the policy checks
+//! against PCRs 8 and 9 are taken as inputs.
+
+use serde::{Deserialize, Serialize};
+
+use crate::tpmevents::{TPMEvent, TPMEventID};
+use crate::uefi;
+use crate::uefi::efivars;
+use crate::uefi::sigdb::{SignatureDatabase, SignatureDatabaseError};
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct SignatureEntry {
+    #[serde(rename = "SignatureOwner")]
+    pub signature_owner: String,
+    #[serde(rename = "SignatureData")]
+    pub signature_data: String,
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct Sha256Digest {
+    pub sha256: String,
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct ScrtmAndBios {
+    pub scrtm: String,
+    pub platform_firmware: Vec<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct Kernel {
+    pub shim_authcode_sha256: String,
+    pub grub_authcode_sha256: String,
+    pub kernel_authcode_sha256: String,
+    pub initrd_plain_sha256: String,
+    /// Regular expression the policy matches the kernel command line against
+    pub kernel_cmdline: String,
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct MbRefState {
+    pub has_secureboot: bool,
+    pub scrtm_and_bios: Vec<ScrtmAndBios>,
+    pub pk: Vec<SignatureEntry>,
+    pub kek: Vec<SignatureEntry>,
+    pub db: Vec<SignatureEntry>,
+    pub dbx: Vec<SignatureEntry>,
+    pub mokdig: Vec<Sha256Digest>,
+    pub mokxdig: Vec<Sha256Digest>,
+    pub kernels: Vec<Kernel>,
+}
+
+/// Keylime represents digests and signature data as 0x prefixed hex strings
+fn keylime_hex(data: &[u8]) -> String {
+    format!("0x{}", hex::encode(data))
+}
+
+fn event_digest(events: &[TPMEvent], id: TPMEventID) -> Option<String> {
+    events
+        .iter()
+        .find(|e| e.id == id)
+        .map(|e| keylime_hex(&e.hash))
+}
+
+/// Decodes every signature of a secure boot signature database into Keylime
+/// signature entries, whatever its type: certificates, and the hashes dbx
+/// mostly holds.
+fn signature_entries(db: &[u8]) -> Result<Vec<SignatureEntry>, SignatureDatabaseError> {
+    Ok(SignatureDatabase::decode(db)?
+        .signatures()
+        .map(|(_, signature)| SignatureEntry {
+            signature_owner: signature.owner.to_string(),
+            signature_data: keylime_hex(&signature.data),
+        })
+        .collect())
+}
+
+/// Builds the Keylime measured boot reference state from the events computed
+/// for PCRs 4, 7 and 14, and the secure boot variables stored in efivars.
+/// The kernel entry is only emitted if shim, grub and the kernel were all
+/// measured.
+pub fn mb_refstate(
+    events: &[TPMEvent],
+    efivars_path: &str,
+    initrd_sha256: &[u8],
+    kernel_cmdline: &str,
+) -> Result<MbRefState, SignatureDatabaseError> {
+    let sb_enabled_hash = uefi::get_secureboot_state_event(true).hash();
+    // Loaded in PK, KEK, db, dbx order
+    let mut sb_vars =
+        efivars::EFIVarsLoader::new(efivars_path, efivars::SECURE_BOOT_ATTR_HEADER_LENGTH)
+            .map(|var| signature_entries(var.data()));
+
+    let kernels = match (
+        event_digest(events, TPMEventID::Pcr4Shim),
+        event_digest(events, TPMEventID::Pcr4Grub),
+        event_digest(events, TPMEventID::Pcr4Vmlinuz),
+    ) {
+        (Some(shim), Some(grub), Some(kernel)) => vec![Kernel {
+            shim_authcode_sha256: shim,
+            grub_authcode_sha256: grub,
+            kernel_authcode_sha256: kernel,
+            initrd_plain_sha256: keylime_hex(initrd_sha256),
+            kernel_cmdline: kernel_cmdline.into(),
+        }],
+        _ => vec![],
+    };
+
+    Ok(MbRefState {
+        has_secureboot: events
+            .iter()
+            .any(|e| e.id == TPMEventID::Pcr7SecureBoot && e.hash == sb_enabled_hash),
+        scrtm_and_bios: vec![],
+        pk: sb_vars.next().transpose()?.unwrap_or_default(),
+        kek: sb_vars.next().transpose()?.unwrap_or_default(),
+        db: sb_vars.next().transpose()?.unwrap_or_default(),
+        dbx: sb_vars.next().transpose()?.unwrap_or_default(),
+        mokdig: event_digest(events, TPMEventID::Pcr14MokList)
+            .map(|sha256| Sha256Digest { sha256 })
+            .into_iter()
+            .collect(),
+        mokxdig: event_digest(events, TPMEventID::Pcr14MokListX)
+            .map(|sha256| Sha256Digest { sha256 })
+            .into_iter()
+            .collect(),
+        kernels,
+    })
+}
@@ -0,0 +1,112 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+use super::*;
+use crate::test_support::{OWNER, cert, key, signature_list, temp_dir};
+use crate::uefi::GUID_SECURITY_DATABASE;
+use crate::uefi::sigdb::SignatureType;
+use std::fs;
+
+fn event(id: TPMEventID, hash: Vec<u8>) -> TPMEvent {
+    TPMEvent {
+        event_type: id.event_type(),
+        pcr: 0,
+        hash,
+        id,
+        data: None,
+    }
+}
+
+fn write_var(dir: &std::path::Path, name: &str, data: &[u8]) {
+    let mut var = vec![0, 0, 0, 0];
+    var.extend(data);
+    fs::write(dir.join(format!("{name}-{GUID_SECURITY_DATABASE}")), var).unwrap();
+}
+
+#[test]
+fn test_mb_refstate() {
+    let dir = temp_dir();
+    let efivars_dir = dir.path();
+    let cert = cert("Test db", &key(), None).to_der().unwrap();
+    write_var(
+        efivars_dir,
+        "db",
+        &signature_list(SignatureType::X509, std::slice::from_ref(&cert)),
+    );
+
+    let events = vec![
+        event(TPMEventID::Pcr4Shim, vec![1, 2]),
+        event(TPMEventID::Pcr4Grub, vec![3, 4]),
+        event(TPMEventID::Pcr4Vmlinuz, vec![7, 8]),
+        event(
+            TPMEventID::Pcr7SecureBoot,
+            uefi::get_secureboot_state_event(true).hash(),
+        ),
+        event(TPMEventID::Pcr14MokList, vec![5, 6]),
+    ];
+    let efivars_path = efivars_dir.to_str().unwrap();
+    let refstate = mb_refstate(&events, efivars_path, &[9, 10], "root=.*").unwrap();
+
+    assert_eq!(
+        refstate,
+        MbRefState {
+            has_secureboot: true,
+            scrtm_and_bios: vec![],
+            pk: vec![],
+            kek: vec![],
+            db: vec![SignatureEntry {
+                signature_owner: OWNER.to_string(),
+                signature_data: format!("0x{}", hex::encode(&cert)),
+            }],
+            dbx: vec![],
+            mokdig: vec![Sha256Digest {
+                sha256: "0x0506".into()
+            }],
+            mokxdig: vec![],
+            kernels: vec![Kernel {
+                shim_authcode_sha256: "0x0102".into(),
+                grub_authcode_sha256: "0x0304".into(),
+                kernel_authcode_sha256: "0x0708".into(),
+                initrd_plain_sha256: "0x090a".into(),
+                kernel_cmdline: "root=.*".into(),
+            }],
+        }
+    );
+
+    // Without a measured kernel there is no complete kernel entry
+    let refstate = mb_refstate(&events[..2], efivars_path, &[9, 10], "root=.*").unwrap();
+    assert!(refstate.kernels.is_empty());
+}
+
+#[test]
+fn test_mb_refstate_dbx_hashes() {
+    let dir = temp_dir();
+    let efivars_dir = dir.path();
+    let cert = cert("Test dbx", &key(), None).to_der().unwrap();
+    let hashes = vec![vec![0xaa; 32], vec![0xbb; 32]];
+    let dbx = [
+        signature_list(SignatureType::Sha256, &hashes),
+        signature_list(SignatureType::X509, std::slice::from_ref(&cert)),
+    ]
+    .concat();
+    write_var(efivars_dir, "dbx", &dbx);
+
+    let refstate = mb_refstate(&[], efivars_dir.to_str().unwrap(), &[], "").unwrap();
+    let entry = |data: &[u8]| SignatureEntry {
+        signature_owner: OWNER.to_string(),
+        signature_data: format!("0x{}", hex::encode(data)),
+    };
+    assert_eq!(
+        refstate.dbx,
+        vec![entry(&hashes[0]), entry(&hashes[1]), entry(&cert)]
+    );
+    assert!(refstate.kernels.is_empty());
+}
+
+#[test]
+fn test_mb_refstate_invalid_db() {
+    let dir = temp_dir();
+    let efivars_dir = dir.path();
+    write_var(efivars_dir, "db", &[1, 2, 3]);
+    assert!(mb_refstate(&[], efivars_dir.to_str().unwrap(), &[], "").is_err());
+}
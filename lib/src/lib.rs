@@ -5,20 +5,34 @@
 // SPDX-License-Identifier: MIT
 pub use pcrs::Pcr;
 
+pub mod authenticode;
 pub mod certs;
+pub mod clevis;
+mod der;
 mod esp;
+pub mod keylime;
 mod linux;
 mod mok;
 pub mod pcrs;
 pub mod pefile;
+pub mod quote;
+pub mod revocation;
 pub mod rootfs;
+pub mod sbat;
 pub mod shim;
+#[cfg(test)]
+mod test_support;
 pub mod tpmevents;
 pub mod uefi;
 
-pub fn compute_pcr4(kernels_dir: &str, esp_path: &str, uki: bool, secureboot: bool) -> Pcr {
-    let events = tpmevents::compute::pcr4_events(kernels_dir, esp_path, uki, secureboot);
-    Pcr::compile_from(&events)
+pub fn compute_pcr4(
+    kernels_dir: &str,
+    esp_path: &str,
+    uki: bool,
+    secureboot: bool,
+) -> Result<Pcr, tpmevents::compute::ComputeError> {
+    let events = tpmevents::compute::pcr4_events(kernels_dir, esp_path, uki, secureboot)?;
+    Ok(Pcr::compile_from(&events))
 }
 
 pub fn compute_pcr11(uki: &str) -> Pcr {
@@ -35,20 +49,38 @@ pub fn compute_pcr11(uki: &str) -> Pcr {
 ///    - EV_SEPARATOR
 ///    - EV_EFI_VARIABLE_AUTHORITY: db
 ///    - EV_EFI_VARIABLE_AUTHORITY: SbatLevel
-///    - EV_EFI_VARIABLE_AUTHORITY: MokListRT
+///    - EV_EFI_VARIABLE_AUTHORITY: db, vendor_db or MokListRT, for grub and
+///      vmlinuz
 ///
 /// EFI vars are needed to compute pcr7.
 /// EFI vars can be loaded from
 ///     - efivars
 ///
-pub fn compute_pcr7(efivars_path: Option<&str>, esp_path: &str, secureboot_enabled: bool) -> Pcr {
+/// MokListRT, read from the MOK variables directory if given, provides the
+/// MOKs that may verify the binaries shim loads, besides shim's vendor_cert.
+/// The vmlinuz image of the kernels directory is one of them, if given.
+///
+/// The measured SbatLevel is picked from the shim .sbatlevel section as the
+/// selection says. The original SbatLevel is measured instead if Secure Boot
+/// is disabled or shim has no .sbatlevel section.
+pub fn compute_pcr7(
+    efivars_path: Option<&str>,
+    mok_variables_path: Option<&str>,
+    esp_path: &str,
+    kernels_dir: Option<&str>,
+    secureboot_enabled: bool,
+    sbat_level: &shim::SbatLevelSelection,
+) -> Result<Pcr, tpmevents::compute::ComputeError> {
     let events = tpmevents::compute::pcr7_events(
         efivars_path.expect("No efivars directory path provided"),
+        mok_variables_path,
         esp_path,
+        kernels_dir,
         secureboot_enabled,
-    );
+        sbat_level,
+    )?;
 
-    Pcr::compile_from(&events)
+    Ok(Pcr::compile_from(&events))
 }
 
 pub fn compute_pcr14(mok_variables: &str) -> Pcr {
@@ -3,43 +3,56 @@
 //
 // SPDX-License-Identifier: MIT
 
-use sha2::{Digest, Sha256};
 use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
 
-const MOK_EVENTS_PCR14: [&str; 3] = ["MokList", "MokListX", "MokListTrusted"];
+pub(crate) const MOK_LIST: &str = "MokList";
+pub(crate) const MOK_EVENTS_PCR14: [&str; 3] = ["MokList", "MokListX", "MokListTrusted"];
 
-fn mok_event_to_file_name(event_name: &str) -> String {
+pub(crate) fn mok_event_to_file_name(event_name: &str) -> String {
     format!("{event_name}RT")
 }
 
-fn mok_event_hash(events_dir_path: &Path, event_name: &str) -> Vec<u8> {
-    let data = fs::read(events_dir_path.join(mok_event_to_file_name(event_name))).unwrap();
-    Sha256::digest(data).to_vec()
+/// Reads a MOK variable as shim mirrors it into its RT copy, empty if it does
+/// not exist
+pub(crate) fn load_mok_variable(events_dir_path: &str, event_name: &str) -> io::Result<Vec<u8>> {
+    match fs::read(Path::new(events_dir_path).join(mok_event_to_file_name(event_name))) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(vec![]),
+        res => res,
+    }
+}
+
+fn mok_event_data(events_dir_path: &Path, event_name: &str) -> Vec<u8> {
+    fs::read(events_dir_path.join(mok_event_to_file_name(event_name))).unwrap()
 }
 
+/// Iterates over the contents of the MOK variables shim measures into PCR 14
 #[derive(Debug, Clone)]
-pub struct MokEventHashes {
+pub struct MokEventData {
     /// Path to the directory containing MokList{}RT files
     path: PathBuf,
     index: usize,
 }
 
-impl MokEventHashes {
-    pub fn new(path: &str) -> MokEventHashes {
-        MokEventHashes {
+impl MokEventData {
+    pub fn new(path: &str) -> MokEventData {
+        MokEventData {
             path: path.into(),
             index: 0,
         }
     }
 }
 
-impl Iterator for MokEventHashes {
+impl Iterator for MokEventData {
     type Item = Vec<u8>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let hash = mok_event_hash(&self.path, MOK_EVENTS_PCR14.get(self.index)?);
+        let data = mok_event_data(&self.path, MOK_EVENTS_PCR14.get(self.index)?);
         self.index += 1;
-        Some(hash)
+        Some(data)
     }
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,18 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+use super::*;
+use crate::test_support::temp_dir;
+
+#[test]
+fn test_load_mok_variable() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("MokListRT"), [1, 2, 3]).unwrap();
+    let path = dir.path().to_str().unwrap();
+
+    let mok_list = load_mok_variable(path, MOK_LIST).unwrap();
+    // Variables that are not enrolled are empty
+    let mok_list_x = load_mok_variable(path, "MokListX").unwrap();
+    assert_eq!(mok_list, [1, 2, 3]);
+    assert!(mok_list_x.is_empty());
+}
@@ -8,6 +8,7 @@ use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 use sha2::{Digest, Sha256};
+use std::fmt;
 
 const PCR_INIT_VALUE: [u8; 32] = [
     0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
@@ -55,6 +56,13 @@ impl Pcr {
             events: events.clone(),
         }
     }
+
+    /// Drops the raw data of the events, which can be large, keeping only
+    /// their digests
+    pub fn without_event_data(mut self) -> Pcr {
+        self.events.iter_mut().for_each(|e| e.data = None);
+        self
+    }
 }
 
 /// Supports compiling vectors of PCRs from vectors of events that belong
@@ -68,3 +76,179 @@ pub fn compile_pcrs(events: &[TPMEvent]) -> Vec<Pcr> {
         .map(|n| Pcr::compile_from(&events.iter().filter(|e| e.pcr == *n).cloned().collect()))
         .collect()
 }
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MissingPcrError {
+    pub id: u64,
+}
+
+impl fmt::Display for MissingPcrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PCR {} is not available in the computed PCRs", self.id)
+    }
+}
+
+impl std::error::Error for MissingPcrError {}
+
+/// Selects the PCRs with the given ids in ascending id order, which is the
+/// order TPM PCR selections follow
+pub fn select_pcrs<'a>(pcrs: &'a [Pcr], ids: &[u64]) -> Result<Vec<&'a Pcr>, MissingPcrError> {
+    ids.iter()
+        .sorted()
+        .dedup()
+        .map(|id| {
+            pcrs.iter()
+                .find(|p| p.id == *id)
+                .ok_or(MissingPcrError { id: *id })
+        })
+        .collect()
+}
+
+/// Number of PCRs in a PC Client TPM bank
+pub const PCR_COUNT: u64 = 24;
+// PCRs reset to all ones at TPM2_Startup, extended by the dynamic root of
+// trust for measurement
+const DRTM_PCRS: std::ops::RangeInclusive<u64> = 17..=22;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PcrBankError {
+    /// The PCR index is not part of the bank
+    InvalidPcr(u64),
+    /// The initial value is not a sha256 digest
+    InvalidValueLength(usize),
+}
+
+impl fmt::Display for PcrBankError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PcrBankError::InvalidPcr(id) => {
+                write!(f, "PCR {id} is not in a bank of {PCR_COUNT} PCRs")
+            }
+            PcrBankError::InvalidValueLength(len) => {
+                write!(f, "PCR value of {len} bytes is not a sha256 digest")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PcrBankError {}
+
+/// Value of a PCR in two states of a bank
+#[serde_as]
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct PcrDiff {
+    pub id: u64,
+    #[serde_as(as = "serde_with::hex::Hex")]
+    pub before: Vec<u8>,
+    #[serde_as(as = "serde_with::hex::Hex")]
+    pub after: Vec<u8>,
+}
+
+/// The sha256 bank of a TPM, extended one event at a time.
+///
+/// Unlike compile_pcrs, events of different PCRs can be interleaved in
+/// the order they are logged, and the bank can be inspected after any of
+/// them to verify partial logs.
+#[derive(Clone, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub struct PcrBank {
+    pcrs: Vec<Pcr>,
+}
+
+impl Default for PcrBank {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PcrBank {
+    /// Creates a bank holding the values PCRs have after TPM2_Startup at
+    /// locality 0: DRTM PCRs 17 to 22 are all ones, the rest all zeros.
+    pub fn new() -> PcrBank {
+        let pcrs = (0..PCR_COUNT)
+            .map(|id| Pcr {
+                id,
+                value: if DRTM_PCRS.contains(&id) {
+                    vec![0xff; 32]
+                } else {
+                    PCR_INIT_VALUE.to_vec()
+                },
+                events: vec![],
+            })
+            .collect();
+        PcrBank { pcrs }
+    }
+
+    /// Sets the value of PCR 0 after TPM2_Startup at the given locality.
+    /// Platforms with an H-CRTM or starting up at locality 3 set the last
+    /// byte of PCR 0 to the locality.
+    pub fn with_startup_locality(self, locality: u8) -> PcrBank {
+        let mut value = PCR_INIT_VALUE.to_vec();
+        value[31] = locality;
+        self.with_initial_value(0, value).unwrap()
+    }
+
+    /// Overrides the value a PCR has before any event is extended into it
+    pub fn with_initial_value(mut self, id: u64, value: Vec<u8>) -> Result<PcrBank, PcrBankError> {
+        if value.len() != PCR_INIT_VALUE.len() {
+            return Err(PcrBankError::InvalidValueLength(value.len()));
+        }
+        self.pcr_mut(id)?.value = value;
+        Ok(self)
+    }
+
+    fn pcr_mut(&mut self, id: u64) -> Result<&mut Pcr, PcrBankError> {
+        self.pcrs
+            .get_mut(id as usize)
+            .ok_or(PcrBankError::InvalidPcr(id))
+    }
+
+    /// Extends the event into its PCR and returns the new PCR value
+    pub fn extend(&mut self, event: &TPMEvent) -> Result<&[u8], PcrBankError> {
+        let pcr = self.pcr_mut(event.pcr.into())?;
+        let mut hasher = Sha256::new();
+        hasher.update(&pcr.value);
+        hasher.update(&event.hash);
+        pcr.value = hasher.finalize().to_vec();
+        pcr.events.push(event.clone());
+        Ok(&pcr.value)
+    }
+
+    /// Extends the events in the order given
+    pub fn replay(&mut self, events: &[TPMEvent]) -> Result<(), PcrBankError> {
+        events.iter().try_for_each(|e| self.extend(e).map(|_| ()))
+    }
+
+    pub fn pcr(&self, id: u64) -> Option<&Pcr> {
+        self.pcrs.get(id as usize)
+    }
+
+    /// All the PCRs of the bank, ordered by id
+    pub fn pcrs(&self) -> &[Pcr] {
+        &self.pcrs
+    }
+
+    /// The PCRs that had events extended into them, ordered by id
+    pub fn snapshot(&self) -> Vec<Pcr> {
+        self.pcrs
+            .iter()
+            .filter(|p| !p.events.is_empty())
+            .cloned()
+            .collect()
+    }
+
+    /// Lists the PCRs whose value differs in the other bank, e.g. a later
+    /// state of the same bank
+    pub fn diff(&self, other: &PcrBank) -> Vec<PcrDiff> {
+        self.pcrs
+            .iter()
+            .zip(&other.pcrs)
+            .filter(|(a, b)| a.value != b.value)
+            .map(|(a, b)| PcrDiff {
+                id: a.id,
+                before: a.value.clone(),
+                after: b.value.clone(),
+            })
+            .collect()
+    }
+}
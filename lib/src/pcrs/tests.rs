@@ -3,7 +3,7 @@
 // SPDX-License-Identifier: MIT
 
 use super::*;
-use crate::tpmevents::{TPMEvent, TPMEventID};
+use crate::tpmevents::{EventType, TPMEvent, TPMEventID};
 
 #[test]
 fn test_pcr_serialization() {
@@ -11,14 +11,15 @@ fn test_pcr_serialization() {
         id: 123,
         value: vec![0, 0, 0, 0, 0, 0, 0, 253],
         events: vec![TPMEvent {
-            name: "foo".into(),
+            event_type: EventType::Ipl,
             pcr: 11,
             hash: vec![1, 0, 2, 3, 255],
             id: TPMEventID::Pcr11UnameContent,
+            data: None,
         }],
     };
     let expected = String::from(
-        "{\"id\":123,\"value\":\"00000000000000fd\",\"events\":[{\"name\":\"foo\",\"pcr\":11,\"hash\":\"01000203ff\",\"id\":\"Pcr11UnameContent\"}]}",
+        "{\"id\":123,\"value\":\"00000000000000fd\",\"events\":[{\"name\":\".uname_CONTENT\",\"pcr\":11,\"hash\":\"01000203ff\",\"id\":\"Pcr11UnameContent\"}]}",
     );
 
     assert_eq!(serde_json::to_string(&input).unwrap(), expected);
@@ -30,10 +31,11 @@ fn test_pcr_deserialization() {
         id: 0,
         value: vec![0, 0, 0, 0, 0, 0, 0, 240],
         events: vec![TPMEvent {
-            name: "foo".into(),
+            event_type: EventType::Ipl,
             pcr: 11,
             hash: vec![1, 0, 2, 3, 255],
             id: TPMEventID::Pcr11UnameContent,
+            data: None,
         }],
     };
 
@@ -48,22 +50,24 @@ fn test_pcr_deserialization() {
 fn test_pcr_compilation_from_tpmevents() {
     let input = vec![
         TPMEvent {
-            name: "FOOBAR".into(),
+            event_type: EventType::EfiAction,
             pcr: 4,
             hash: vec![
                 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
                 0, 0, 0, 0,
             ],
             id: TPMEventID::Pcr4EfiCall,
+            data: None,
         },
         TPMEvent {
-            name: "BARFOO".into(),
+            event_type: EventType::Separator,
             pcr: 4,
             hash: vec![
                 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
                 0, 0, 0, 1,
             ],
             id: TPMEventID::Pcr4Separator,
+            data: None,
         },
     ];
     let expected = Pcr {
@@ -85,22 +89,24 @@ fn test_pcr_compilation_from_tpmevents() {
 fn test_pcr_compilation_fails_for_heterogeneous_vecs() {
     let input = vec![
         TPMEvent {
-            name: "FOOBAR".into(),
+            event_type: EventType::EfiAction,
             pcr: 4,
             hash: vec![
                 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
                 0, 0, 0, 0,
             ],
             id: TPMEventID::Pcr4EfiCall,
+            data: None,
         },
         TPMEvent {
-            name: "BARFOO".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: vec![
                 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
                 0, 0, 0, 1,
             ],
             id: TPMEventID::Pcr7SecureBoot,
+            data: None,
         },
     ];
 
@@ -111,22 +117,24 @@ fn test_pcr_compilation_fails_for_heterogeneous_vecs() {
 fn test_pcr_compilation_from_heterogeneous_vec() {
     let input = [
         TPMEvent {
-            name: "FOOBAR".into(),
+            event_type: EventType::EfiAction,
             pcr: 4,
             hash: vec![
                 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
                 0, 0, 0, 0,
             ],
             id: TPMEventID::Pcr4EfiCall,
+            data: None,
         },
         TPMEvent {
-            name: "BARFOO".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: vec![
                 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
                 0, 0, 0, 1,
             ],
             id: TPMEventID::Pcr7SecureBoot,
+            data: None,
         },
     ];
 
@@ -153,3 +161,120 @@ fn test_pcr_compilation_from_heterogeneous_vec() {
 
     assert_eq!(res, expected);
 }
+
+#[test]
+fn test_select_pcrs() {
+    let pcr = |id: u64| Pcr {
+        id,
+        value: vec![id as u8; 32],
+        events: vec![],
+    };
+    let pcrs = vec![pcr(14), pcr(4), pcr(7)];
+
+    let selected = select_pcrs(&pcrs, &[7, 4, 7]).unwrap();
+    assert_eq!(selected, vec![&pcrs[1], &pcrs[2]]);
+    assert_eq!(
+        select_pcrs(&pcrs, &[4, 11]),
+        Err(MissingPcrError { id: 11 })
+    );
+}
+
+#[test]
+fn test_pcr_without_event_data() {
+    let event = TPMEvent {
+        event_type: EventType::Separator,
+        pcr: 7,
+        hash: vec![0xdf; 32],
+        id: TPMEventID::Pcr7Separator,
+        data: Some(vec![0; 4]),
+    };
+    let pcr = Pcr::compile_from(&vec![event.clone()]).without_event_data();
+
+    assert_eq!(
+        pcr.events,
+        vec![TPMEvent {
+            data: None,
+            ..event
+        }]
+    );
+}
+
+fn bank_event(pcr: u8, hash: u8, id: TPMEventID) -> TPMEvent {
+    TPMEvent {
+        event_type: id.event_type(),
+        pcr,
+        hash: vec![hash; 32],
+        id,
+        data: None,
+    }
+}
+
+#[test]
+fn test_pcr_bank_initial_values() {
+    let bank = PcrBank::new();
+    assert_eq!(bank.pcrs().len(), 24);
+    assert_eq!(bank.pcr(0).unwrap().value, [0; 32]);
+    assert_eq!(bank.pcr(16).unwrap().value, [0; 32]);
+    assert_eq!(bank.pcr(17).unwrap().value, [0xff; 32]);
+    assert_eq!(bank.pcr(22).unwrap().value, [0xff; 32]);
+    assert_eq!(bank.pcr(23).unwrap().value, [0; 32]);
+    assert!(bank.pcr(24).is_none());
+
+    let bank = PcrBank::new().with_startup_locality(3);
+    let mut locality3 = [0; 32];
+    locality3[31] = 3;
+    assert_eq!(bank.pcr(0).unwrap().value, locality3);
+
+    assert_eq!(
+        PcrBank::new().with_initial_value(24, vec![0; 32]),
+        Err(PcrBankError::InvalidPcr(24))
+    );
+    assert_eq!(
+        PcrBank::new().with_initial_value(4, vec![0; 20]),
+        Err(PcrBankError::InvalidValueLength(20))
+    );
+}
+
+#[test]
+fn test_pcr_bank_replay() {
+    // Interleaved PCRs, as they would be logged
+    let events = vec![
+        bank_event(4, 1, TPMEventID::Pcr4EfiCall),
+        bank_event(7, 2, TPMEventID::Pcr7SecureBoot),
+        bank_event(4, 3, TPMEventID::Pcr4Separator),
+        bank_event(7, 4, TPMEventID::Pcr7Separator),
+        bank_event(14, 5, TPMEventID::Pcr14MokList),
+    ];
+    let mut bank = PcrBank::new();
+    bank.replay(&events).unwrap();
+
+    let mut expected = compile_pcrs(&events);
+    expected.sort_by_key(|p| p.id);
+    assert_eq!(bank.snapshot(), expected);
+
+    let mut invalid = bank_event(4, 1, TPMEventID::Pcr4EfiCall);
+    invalid.pcr = 24;
+    assert_eq!(bank.extend(&invalid), Err(PcrBankError::InvalidPcr(24)));
+}
+
+#[test]
+fn test_pcr_bank_diff() {
+    let mut bank = PcrBank::new();
+    bank.extend(&bank_event(4, 1, TPMEventID::Pcr4EfiCall))
+        .unwrap();
+    let before = bank.clone();
+    let value = bank
+        .extend(&bank_event(4, 2, TPMEventID::Pcr4Separator))
+        .unwrap()
+        .to_vec();
+
+    assert_eq!(
+        before.diff(&bank),
+        vec![PcrDiff {
+            id: 4,
+            before: before.pcr(4).unwrap().value.clone(),
+            after: value,
+        }]
+    );
+    assert!(bank.diff(&bank).is_empty());
+}
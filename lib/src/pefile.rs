@@ -4,23 +4,32 @@
 // SPDX-License-Identifier: MIT
 
 use lief::generic::Section;
-use std::fs::File;
-use std::os::unix::fs::FileExt;
+use log::warn;
+use std::cell::OnceCell;
+use std::fs;
+
+use crate::authenticode::{self, AuthenticodeError};
 
 const SHIM_VENDOR_CERT_SECTION: &str = ".vendor_cert";
 
 pub struct PeFile {
     image: lief::pe::Binary,
+    /// Contents of the file, read once at load
+    raw: Vec<u8>,
     path: String,
     vmlinuz: bool,
+    /// Authenticode hash, computed and verified on first use
+    authenticode: OnceCell<Result<Vec<u8>, AuthenticodeError>>,
 }
 
 impl PeFile {
     pub fn load_from_file(path: &str, vmlinuz: bool) -> Option<PeFile> {
         Some(PeFile {
             image: lief::pe::Binary::parse(path)?,
+            raw: fs::read(path).ok()?,
             path: path.into(),
             vmlinuz,
+            authenticode: OnceCell::new(),
         })
     }
 
@@ -28,19 +37,40 @@ impl PeFile {
         &self.image
     }
 
-    pub fn authenticode(&self) -> Vec<u8> {
-        if self.vmlinuz {
-            return self.authenticode_vmlinuz();
-        }
+    pub fn path(&self) -> &str {
+        &self.path
+    }
 
-        self.image.authentihash(lief::pe::Algorithms::SHA_256)
+    /// Contents of the file
+    pub fn raw(&self) -> &[u8] {
+        &self.raw
     }
 
-    fn authenticode_vmlinuz(&self) -> Vec<u8> {
-        if let Some(signature) = self.signatures().next() {
-            return signature.content_info().digest();
+    /// Computes the sha256 Authenticode hash of the image, failing if it
+    /// does not match the digest its signatures were made over. The result
+    /// is computed once and cached.
+    pub fn authenticode(&self) -> Result<Vec<u8>, AuthenticodeError> {
+        self.authenticode
+            .get_or_init(|| self.compute_authenticode())
+            .clone()
+    }
+
+    fn compute_authenticode(&self) -> Result<Vec<u8>, AuthenticodeError> {
+        let hash = authenticode::verified_authenticode(&self.raw)?;
+
+        // lief does not hash vmlinuz images as firmware does
+        if !self.vmlinuz {
+            let lief_hash = self.image.authentihash(lief::pe::Algorithms::SHA_256);
+            if lief_hash != hash {
+                warn!(
+                    "{}: lief authentihash {} differs from the Authenticode hash {}",
+                    self.path,
+                    hex::encode(lief_hash),
+                    hex::encode(&hash)
+                );
+            }
         }
-        vec![]
+        Ok(hash)
     }
 
     fn long_section_name(&self, mut name: String) -> String {
@@ -49,25 +79,15 @@ impl PeFile {
         }
         name.remove(0);
         // Symbols are 18 bytes long
-        let mut string_offset = self.image.header().pointerto_symbol_table()
+        let string_offset = self.image.header().pointerto_symbol_table()
             + self.image.header().numberof_symbols() * 18
             + name.parse::<u32>().unwrap();
-        let mut long_name = String::new();
-        let mut next_char: [u8; 1] = [0];
-        loop {
-            let image_file = File::open(&self.path).unwrap();
-            if image_file
-                .read_exact_at(&mut next_char, string_offset as u64)
-                .is_err()
-                || next_char[0] == 0
-            {
-                break;
-            }
-
-            long_name.push(next_char[0] as char);
-            string_offset += 1;
-        }
-        long_name
+        self.raw
+            .iter()
+            .skip(string_offset as usize)
+            .take_while(|c| **c != 0)
+            .map(|c| *c as char)
+            .collect()
     }
 
     pub fn section(&self, name: &str) -> Option<Vec<u8>> {
@@ -79,24 +99,38 @@ impl PeFile {
         None
     }
 
-    fn get_vendor_cert_auth(&self) -> Option<Vec<u8>> {
+    /// Reads the (size, offset) pair at the given header positions of the
+    /// .vendor_cert section, and returns the data they point to
+    fn get_vendor_cert_part(&self, size_at: usize, offset_at: usize) -> Option<Vec<u8>> {
         let vendor_cert_raw = self.section(SHIM_VENDOR_CERT_SECTION)?;
         // 4 u32 header consisting of:
         //  - auth_size
         //  - deauth_size
         //  - auth_offset
         //  - deauth_offset
-        let auth_size = u32::from_le_bytes(
-            vendor_cert_raw[0..4]
+        let size = u32::from_le_bytes(
+            vendor_cert_raw[size_at..size_at + 4]
                 .try_into()
                 .expect("Badly hardcoded section size"),
         ) as usize;
-        let auth_offset = u32::from_le_bytes(
-            vendor_cert_raw[8..12]
+        let offset = u32::from_le_bytes(
+            vendor_cert_raw[offset_at..offset_at + 4]
                 .try_into()
                 .expect("Badly hardcoded section size"),
         ) as usize;
-        Some(vendor_cert_raw[auth_offset..auth_offset + auth_size].to_vec())
+        Some(vendor_cert_raw[offset..offset + size].to_vec())
+    }
+
+    fn get_vendor_cert_auth(&self) -> Option<Vec<u8>> {
+        self.get_vendor_cert_part(0, 8)
+    }
+
+    /// The .vendor_cert section of shim also stores vendor_dbx, a dbx
+    /// formatted list of the hashes and certificates shim forbids on top of
+    /// dbx and MokListX.
+    /// This function returns its raw contents, empty if there is none
+    pub fn vendor_dbx(&self) -> Vec<u8> {
+        self.get_vendor_cert_part(4, 12).unwrap_or_default()
     }
 
     /// The pe file can carry a .vendor_cert section, in which it could store
@@ -125,19 +159,4 @@ impl PeFile {
     pub fn signatures(&self) -> lief::pe::signature::Signatures<'_> {
         self.image.signatures()
     }
-
-    pub fn find_cert_in_db(&self, db: &Vec<crate::certs::X509Cert>) -> Option<Vec<u8>> {
-        for signature in self.signatures() {
-            for certificate in signature.certificates() {
-                let file_cert_subject = certificate.subject();
-                let file_cert_issuer = certificate.issuer();
-                for cert in db {
-                    if cert.subject == file_cert_subject || cert.subject == file_cert_issuer {
-                        return Some(cert.raw.clone());
-                    }
-                }
-            }
-        }
-        None
-    }
 }
@@ -0,0 +1,359 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+
+//! Offline verification of TPM2 quotes against predicted PCR values.
+//!
+//! A quote is made of the TPMS_ATTEST structure the TPM signed, the
+//! TPMT_SIGNATURE over it, and the public part of the attestation key (AK),
+//! as written by `tpm2_quote -m <msg> -s <sig>` and
+//! `tpm2_readpublic -f pem`.
+use openssl::bn::BigNum;
+use openssl::ecdsa::EcdsaSig;
+use openssl::hash::{MessageDigest, hash};
+use openssl::pkey::{PKey, Public};
+use openssl::rsa::Padding;
+use openssl::sign::{RsaPssSaltlen, Verifier};
+use serde::Serialize;
+use std::fmt;
+
+use crate::pcrs::{Pcr, select_pcrs};
+
+const TPM_GENERATED_VALUE: u32 = 0xff544347;
+const TPM_ST_ATTEST_QUOTE: u16 = 0x8018;
+
+const TPM_ALG_RSASSA: u16 = 0x0014;
+const TPM_ALG_RSAPSS: u16 = 0x0016;
+const TPM_ALG_ECDSA: u16 = 0x0018;
+const TPM_ALG_SHA1: u16 = 0x0004;
+const TPM_ALG_SHA256: u16 = 0x000b;
+const TPM_ALG_SHA384: u16 = 0x000c;
+const TPM_ALG_SHA512: u16 = 0x000d;
+
+const RSA_PSS_SALTLEN_AUTO: i32 = -2;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum QuoteError {
+    /// The attestation or signature structure is malformed
+    Parse(String),
+    /// The quote uses an algorithm this crate does not support
+    UnsupportedAlgorithm(u16),
+    /// The signature does not verify with the attestation key
+    BadSignature,
+    /// The quote does not hold the expected nonce in its extraData
+    NonceMismatch,
+    /// None of the predicted PCR sets matches the quoted PCR digest
+    NoMatch,
+    /// The quote selects PCRs that no candidate predicts, whose values are
+    /// needed to check the quoted PCR digest
+    UnpredictedPcrs(Vec<u64>),
+    Openssl(String),
+}
+
+impl fmt::Display for QuoteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QuoteError::Parse(e) => write!(f, "Error parsing quote: {e}"),
+            QuoteError::UnsupportedAlgorithm(alg) => {
+                write!(f, "Unsupported TPM algorithm {alg:#06x}")
+            }
+            QuoteError::BadSignature => write!(f, "Quote signature verification failed"),
+            QuoteError::NonceMismatch => write!(f, "Quote extraData does not match the nonce"),
+            QuoteError::NoMatch => {
+                write!(f, "No predicted PCR set matches the quoted PCR digest")
+            }
+            QuoteError::UnpredictedPcrs(ids) => {
+                write!(
+                    f,
+                    "The quote selects PCRs {ids:?}, which are not predicted. Quote only predicted PCRs"
+                )
+            }
+            QuoteError::Openssl(e) => write!(f, "OpenSSL error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for QuoteError {}
+
+impl From<openssl::error::ErrorStack> for QuoteError {
+    fn from(e: openssl::error::ErrorStack) -> Self {
+        QuoteError::Openssl(e.to_string())
+    }
+}
+
+/// PCRs of a bank selected by a quote
+#[derive(Clone, Debug, PartialEq)]
+pub struct PcrSelection {
+    pub hash_alg: u16,
+    pub pcr_ids: Vec<u64>,
+}
+
+/// TPMS_ATTEST of a quote
+#[derive(Clone, Debug, PartialEq)]
+pub struct Attest {
+    pub qualified_signer: Vec<u8>,
+    pub extra_data: Vec<u8>,
+    pub clock: u64,
+    pub reset_count: u32,
+    pub restart_count: u32,
+    pub safe: bool,
+    pub firmware_version: u64,
+    pub pcr_selections: Vec<PcrSelection>,
+    pub pcr_digest: Vec<u8>,
+}
+
+/// TPMT_SIGNATURE
+#[derive(Clone, Debug, PartialEq)]
+pub enum Signature {
+    RsaSsa {
+        hash_alg: u16,
+        sig: Vec<u8>,
+    },
+    RsaPss {
+        hash_alg: u16,
+        sig: Vec<u8>,
+    },
+    Ecdsa {
+        hash_alg: u16,
+        r: Vec<u8>,
+        s: Vec<u8>,
+    },
+}
+
+/// Predicted PCR set matching a quote
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct QuoteMatch {
+    /// Index of the matching PCR set among the candidates
+    pub candidate: usize,
+    /// Quoted sha256 PCRs
+    pub pcr_ids: Vec<u64>,
+}
+
+/// Big endian reader over TPM marshalled structures
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], QuoteError> {
+        if self.buf.len() < n {
+            return Err(QuoteError::Parse("unexpected end of data".into()));
+        }
+        let (head, tail) = self.buf.split_at(n);
+        self.buf = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, QuoteError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, QuoteError> {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, QuoteError> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, QuoteError> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    // TPM2B_* structures: u16 size followed by the buffer
+    fn sized(&mut self) -> Result<Vec<u8>, QuoteError> {
+        let size = self.u16()? as usize;
+        Ok(self.take(size)?.to_vec())
+    }
+}
+
+fn message_digest(hash_alg: u16) -> Result<MessageDigest, QuoteError> {
+    match hash_alg {
+        TPM_ALG_SHA1 => Ok(MessageDigest::sha1()),
+        TPM_ALG_SHA256 => Ok(MessageDigest::sha256()),
+        TPM_ALG_SHA384 => Ok(MessageDigest::sha384()),
+        TPM_ALG_SHA512 => Ok(MessageDigest::sha512()),
+        alg => Err(QuoteError::UnsupportedAlgorithm(alg)),
+    }
+}
+
+/// Parses the TPMS_ATTEST structure of a quote
+pub fn parse_attest(raw: &[u8]) -> Result<Attest, QuoteError> {
+    let mut r = Reader { buf: raw };
+    if r.u32()? != TPM_GENERATED_VALUE {
+        return Err(QuoteError::Parse("not generated by a TPM".into()));
+    }
+    if r.u16()? != TPM_ST_ATTEST_QUOTE {
+        return Err(QuoteError::Parse("not a quote".into()));
+    }
+    let qualified_signer = r.sized()?;
+    let extra_data = r.sized()?;
+    let clock = r.u64()?;
+    let reset_count = r.u32()?;
+    let restart_count = r.u32()?;
+    let safe = r.u8()? != 0;
+    let firmware_version = r.u64()?;
+
+    let mut pcr_selections = vec![];
+    for _ in 0..r.u32()? {
+        let hash_alg = r.u16()?;
+        let size = r.u8()? as usize;
+        let bitmap = r.take(size)?;
+        let pcr_ids = (0..size * 8)
+            .filter(|i| bitmap[i / 8] & (1 << (i % 8)) != 0)
+            .map(|i| i as u64)
+            .collect();
+        pcr_selections.push(PcrSelection { hash_alg, pcr_ids });
+    }
+    let pcr_digest = r.sized()?;
+
+    Ok(Attest {
+        qualified_signer,
+        extra_data,
+        clock,
+        reset_count,
+        restart_count,
+        safe,
+        firmware_version,
+        pcr_selections,
+        pcr_digest,
+    })
+}
+
+/// Parses a TPMT_SIGNATURE structure
+pub fn parse_signature(raw: &[u8]) -> Result<Signature, QuoteError> {
+    let mut r = Reader { buf: raw };
+    let sig_alg = r.u16()?;
+    let hash_alg = r.u16()?;
+    match sig_alg {
+        TPM_ALG_RSASSA => Ok(Signature::RsaSsa {
+            hash_alg,
+            sig: r.sized()?,
+        }),
+        TPM_ALG_RSAPSS => Ok(Signature::RsaPss {
+            hash_alg,
+            sig: r.sized()?,
+        }),
+        TPM_ALG_ECDSA => Ok(Signature::Ecdsa {
+            hash_alg,
+            r: r.sized()?,
+            s: r.sized()?,
+        }),
+        alg => Err(QuoteError::UnsupportedAlgorithm(alg)),
+    }
+}
+
+impl Signature {
+    fn hash_alg(&self) -> u16 {
+        match self {
+            Signature::RsaSsa { hash_alg, .. }
+            | Signature::RsaPss { hash_alg, .. }
+            | Signature::Ecdsa { hash_alg, .. } => *hash_alg,
+        }
+    }
+
+    /// Verifies the signature of the raw TPMS_ATTEST with the AK
+    pub fn verify(&self, attest: &[u8], ak: &PKey<Public>) -> Result<(), QuoteError> {
+        let digest = message_digest(self.hash_alg())?;
+        let mut verifier = Verifier::new(digest, ak)?;
+        let sig = match self {
+            Signature::RsaSsa { sig, .. } => {
+                verifier.set_rsa_padding(Padding::PKCS1)?;
+                sig.clone()
+            }
+            Signature::RsaPss { sig, .. } => {
+                verifier.set_rsa_padding(Padding::PKCS1_PSS)?;
+                // TPMs use the digest size or the largest salt that fits,
+                // RSA_PSS_SALTLEN_AUTO recovers it from the signature
+                verifier.set_rsa_pss_saltlen(RsaPssSaltlen::custom(RSA_PSS_SALTLEN_AUTO))?;
+                sig.clone()
+            }
+            Signature::Ecdsa { r, s, .. } => {
+                EcdsaSig::from_private_components(BigNum::from_slice(r)?, BigNum::from_slice(s)?)?
+                    .to_der()?
+            }
+        };
+        verifier.update(attest)?;
+        match verifier.verify(&sig) {
+            Ok(true) => Ok(()),
+            _ => Err(QuoteError::BadSignature),
+        }
+    }
+}
+
+/// Digest of the concatenated values of the selected PCRs, as the TPM
+/// computes the pcrDigest of a quote
+pub fn pcr_composite_digest(
+    pcrs: &[Pcr],
+    pcr_ids: &[u64],
+    hash_alg: u16,
+) -> Result<Option<Vec<u8>>, QuoteError> {
+    let Ok(selected) = select_pcrs(pcrs, pcr_ids) else {
+        return Ok(None);
+    };
+    let values: Vec<u8> = selected.iter().flat_map(|p| p.value.clone()).collect();
+    Ok(Some(hash(message_digest(hash_alg)?, &values)?.to_vec()))
+}
+
+/// Finds the first of the candidate PCR sets matching the quoted PCRs.
+///
+/// Only sha256 PCR selections can be checked, as computed PCRs are sha256
+/// ones. The composite digest uses the hash algorithm of the signature.
+/// The digest covers every quoted PCR, so quotes of PCRs that no candidate
+/// predicts can't be checked and are rejected. Candidates lacking some of
+/// the quoted PCRs never match.
+pub fn match_pcrs(
+    attest: &Attest,
+    hash_alg: u16,
+    candidates: &[Vec<Pcr>],
+) -> Result<QuoteMatch, QuoteError> {
+    let mut pcr_ids = vec![];
+    for selection in &attest.pcr_selections {
+        if selection.pcr_ids.is_empty() {
+            continue;
+        }
+        if selection.hash_alg != TPM_ALG_SHA256 {
+            return Err(QuoteError::UnsupportedAlgorithm(selection.hash_alg));
+        }
+        pcr_ids.extend(&selection.pcr_ids);
+    }
+
+    let unpredicted: Vec<u64> = pcr_ids
+        .iter()
+        .filter(|id| !candidates.iter().flatten().any(|p| p.id == **id))
+        .copied()
+        .collect();
+    if !unpredicted.is_empty() {
+        return Err(QuoteError::UnpredictedPcrs(unpredicted));
+    }
+
+    for (candidate, pcrs) in candidates.iter().enumerate() {
+        if pcr_composite_digest(pcrs, &pcr_ids, hash_alg)?.as_ref() == Some(&attest.pcr_digest) {
+            return Ok(QuoteMatch { candidate, pcr_ids });
+        }
+    }
+    Err(QuoteError::NoMatch)
+}
+
+/// Verifies a quote with the AK public key, checks its nonce if given, and
+/// finds which of the candidate PCR sets it attests.
+pub fn verify_quote(
+    attest: &[u8],
+    signature: &[u8],
+    ak: &PKey<Public>,
+    nonce: Option<&[u8]>,
+    candidates: &[Vec<Pcr>],
+) -> Result<QuoteMatch, QuoteError> {
+    let signature = parse_signature(signature)?;
+    signature.verify(attest, ak)?;
+    let parsed = parse_attest(attest)?;
+    if let Some(nonce) = nonce
+        && parsed.extra_data != nonce
+    {
+        return Err(QuoteError::NonceMismatch);
+    }
+    match_pcrs(&parsed, signature.hash_alg(), candidates)
+}
+
+#[cfg(test)]
+mod tests;
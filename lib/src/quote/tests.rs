@@ -0,0 +1,196 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+use super::*;
+use openssl::ec::{EcGroup, EcKey};
+use openssl::nid::Nid;
+use openssl::pkey::Private;
+use openssl::rsa::Rsa;
+use openssl::sign::Signer;
+use sha2::{Digest, Sha256};
+
+const NONCE: &[u8] = b"nonce";
+
+fn pcr(id: u64, value: u8) -> Pcr {
+    Pcr {
+        id,
+        value: vec![value; 32],
+        events: vec![],
+    }
+}
+
+fn sized(buf: &[u8]) -> Vec<u8> {
+    [(buf.len() as u16).to_be_bytes().as_slice(), buf].concat()
+}
+
+/// Marshals a quote of a single PCR selection
+fn attest(hash_alg: u16, pcr_ids: &[u64], pcr_digest: &[u8]) -> Vec<u8> {
+    let mut bitmap = [0u8; 3];
+    pcr_ids
+        .iter()
+        .for_each(|i| bitmap[*i as usize / 8] |= 1 << (i % 8));
+    [
+        TPM_GENERATED_VALUE.to_be_bytes().to_vec(),
+        TPM_ST_ATTEST_QUOTE.to_be_bytes().to_vec(),
+        sized(b"signer"),
+        sized(NONCE),
+        7u64.to_be_bytes().to_vec(),
+        1u32.to_be_bytes().to_vec(),
+        2u32.to_be_bytes().to_vec(),
+        vec![1],
+        3u64.to_be_bytes().to_vec(),
+        1u32.to_be_bytes().to_vec(),
+        hash_alg.to_be_bytes().to_vec(),
+        vec![3],
+        bitmap.to_vec(),
+        sized(pcr_digest),
+    ]
+    .concat()
+}
+
+fn rsa_signature(key: &PKey<Private>, attest: &[u8]) -> Vec<u8> {
+    let mut signer = Signer::new(MessageDigest::sha256(), key).unwrap();
+    signer.update(attest).unwrap();
+    [
+        TPM_ALG_RSASSA.to_be_bytes().to_vec(),
+        TPM_ALG_SHA256.to_be_bytes().to_vec(),
+        sized(&signer.sign_to_vec().unwrap()),
+    ]
+    .concat()
+}
+
+fn ecdsa_signature(key: &EcKey<Private>, attest: &[u8]) -> Vec<u8> {
+    let sig = EcdsaSig::sign(&Sha256::digest(attest), key).unwrap();
+    [
+        TPM_ALG_ECDSA.to_be_bytes().to_vec(),
+        TPM_ALG_SHA256.to_be_bytes().to_vec(),
+        sized(&sig.r().to_vec()),
+        sized(&sig.s().to_vec()),
+    ]
+    .concat()
+}
+
+fn public(key: &PKey<Private>) -> PKey<Public> {
+    PKey::public_key_from_pem(&key.public_key_to_pem().unwrap()).unwrap()
+}
+
+fn candidates() -> Vec<Vec<Pcr>> {
+    vec![
+        vec![pcr(4, 1), pcr(7, 2), pcr(14, 3)],
+        vec![pcr(4, 4), pcr(7, 5), pcr(14, 6)],
+    ]
+}
+
+fn composite(values: &[u8]) -> Vec<u8> {
+    let concat: Vec<u8> = values.iter().flat_map(|v| [*v; 32]).collect();
+    Sha256::digest(concat).to_vec()
+}
+
+#[test]
+fn test_parse_attest() {
+    let parsed = parse_attest(&attest(TPM_ALG_SHA256, &[14, 4], &[9; 32])).unwrap();
+
+    assert_eq!(parsed.qualified_signer, b"signer");
+    assert_eq!(parsed.extra_data, NONCE);
+    assert_eq!(parsed.clock, 7);
+    assert!(parsed.safe);
+    assert_eq!(
+        parsed.pcr_selections,
+        vec![PcrSelection {
+            hash_alg: TPM_ALG_SHA256,
+            pcr_ids: vec![4, 14],
+        }]
+    );
+    assert_eq!(parsed.pcr_digest, [9; 32]);
+    assert!(matches!(
+        parse_attest(&attest(TPM_ALG_SHA256, &[4], &[9; 32])[..40]),
+        Err(QuoteError::Parse(_))
+    ));
+}
+
+#[test]
+fn test_verify_quote_rsa() {
+    let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+    let attest = attest(TPM_ALG_SHA256, &[4, 7], &composite(&[4, 5]));
+    let signature = rsa_signature(&key, &attest);
+
+    assert_eq!(
+        verify_quote(
+            &attest,
+            &signature,
+            &public(&key),
+            Some(NONCE),
+            &candidates()
+        ),
+        Ok(QuoteMatch {
+            candidate: 1,
+            pcr_ids: vec![4, 7],
+        })
+    );
+
+    let mut tampered = attest.clone();
+    *tampered.last_mut().unwrap() ^= 1;
+    assert_eq!(
+        verify_quote(&tampered, &signature, &public(&key), None, &candidates()),
+        Err(QuoteError::BadSignature)
+    );
+    assert_eq!(
+        verify_quote(
+            &attest,
+            &signature,
+            &public(&key),
+            Some(b"other"),
+            &candidates()
+        ),
+        Err(QuoteError::NonceMismatch)
+    );
+}
+
+#[test]
+fn test_verify_quote_ecdsa() {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+    let ec_key = EcKey::generate(&group).unwrap();
+    let key = PKey::from_ec_key(ec_key.clone()).unwrap();
+    let attest = attest(TPM_ALG_SHA256, &[4, 7, 14], &composite(&[1, 2, 3]));
+    let signature = ecdsa_signature(&ec_key, &attest);
+
+    assert_eq!(
+        verify_quote(&attest, &signature, &public(&key), None, &candidates()),
+        Ok(QuoteMatch {
+            candidate: 0,
+            pcr_ids: vec![4, 7, 14],
+        })
+    );
+}
+
+#[test]
+fn test_match_pcrs_errors() {
+    let parsed = parse_attest(&attest(TPM_ALG_SHA256, &[4, 7], &composite(&[1, 5]))).unwrap();
+    assert_eq!(
+        match_pcrs(&parsed, TPM_ALG_SHA256, &candidates()),
+        Err(QuoteError::NoMatch)
+    );
+
+    // PCRs 0 and 11 are not predicted
+    let parsed =
+        parse_attest(&attest(TPM_ALG_SHA256, &[0, 4, 11], &composite(&[0, 1, 0]))).unwrap();
+    assert_eq!(
+        match_pcrs(&parsed, TPM_ALG_SHA256, &candidates()),
+        Err(QuoteError::UnpredictedPcrs(vec![0, 11]))
+    );
+
+    // Only the second candidate predicts PCR 11, which does not match
+    let mut with_pcr11 = candidates();
+    with_pcr11[1].push(pcr(11, 7));
+    let parsed = parse_attest(&attest(TPM_ALG_SHA256, &[4, 11], &composite(&[1, 7]))).unwrap();
+    assert_eq!(
+        match_pcrs(&parsed, TPM_ALG_SHA256, &with_pcr11),
+        Err(QuoteError::NoMatch)
+    );
+
+    let parsed = parse_attest(&attest(TPM_ALG_SHA1, &[4], &[0; 20])).unwrap();
+    assert_eq!(
+        match_pcrs(&parsed, TPM_ALG_SHA256, &candidates()),
+        Err(QuoteError::UnsupportedAlgorithm(TPM_ALG_SHA1))
+    );
+}
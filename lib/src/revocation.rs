@@ -0,0 +1,213 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+
+//! Checks of the boot chain images against the forbidden signature
+//! databases, so no reference values are computed for images firmware or
+//! shim would refuse to run.
+//!
+//! Firmware checks shim against dbx. shim checks grub and the kernel against
+//! dbx, MokListX and the vendor_dbx built into it. An image is revoked if its
+//! Authenticode hash is listed, if one of its signatures verifies against a
+//! listed certificate, or if the TBSCertificate hash of a certificate it
+//! carries is listed.
+use openssl::pkcs7::Pkcs7;
+use serde::Serialize;
+use serde_with::serde_as;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::io;
+
+use crate::authenticode::{self, AuthenticodeError};
+use crate::certs::{self, CertDbParsingError, Revocation};
+use crate::der::{Der, TAG_SEQUENCE};
+use crate::esp;
+use crate::linux;
+use crate::mok;
+use crate::pefile::PeFile;
+use crate::uefi::efivars;
+
+const DBX: &str = "dbx";
+const MOK_LIST_X: &str = "MokListX";
+const VENDOR_DBX: &str = "vendor_dbx";
+
+#[derive(Debug)]
+pub enum RevocationError {
+    /// A forbidden signature database could not be parsed
+    Database(String, CertDbParsingError),
+    /// An image could not be read or its signatures could not be parsed
+    Image(String, AuthenticodeError),
+    Io(io::Error),
+}
+
+impl fmt::Display for RevocationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RevocationError::Database(name, e) => write!(f, "{name}: {e}"),
+            RevocationError::Image(path, e) => write!(f, "{path}: {e}"),
+            RevocationError::Io(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for RevocationError {}
+
+impl From<io::Error> for RevocationError {
+    fn from(e: io::Error) -> Self {
+        RevocationError::Io(e)
+    }
+}
+
+/// Why a database forbids an image
+#[serde_as]
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reason {
+    /// The Authenticode hash of the image is listed
+    ImageHash(#[serde_as(as = "serde_with::hex::Hex")] Vec<u8>),
+    /// A signature verifies against the listed certificate with this subject
+    Certificate(String),
+    /// The TBSCertificate hash of the certificate with this subject, carried
+    /// by a signature, is listed
+    CertificateTbsHash(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RevokedImage {
+    pub image: String,
+    pub database: String,
+    pub reason: Reason,
+}
+
+/// A forbidden signature database, along with its name
+pub struct Database {
+    pub name: String,
+    pub revocations: Vec<Revocation>,
+}
+
+impl Database {
+    pub fn parse(name: &str, data: &[u8]) -> Result<Database, RevocationError> {
+        Ok(Database {
+            name: name.into(),
+            revocations: certs::get_revocations(data)
+                .map_err(|e| RevocationError::Database(name.into(), e))?,
+        })
+    }
+}
+
+/// sha256 of the TBSCertificate of a DER encoded certificate
+fn tbs_hash(cert: &[u8]) -> Option<Vec<u8>> {
+    let tbs = Der::new(cert).next_if(TAG_SEQUENCE)?.children().next()?;
+    Some(Sha256::digest(tbs.raw).to_vec())
+}
+
+/// Reasons each database forbids the image for. Images whose signatures
+/// are not over their Authenticode hash fail, rather than being checked
+/// against a hash no signature covers.
+pub fn check_image(
+    image: &[u8],
+    databases: &[Database],
+) -> Result<Vec<(String, Reason)>, AuthenticodeError> {
+    check_verified_image(
+        image,
+        &authenticode::verified_authenticode(image)?,
+        databases,
+    )
+}
+
+/// check_image for an image whose Authenticode hash has been verified
+fn check_verified_image(
+    image: &[u8],
+    hash: &[u8],
+    databases: &[Database],
+) -> Result<Vec<(String, Reason)>, AuthenticodeError> {
+    let signatures = authenticode::signatures(image)?;
+    // Certificates carried by the signatures, with their TBSCertificate hash
+    let mut carried = vec![];
+    for signature in &signatures {
+        let p7 =
+            Pkcs7::from_der(signature).map_err(|e| AuthenticodeError::Signature(e.to_string()))?;
+        for cert in p7
+            .signed()
+            .and_then(|s| s.certificates())
+            .into_iter()
+            .flatten()
+        {
+            let der = cert.to_der()?;
+            if let Some(tbs) = tbs_hash(&der) {
+                carried.push((certs::X509Cert::from_der(&der)?, tbs));
+            }
+        }
+    }
+
+    let mut reasons = vec![];
+    for database in databases {
+        for revocation in &database.revocations {
+            let reason = match revocation {
+                Revocation::ImageHash(h) if h == hash => Some(Reason::ImageHash(hash.to_vec())),
+                Revocation::Certificate(cert) => signatures
+                    .iter()
+                    .any(|s| authenticode::verify_signature(s, &cert.cert) == Ok(true))
+                    .then(|| Reason::Certificate(cert.subject.clone())),
+                Revocation::CertificateTbsHash(h) => carried
+                    .iter()
+                    .find(|(_, tbs)| tbs == h)
+                    .map(|(cert, _)| Reason::CertificateTbsHash(cert.subject.clone())),
+                _ => None,
+            };
+            reasons.extend(reason.map(|r| (database.name.clone(), r)));
+        }
+    }
+    Ok(reasons)
+}
+
+fn check_pefile(pe: &PeFile, databases: &[Database]) -> Result<Vec<RevokedImage>, RevocationError> {
+    let reasons = pe
+        .authenticode()
+        .and_then(|hash| check_verified_image(pe.raw(), &hash, databases))
+        .map_err(|e| RevocationError::Image(pe.path().into(), e))?;
+    Ok(reasons
+        .into_iter()
+        .map(|(database, reason)| RevokedImage {
+            image: pe.path().into(),
+            database,
+            reason,
+        })
+        .collect())
+}
+
+/// Checks shim, grub and, if a kernels directory is given, the vmlinuz image
+/// shim boots against the databases that apply to each of them
+pub fn check_boot_chain(
+    esp_path: &str,
+    kernels_dir: Option<&str>,
+    efivars_path: &str,
+    mok_variables: Option<&str>,
+) -> Result<Vec<RevokedImage>, RevocationError> {
+    let esp = esp::Esp::new(esp_path)?;
+    let efivars =
+        efivars::EFIVarsLoader::new(efivars_path, efivars::SECURE_BOOT_ATTR_HEADER_LENGTH);
+    let dbx = Database::parse(DBX, &efivars.secureboot_dbx())?;
+    let shim = esp.shim();
+    let mut revoked = check_pefile(&shim, std::slice::from_ref(&dbx))?;
+
+    let mok_list_x = match mok_variables {
+        Some(dir) => mok::load_mok_variable(dir, MOK_LIST_X)?,
+        None => vec![],
+    };
+    let shim_databases = [
+        dbx,
+        Database::parse(MOK_LIST_X, &mok_list_x)?,
+        Database::parse(VENDOR_DBX, &shim.vendor_dbx())?,
+    ];
+    revoked.extend(check_pefile(&esp.grub(), &shim_databases)?);
+    if let Some(kernels_dir) = kernels_dir {
+        let vmlinuz = linux::load_vmlinuz(kernels_dir)
+            .map_err(|e| io::Error::new(io::ErrorKind::NotFound, e.to_string()))?;
+        revoked.extend(check_pefile(&vmlinuz, &shim_databases)?);
+    }
+    Ok(revoked)
+}
+
+#[cfg(test)]
+mod tests;
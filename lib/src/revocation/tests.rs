@@ -0,0 +1,116 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+use super::*;
+use crate::test_support::{
+    OWNER, authenticode_signature, cert, expected_digest, image, key, signature_list,
+};
+use crate::uefi::sigdb::SignatureType;
+
+#[test]
+fn test_get_revocations() {
+    let ca_key = key();
+    let ca = cert("Secure Boot CA", &ca_key, None);
+    let dbx = [
+        signature_list(SignatureType::Sha256, &[vec![1; 32], vec![2; 32]]),
+        // TBS hash followed by the EFI_TIME of the revocation
+        signature_list(
+            SignatureType::X509Sha256,
+            &[[vec![3; 32], vec![0; 16]].concat()],
+        ),
+        signature_list(SignatureType::X509, &[ca.to_der().unwrap()]),
+    ]
+    .concat();
+
+    let revocations = certs::get_revocations(&dbx).unwrap();
+    assert_eq!(revocations.len(), 4);
+    assert!(matches!(&revocations[0], Revocation::ImageHash(h) if *h == [1; 32]));
+    assert!(matches!(&revocations[1], Revocation::ImageHash(h) if *h == [2; 32]));
+    assert!(matches!(&revocations[2], Revocation::CertificateTbsHash(h) if *h == [3; 32]));
+    let Revocation::Certificate(revoked_ca) = &revocations[3] else {
+        panic!("{:?} is not a certificate", revocations[3]);
+    };
+    assert_eq!(revoked_ca.subject, "CN=Secure Boot CA");
+    assert_eq!(
+        revoked_ca.raw,
+        [OWNER.to_bytes_le().to_vec(), ca.to_der().unwrap()].concat()
+    );
+
+    assert!(certs::get_revocations(&[]).unwrap().is_empty());
+    assert!(certs::get_revocations(&dbx[..dbx.len() - 1]).is_err());
+    assert!(certs::get_revocations(&dbx[..20]).is_err());
+}
+
+#[test]
+fn test_check_image() {
+    let (ca_key, signer_key, other_key) = (key(), key(), key());
+    let ca = cert("Secure Boot CA", &ca_key, None);
+    let signer = cert("Secure Boot Signer", &signer_key, Some((&ca, &ca_key)));
+    let other = cert("Secure Boot CA", &other_key, None);
+    let digest = expected_digest(&image(None));
+    let signed = image(Some(&authenticode_signature(&digest, &signer, &signer_key)));
+    let signer_tbs = tbs_hash(&signer.to_der().unwrap()).unwrap();
+    let database = |name: &str, lists: &[Vec<u8>]| Database::parse(name, &lists.concat()).unwrap();
+
+    let unrelated = [
+        database(
+            DBX,
+            &[
+                signature_list(SignatureType::Sha256, &[vec![0; 32]]),
+                signature_list(SignatureType::X509Sha256, &[vec![0; 48]]),
+                signature_list(SignatureType::X509, &[other.to_der().unwrap()]),
+            ],
+        ),
+        database(MOK_LIST_X, &[]),
+    ];
+    assert_eq!(check_image(&signed, &unrelated), Ok(vec![]));
+
+    let revoking = [
+        database(
+            DBX,
+            &[signature_list(
+                SignatureType::Sha256,
+                std::slice::from_ref(&digest),
+            )],
+        ),
+        database(
+            MOK_LIST_X,
+            &[
+                signature_list(SignatureType::X509, &[ca.to_der().unwrap()]),
+                signature_list(
+                    SignatureType::X509Sha256,
+                    &[[signer_tbs, vec![0; 16]].concat()],
+                ),
+            ],
+        ),
+    ];
+    assert_eq!(
+        check_image(&signed, &revoking),
+        Ok(vec![
+            (DBX.into(), Reason::ImageHash(digest.clone())),
+            (
+                MOK_LIST_X.into(),
+                Reason::Certificate("CN=Secure Boot CA".into())
+            ),
+            (
+                MOK_LIST_X.into(),
+                Reason::CertificateTbsHash("CN=Secure Boot Signer".into())
+            ),
+        ])
+    );
+    // Unsigned images can only be revoked by their hash
+    assert_eq!(
+        check_image(&image(None), &revoking),
+        Ok(vec![(DBX.into(), Reason::ImageHash(digest))])
+    );
+    // Images whose signatures are over another image are not checked
+    let forged = image(Some(&authenticode_signature(
+        &[0; 32],
+        &signer,
+        &signer_key,
+    )));
+    assert!(matches!(
+        check_image(&forged, &revoking),
+        Err(AuthenticodeError::DigestMismatch { .. })
+    ));
+}
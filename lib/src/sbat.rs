@@ -0,0 +1,196 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+
+//! SBAT (Secure Boot Advanced Targeting) evaluation of the boot chain.
+//!
+//! Images carry a `.sbat` section: CSV lines of component name, component
+//! generation and vendor information. SbatLevel lists the minimum generation
+//! of each revoked component. shim refuses to load an image that has a
+//! component with a lower generation than SbatLevel requires, as well as
+//! images it loads that have no `.sbat` section at all.
+use serde::Serialize;
+use std::fmt;
+
+use crate::esp;
+use crate::linux;
+use crate::pefile::PeFile;
+use crate::shim::{self, SbatLevelSelection};
+use crate::uefi::efivars::{EFIVarsLoader, SECURE_BOOT_ATTR_HEADER_LENGTH};
+
+pub const SBAT_SECTION: &str = ".sbat";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SbatError {
+    /// The line with this number has no component name and generation
+    Parse(usize),
+    /// shim has no .sbatlevel section to take the policy from
+    MissingSbatLevel,
+    /// The efivars directory has no SbatLevelRT variable
+    MissingSbatLevelRT,
+    Io(String),
+}
+
+impl fmt::Display for SbatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SbatError::Parse(line) => write!(f, "Invalid SBAT entry at line {line}"),
+            SbatError::MissingSbatLevel => write!(f, "shim has no .sbatlevel section"),
+            SbatError::MissingSbatLevelRT => write!(f, "No SbatLevelRT variable in efivars"),
+            SbatError::Io(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for SbatError {}
+
+/// A line of a .sbat section or of SbatLevel
+#[derive(Clone, Debug, PartialEq)]
+pub struct SbatEntry {
+    pub component: String,
+    pub generation: u32,
+    /// Remaining columns: vendor name, package name, version and URL in
+    /// .sbat sections, the datestamp of the policy in the SbatLevel header
+    pub fields: Vec<String>,
+}
+
+/// Parses SBAT CSV data, up to its first NUL byte as sections are padded
+pub fn parse_sbat(data: &[u8]) -> Result<Vec<SbatEntry>, SbatError> {
+    let end = data.iter().position(|b| *b == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end])
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(n, line)| {
+            let mut fields = line.split(',').map(|f| f.trim().to_string());
+            let component = fields.next().filter(|c| !c.is_empty());
+            let generation = fields.next().and_then(|g| g.parse().ok());
+            match (component, generation) {
+                (Some(component), Some(generation)) => Ok(SbatEntry {
+                    component,
+                    generation,
+                    fields: fields.collect(),
+                }),
+                _ => Err(SbatError::Parse(n + 1)),
+            }
+        })
+        .collect()
+}
+
+/// A component generation of an image below the one SbatLevel requires
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SbatViolation {
+    pub component: String,
+    pub generation: u32,
+    pub required: u32,
+}
+
+/// Checks the entries of an image against SbatLevel, as shim does
+pub fn evaluate(entries: &[SbatEntry], sbat_level: &[SbatEntry]) -> Vec<SbatViolation> {
+    sbat_level
+        .iter()
+        .flat_map(|level| {
+            entries
+                .iter()
+                .filter(move |e| e.component == level.component && e.generation < level.generation)
+                .map(|e| SbatViolation {
+                    component: e.component.clone(),
+                    generation: e.generation,
+                    required: level.generation,
+                })
+        })
+        .collect()
+}
+
+/// SbatLevel shim applies with the .sbatlevel section of the shim in the
+/// ESP, selected as for PCR 7. Selecting it from the efivars directory
+/// requires its SbatLevelRT variable.
+pub fn shim_sbat_level(
+    esp_path: &str,
+    efivars_path: Option<&str>,
+    selection: &SbatLevelSelection,
+) -> Result<Vec<SbatEntry>, SbatError> {
+    let esp = esp::Esp::new(esp_path).map_err(|e| SbatError::Io(e.to_string()))?;
+    let sbatlevel_raw = esp
+        .shim()
+        .section(shim::SHIM_SBATLEVEL_SECTION)
+        .ok_or(SbatError::MissingSbatLevel)?;
+    let efivars = efivars_path.map(|path| EFIVarsLoader::new(path, SECURE_BOOT_ATTR_HEADER_LENGTH));
+    sbat_level(&sbatlevel_raw, efivars.as_ref(), selection)
+}
+
+fn sbat_level(
+    sbatlevel_raw: &[u8],
+    efivars: Option<&EFIVarsLoader>,
+    selection: &SbatLevelSelection,
+) -> Result<Vec<SbatEntry>, SbatError> {
+    let data = match selection {
+        SbatLevelSelection::Policy(policy) => shim::get_sbatlevel_section(sbatlevel_raw, policy),
+        SbatLevelSelection::Efivars => {
+            let efivars = efivars.ok_or(SbatError::MissingSbatLevelRT)?;
+            let current = efivars.sbat_level();
+            if current.is_empty() {
+                return Err(SbatError::MissingSbatLevelRT);
+            }
+            shim::get_effective_sbatlevel(sbatlevel_raw, &efivars.sbat_policy(), &current)
+        }
+    };
+    parse_sbat(&data)
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rejection {
+    /// The image is loaded by shim and has no .sbat section
+    MissingSbat,
+    Revoked(SbatViolation),
+}
+
+/// Image of the boot chain shim would refuse to load
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RejectedImage {
+    pub image: String,
+    pub reason: Rejection,
+}
+
+fn check_pefile(
+    pe: &PeFile,
+    sbat_level: &[SbatEntry],
+    sbat_required: bool,
+) -> Result<Vec<RejectedImage>, SbatError> {
+    let rejected = |reason| RejectedImage {
+        image: pe.path().into(),
+        reason,
+    };
+    let Some(section) = pe.section(SBAT_SECTION) else {
+        return Ok(match sbat_required {
+            true => vec![rejected(Rejection::MissingSbat)],
+            false => vec![],
+        });
+    };
+    Ok(evaluate(&parse_sbat(&section)?, sbat_level)
+        .into_iter()
+        .map(|violation| rejected(Rejection::Revoked(violation)))
+        .collect())
+}
+
+/// Checks shim, grub and, if a kernels directory is given, the vmlinuz image
+/// against SbatLevel. grub must have a .sbat section; the kernel is loaded
+/// through the shim protocol, which lets images without one through.
+pub fn check_boot_chain(
+    esp_path: &str,
+    kernels_dir: Option<&str>,
+    sbat_level: &[SbatEntry],
+) -> Result<Vec<RejectedImage>, SbatError> {
+    let esp = esp::Esp::new(esp_path).map_err(|e| SbatError::Io(e.to_string()))?;
+    let mut rejected = check_pefile(&esp.shim(), sbat_level, false)?;
+    rejected.extend(check_pefile(&esp.grub(), sbat_level, true)?);
+    if let Some(kernels_dir) = kernels_dir {
+        let vmlinuz = linux::load_vmlinuz(kernels_dir).map_err(|e| SbatError::Io(e.to_string()))?;
+        rejected.extend(check_pefile(&vmlinuz, sbat_level, false)?);
+    }
+    Ok(rejected)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,113 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+use super::*;
+use crate::shim::SbatLevelPolicyType;
+use crate::test_support::{sbatlevel_section, temp_dir};
+use crate::uefi::GUID_SHIM_LOCK;
+use crate::uefi::efivars::write_efivar;
+
+const GRUB_SBAT: &[u8] =
+    b"sbat,1,SBAT Version,sbat,1,https://github.com/rhboot/shim/blob/main/SBAT.md
+grub,3,Free Software Foundation,grub,2.12,https://www.gnu.org/software/grub/
+grub.rh,2,Red Hat,grub2,2.12-28.fc42,mailto:secalert@redhat.com
+\0\0\0\0";
+
+const SBAT_LEVEL: &[u8] = b"sbat,1,2024010900\nshim,4\ngrub,4\ngrub.peimage,2\n";
+
+#[test]
+fn test_parse_sbat() {
+    let entries = parse_sbat(GRUB_SBAT).unwrap();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(
+        entries[1],
+        SbatEntry {
+            component: "grub".into(),
+            generation: 3,
+            fields: vec![
+                "Free Software Foundation".into(),
+                "grub".into(),
+                "2.12".into(),
+                "https://www.gnu.org/software/grub/".into()
+            ],
+        }
+    );
+
+    let level = parse_sbat(SBAT_LEVEL).unwrap();
+    assert_eq!(level[0].component, "sbat");
+    assert_eq!(level[0].fields, ["2024010900"]);
+    assert_eq!(level[3].generation, 2);
+
+    assert_eq!(parse_sbat(b""), Ok(vec![]));
+    assert_eq!(parse_sbat(b"sbat,1\ngrub\n"), Err(SbatError::Parse(2)));
+    assert_eq!(parse_sbat(b"grub,three\n"), Err(SbatError::Parse(1)));
+    assert_eq!(parse_sbat(b",1\n"), Err(SbatError::Parse(1)));
+}
+
+#[test]
+fn test_evaluate() {
+    let grub = parse_sbat(GRUB_SBAT).unwrap();
+    assert_eq!(
+        evaluate(&grub, &parse_sbat(SBAT_LEVEL).unwrap()),
+        [SbatViolation {
+            component: "grub".into(),
+            generation: 3,
+            required: 4,
+        }]
+    );
+    // Components the image does not have, and generations at or above the
+    // required one, are accepted
+    let level = parse_sbat(b"sbat,1,2023012900\nshim,2\ngrub,3\ngrub.rh,2\n").unwrap();
+    assert!(evaluate(&grub, &level).is_empty());
+    // Images with an older SBAT format are revoked by the sbat entry
+    let level = parse_sbat(b"sbat,2,2030010100\n").unwrap();
+    assert_eq!(evaluate(&grub, &level)[0].component, "sbat");
+}
+
+#[test]
+fn test_sbat_level() {
+    let previous = b"sbat,1,2023012900\nshim,2\ngrub,3\n";
+    let raw = sbatlevel_section(previous, SBAT_LEVEL);
+    let policy = |policy| SbatLevelSelection::Policy(policy);
+    assert_eq!(
+        sbat_level(&raw, None, &policy(SbatLevelPolicyType::PREVIOUS)),
+        parse_sbat(previous)
+    );
+    assert_eq!(
+        sbat_level(&raw, None, &policy(SbatLevelPolicyType::LATEST)),
+        parse_sbat(SBAT_LEVEL)
+    );
+
+    // No SbatLevelRT to derive the SbatLevel from
+    let dir = temp_dir();
+    let efivars = EFIVarsLoader::new(dir.path().to_str().unwrap(), SECURE_BOOT_ATTR_HEADER_LENGTH);
+    assert_eq!(
+        sbat_level(&raw, None, &SbatLevelSelection::Efivars),
+        Err(SbatError::MissingSbatLevelRT)
+    );
+    assert_eq!(
+        sbat_level(&raw, Some(&efivars), &SbatLevelSelection::Efivars),
+        Err(SbatError::MissingSbatLevelRT)
+    );
+
+    // An older SbatLevelRT is replaced by the previous policy
+    let sbat_level_rt = format!("SbatLevelRT-{GUID_SHIM_LOCK}");
+    write_efivar(
+        dir.path(),
+        &sbat_level_rt,
+        0x6,
+        b"sbat,1,2022052400\ngrub,2\n",
+    )
+    .unwrap();
+    assert_eq!(
+        sbat_level(&raw, Some(&efivars), &SbatLevelSelection::Efivars),
+        parse_sbat(previous)
+    );
+    // and a newer one is kept
+    let newer = b"sbat,1,2025051000\nshim,4\ngrub,5\n";
+    write_efivar(dir.path(), &sbat_level_rt, 0x6, newer).unwrap();
+    assert_eq!(
+        sbat_level(&raw, Some(&efivars), &SbatLevelSelection::Efivars),
+        parse_sbat(newer)
+    );
+}
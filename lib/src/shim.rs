@@ -3,16 +3,36 @@
 //
 // SPDX-License-Identifier: MIT
 
+use crate::sbat;
 use crate::uefi::{GUID_SHIM_LOCK, UEFIVariableData};
 
 pub const SHIM_SBATLEVEL_SECTION: &str = ".sbatlevel";
 const SBAT_VAR_ORIGINAL: &str = "sbat,1,2021030218\n";
+// Values of the SbatPolicy variable
+const SBAT_POLICY_LATEST: u8 = 1;
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum SbatLevelPolicyType {
     PREVIOUS,
     LATEST,
 }
 
+/// How the SbatLevel shim measures into PCR 7 is chosen
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SbatLevelSelection {
+    /// A policy of the .sbatlevel section, as applied by shim
+    Policy(SbatLevelPolicyType),
+    /// Derived from the SbatPolicy and SbatLevelRT variables of the target
+    /// machine, as shim does on boot
+    Efivars,
+}
+
+impl Default for SbatLevelSelection {
+    fn default() -> Self {
+        SbatLevelSelection::Policy(SbatLevelPolicyType::PREVIOUS)
+    }
+}
+
 // Given the raw .sbatlevel section data, it returns the .sbatlevel data of the
 // target sbatlevel policy, which can be previous or latest.
 //
@@ -20,7 +40,10 @@ pub enum SbatLevelPolicyType {
 //  - version
 //  - previous .sbatlevel policy section offset
 //  - latest .sbatlevel policy section offset
-fn get_sbatlevel_section(sbatlevel_raw: &[u8], sbatlevel_policy: &SbatLevelPolicyType) -> Vec<u8> {
+pub(crate) fn get_sbatlevel_section(
+    sbatlevel_raw: &[u8],
+    sbatlevel_policy: &SbatLevelPolicyType,
+) -> Vec<u8> {
     let raw_len = sbatlevel_raw.len();
     assert!(raw_len > 12, "Unknown sbatlevel data format: too short");
 
@@ -53,6 +76,39 @@ fn get_sbatlevel_section(sbatlevel_raw: &[u8], sbatlevel_policy: &SbatLevelPolic
     sbatlevel_raw[policy_offset..policy_end].to_vec()
 }
 
+/// Datestamp of the header entry of SbatLevel data, if it is valid
+fn sbat_level_datestamp(data: &[u8]) -> Option<u64> {
+    let entries = sbat::parse_sbat(data).ok()?;
+    let header = entries.first().filter(|e| e.component == "sbat")?;
+    header.fields.first()?.parse().ok()
+}
+
+/// Given the raw .sbatlevel section data and the SbatPolicy and SbatLevel
+/// variable data, it returns the SbatLevel shim sets on boot:
+///  - SbatPolicy latest selects the latest .sbatlevel policy. Any other
+///    value, or no SbatPolicy, selects the previous one, as reset is ignored
+///    while Secure Boot is enabled.
+///  - The current SbatLevel is kept unless it is invalid or older than the
+///    selected policy.
+pub fn get_effective_sbatlevel(
+    sbatlevel_raw: &[u8],
+    sbat_policy: &[u8],
+    current_sbatlevel: &[u8],
+) -> Vec<u8> {
+    let policy = match sbat_policy.first() {
+        Some(&SBAT_POLICY_LATEST) => SbatLevelPolicyType::LATEST,
+        _ => SbatLevelPolicyType::PREVIOUS,
+    };
+    let candidate = get_sbatlevel_section(sbatlevel_raw, &policy);
+    match (
+        sbat_level_datestamp(current_sbatlevel),
+        sbat_level_datestamp(&candidate),
+    ) {
+        (Some(current), Some(selected)) if current >= selected => current_sbatlevel.to_vec(),
+        _ => candidate,
+    }
+}
+
 pub fn get_sbat_var_original_uefivar() -> UEFIVariableData {
     UEFIVariableData::new(GUID_SHIM_LOCK, "SbatLevel", SBAT_VAR_ORIGINAL.into())
 }
@@ -69,3 +125,6 @@ pub fn get_sbatlevel_uefivar(
         get_sbatlevel_section(sbatlevel_raw, sbatlevel_policy),
     )
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,49 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+use super::*;
+use crate::test_support;
+
+const PREVIOUS: &[u8] = b"sbat,1,2023012900\nshim,2\ngrub,3\n";
+const LATEST: &[u8] = b"sbat,1,2024010900\nshim,4\ngrub,4\n";
+
+fn sbatlevel_section() -> Vec<u8> {
+    test_support::sbatlevel_section(PREVIOUS, LATEST)
+}
+
+#[test]
+fn test_get_sbatlevel_section() {
+    let raw = sbatlevel_section();
+    assert_eq!(
+        get_sbatlevel_section(&raw, &SbatLevelPolicyType::PREVIOUS),
+        PREVIOUS
+    );
+    assert_eq!(
+        get_sbatlevel_section(&raw, &SbatLevelPolicyType::LATEST),
+        LATEST
+    );
+}
+
+#[test]
+fn test_get_effective_sbatlevel() {
+    let raw = sbatlevel_section();
+    // No SbatPolicy nor SbatLevel: the previous policy is applied
+    assert_eq!(get_effective_sbatlevel(&raw, &[], &[]), PREVIOUS);
+    assert_eq!(get_effective_sbatlevel(&raw, &[1], &[]), LATEST);
+    // Automatic, and reset while Secure Boot is enabled
+    assert_eq!(get_effective_sbatlevel(&raw, &[2], &[]), PREVIOUS);
+    assert_eq!(get_effective_sbatlevel(&raw, &[3], &[]), PREVIOUS);
+
+    // A SbatLevel at least as recent as the policy is kept
+    assert_eq!(get_effective_sbatlevel(&raw, &[], LATEST), LATEST);
+    assert_eq!(get_effective_sbatlevel(&raw, &[1], LATEST), LATEST);
+    let newer = b"sbat,1,2025051000\nshim,4\ngrub,5\n";
+    assert_eq!(get_effective_sbatlevel(&raw, &[1], newer), newer);
+    // Older or invalid ones are replaced
+    assert_eq!(get_effective_sbatlevel(&raw, &[1], PREVIOUS), LATEST);
+    assert_eq!(
+        get_effective_sbatlevel(&raw, &[], SBAT_VAR_ORIGINAL.as_bytes()),
+        PREVIOUS
+    );
+    assert_eq!(get_effective_sbatlevel(&raw, &[], b"grub,3\n"), PREVIOUS);
+}
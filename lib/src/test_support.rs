@@ -0,0 +1,255 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+
+//! Fixtures shared by the unit tests of several modules: keys and
+//! certificates, signed PE images, signature databases and temporary
+//! directories.
+use openssl::asn1::Asn1Time;
+use openssl::bn::BigNum;
+use openssl::ec::{EcGroup, EcKey};
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkcs7::{Pkcs7, Pkcs7Flags};
+use openssl::pkey::{PKey, Private};
+use openssl::stack::Stack;
+use openssl::x509::extension::BasicConstraints;
+use openssl::x509::{X509, X509NameBuilder};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::authenticode::{
+    OID_SHA256, OPTIONAL_HEADER_MAGIC_PE32_PLUS, PE_OFFSET_OFFSET, PE_SIGNATURE,
+    SECTION_HEADER_SIZE, WIN_CERT_TYPE_PKCS_SIGNED_DATA, WIN_CERTIFICATE_HEADER_SIZE,
+};
+use crate::der::{
+    self, Der, TAG_CONTEXT_0, TAG_INTEGER, TAG_OCTET_STRING, TAG_OID, TAG_SEQUENCE, TAG_SET,
+};
+use crate::uefi::sigdb::{SignatureData, SignatureDatabase, SignatureList, SignatureType};
+
+/// Owner of the signatures of the test signature databases
+pub(crate) const OWNER: Uuid = uuid::uuid!("77fa9abd-0359-4d32-bd60-28f4e78f784b");
+
+const OPTIONAL_HEADER: usize = 0x58;
+pub(crate) const CHECKSUM: usize = OPTIONAL_HEADER + 64;
+pub(crate) const NUMBER_OF_RVA_AND_SIZES: usize = OPTIONAL_HEADER + 108;
+pub(crate) const CERT_DIRECTORY: usize = OPTIONAL_HEADER + 112 + 4 * 8;
+const SIZE_OF_HEADERS: usize = 0x200;
+const TRAILING_DATA: usize = 0x600;
+const CERT_TABLE: usize = 0x610;
+
+/// Directory removed when dropped, even if the test fails
+pub(crate) fn temp_dir() -> tempfile::TempDir {
+    tempfile::Builder::new()
+        .prefix("compute-pcrs-")
+        .tempdir()
+        .unwrap()
+}
+
+pub(crate) fn key() -> PKey<Private> {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+    PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+}
+
+/// Certificate for the key, signed by the issuer or self-signed
+pub(crate) fn cert(cn: &str, key: &PKey<Private>, issuer: Option<(&X509, &PKey<Private>)>) -> X509 {
+    let mut name = X509NameBuilder::new().unwrap();
+    name.append_entry_by_nid(Nid::COMMONNAME, cn).unwrap();
+    let name = name.build();
+    let mut builder = X509::builder().unwrap();
+    builder.set_version(2).unwrap();
+    let serial = BigNum::from_u32(1).unwrap().to_asn1_integer().unwrap();
+    builder.set_serial_number(&serial).unwrap();
+    builder.set_subject_name(&name).unwrap();
+    builder
+        .set_issuer_name(issuer.map_or(&name, |(c, _)| c.subject_name()))
+        .unwrap();
+    builder.set_pubkey(key).unwrap();
+    if issuer.is_none() {
+        let ca = BasicConstraints::new().critical().ca().build().unwrap();
+        builder.append_extension(ca).unwrap();
+    }
+    builder
+        .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+        .unwrap();
+    builder
+        .set_not_after(&Asn1Time::days_from_now(1).unwrap())
+        .unwrap();
+    let signing_key = issuer.map_or(key, |(_, k)| k);
+    builder.sign(signing_key, MessageDigest::sha256()).unwrap();
+    builder.build()
+}
+
+/// Signature database holding a single list with the signatures, all of the
+/// same size
+pub(crate) fn signature_list(signature_type: SignatureType, signatures: &[Vec<u8>]) -> Vec<u8> {
+    let mut list = SignatureList::new(signature_type, signatures[0].len());
+    list.signatures = signatures
+        .iter()
+        .map(|s| SignatureData::new(OWNER, s.clone()))
+        .collect();
+    SignatureDatabase { lists: vec![list] }.encode().unwrap()
+}
+
+/// shim .sbatlevel section: version, then the previous and latest policy
+/// offsets relative to the end of the version field
+pub(crate) fn sbatlevel_section(previous: &[u8], latest: &[u8]) -> Vec<u8> {
+    let previous_offset = 8u32;
+    let latest_offset = previous_offset + previous.len() as u32 + 1;
+    [
+        0u32.to_le_bytes().as_slice(),
+        &previous_offset.to_le_bytes(),
+        &latest_offset.to_le_bytes(),
+        previous,
+        b"\0",
+        latest,
+        b"\0",
+    ]
+    .concat()
+}
+
+pub(crate) fn put(image: &mut [u8], offset: usize, value: &[u8]) {
+    image[offset..offset + value.len()].copy_from_slice(value);
+}
+
+/// Content of the SpcIndirectDataContent of an Authenticode signature over
+/// the digest
+fn indirect_data_content(digest: &[u8]) -> Vec<u8> {
+    let digest_info = der::encode(
+        TAG_SEQUENCE,
+        &[
+            der::encode(TAG_SEQUENCE, &der::encode(TAG_OID, OID_SHA256)),
+            der::encode(TAG_OCTET_STRING, digest),
+        ]
+        .concat(),
+    );
+    [der::encode(TAG_SEQUENCE, &[]), digest_info].concat()
+}
+
+/// Authenticode PKCS#7 SignedData holding only what signed_digest reads
+pub(crate) fn signature(digest: &[u8]) -> Vec<u8> {
+    let indirect_data = der::encode(TAG_SEQUENCE, &indirect_data_content(digest));
+    let content_info = der::encode(
+        TAG_SEQUENCE,
+        &[
+            der::encode(TAG_OID, &[1]),
+            der::encode(TAG_CONTEXT_0, &indirect_data),
+        ]
+        .concat(),
+    );
+    let signed_data = der::encode(
+        TAG_SEQUENCE,
+        &[
+            der::encode(TAG_INTEGER, &[1]),
+            der::encode(TAG_SET, &[]),
+            content_info,
+        ]
+        .concat(),
+    );
+    der::encode(
+        TAG_SEQUENCE,
+        &[
+            der::encode(TAG_OID, &[2]),
+            der::encode(TAG_CONTEXT_0, &signed_data),
+        ]
+        .concat(),
+    )
+}
+
+/// PE32+ image with two sections listed out of file order, trailing data
+/// and, if given, a certificate table holding a signature
+pub(crate) fn image(signature: Option<&[u8]>) -> Vec<u8> {
+    let mut image: Vec<u8> = (0..CERT_TABLE).map(|i| (i % 251) as u8).collect();
+    put(&mut image, PE_OFFSET_OFFSET, &0x40u32.to_le_bytes());
+    put(&mut image, 0x40, PE_SIGNATURE);
+    put(&mut image, 0x44 + 2, &2u16.to_le_bytes());
+    put(&mut image, 0x44 + 16, &240u16.to_le_bytes());
+    put(
+        &mut image,
+        OPTIONAL_HEADER,
+        &OPTIONAL_HEADER_MAGIC_PE32_PLUS.to_le_bytes(),
+    );
+    put(
+        &mut image,
+        OPTIONAL_HEADER + 60,
+        &(SIZE_OF_HEADERS as u32).to_le_bytes(),
+    );
+    put(&mut image, NUMBER_OF_RVA_AND_SIZES, &16u32.to_le_bytes());
+    put(&mut image, CERT_DIRECTORY, &[0; 8]);
+    let section_table = OPTIONAL_HEADER + 240;
+    for (i, offset) in [0x400u32, 0x200].iter().enumerate() {
+        let header = section_table + i * SECTION_HEADER_SIZE;
+        put(&mut image, header + 16, &0x200u32.to_le_bytes());
+        put(&mut image, header + 20, &offset.to_le_bytes());
+    }
+
+    if let Some(signature) = signature {
+        let length = WIN_CERTIFICATE_HEADER_SIZE + signature.len();
+        image.extend((length as u32).to_le_bytes());
+        image.extend(0x0200u16.to_le_bytes());
+        image.extend(WIN_CERT_TYPE_PKCS_SIGNED_DATA.to_le_bytes());
+        image.extend(signature);
+        image.resize(CERT_TABLE + length.next_multiple_of(8), 0);
+        let size = (image.len() - CERT_TABLE) as u32;
+        put(
+            &mut image,
+            CERT_DIRECTORY,
+            &(CERT_TABLE as u32).to_le_bytes(),
+        );
+        put(&mut image, CERT_DIRECTORY + 4, &size.to_le_bytes());
+    }
+    image
+}
+
+pub(crate) fn expected_digest(image: &[u8]) -> Vec<u8> {
+    Sha256::digest(
+        [
+            &image[..CHECKSUM],
+            &image[CHECKSUM + 4..CERT_DIRECTORY],
+            &image[CERT_DIRECTORY + 8..SIZE_OF_HEADERS],
+            &image[0x200..0x400],
+            &image[0x400..0x600],
+            &image[TRAILING_DATA..CERT_TABLE],
+        ]
+        .concat(),
+    )
+    .to_vec()
+}
+
+/// Signs the SpcIndirectDataContent of an Authenticode signature with
+/// openssl, then moves it from a detached id-data content into the
+/// SignedData, as signtool and sbsign do
+pub(crate) fn authenticode_signature(digest: &[u8], signer: &X509, key: &PKey<Private>) -> Vec<u8> {
+    let content = indirect_data_content(digest);
+    let certs = Stack::new().unwrap();
+    let p7 = Pkcs7::sign(
+        signer,
+        key,
+        &certs,
+        &content,
+        Pkcs7Flags::BINARY | Pkcs7Flags::DETACHED,
+    )
+    .unwrap()
+    .to_der()
+    .unwrap();
+
+    let outer: Vec<_> = Der::new(&p7).next().unwrap().children().collect();
+    let signed_data = outer[1].children().next().unwrap();
+    let mut fields: Vec<Vec<u8>> = signed_data.children().map(|t| t.raw.to_vec()).collect();
+    fields[2] = der::encode(
+        TAG_SEQUENCE,
+        &[
+            der::encode(TAG_OID, &[3]),
+            der::encode(TAG_CONTEXT_0, &der::encode(TAG_SEQUENCE, &content)),
+        ]
+        .concat(),
+    );
+    der::encode(
+        TAG_SEQUENCE,
+        &[
+            outer[0].raw.to_vec(),
+            der::encode(TAG_CONTEXT_0, &der::encode(TAG_SEQUENCE, &fields.concat())),
+        ]
+        .concat(),
+    )
+}
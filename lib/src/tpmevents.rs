@@ -3,12 +3,17 @@
 // SPDX-License-Identifier: MIT
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
+use std::hash::{Hash, Hasher};
 use strum::FromRepr;
 
+pub mod cel;
 pub mod combine;
 pub mod compute;
+pub mod eventlog;
+mod model;
 #[cfg(test)]
 mod tests;
+pub mod tpm2_eventlog;
 mod tree;
 
 // Event group definitions
@@ -21,6 +26,15 @@ pub const TPMEG_MOKVARS: u32 = 1 << 4; // Events depending on MOK variables
 pub const TPMEG_UKI: u32 = 1 << 5; // Events depending on UKI
 pub const TPMEG_ALWAYS: u32 = u32::MAX; // Events that always change
 
+// Event groups an event can be sourced from when combining images
+pub const TPMEG_NAMED: [(u32, &str); 5] = [
+    (TPMEG_LINUX, "TPMEG_LINUX"),
+    (TPMEG_BOOTLOADER, "TPMEG_BOOTLOADER"),
+    (TPMEG_SECUREBOOT, "TPMEG_SECUREBOOT"),
+    (TPMEG_MOKVARS, "TPMEG_MOKVARS"),
+    (TPMEG_UKI, "TPMEG_UKI"),
+];
+
 #[derive(Clone, Serialize, Deserialize, Hash, PartialEq, Eq, FromRepr, Debug)]
 pub enum TPMEventID {
     PcrRootNodeEvent, // Don't use it except for TPM Event combination
@@ -40,6 +54,9 @@ pub enum TPMEventID {
     Pcr7GrubDbCert,
     Pcr7GrubVendorDbCert,
     Pcr7GrubMokListCert,
+    Pcr7KernelDbCert,
+    Pcr7KernelVendorDbCert,
+    Pcr7KernelMokListCert,
     Pcr11Linux,
     Pcr11LinuxContent,
     Pcr11Osrel,
@@ -79,6 +96,9 @@ impl TPMEventID {
             TPMEventID::Pcr7GrubDbCert => TPMEG_SECUREBOOT | TPMEG_BOOTLOADER,
             TPMEventID::Pcr7GrubVendorDbCert => TPMEG_SECUREBOOT | TPMEG_BOOTLOADER,
             TPMEventID::Pcr7GrubMokListCert => TPMEG_SECUREBOOT | TPMEG_BOOTLOADER | TPMEG_MOKVARS,
+            TPMEventID::Pcr7KernelDbCert => TPMEG_SECUREBOOT | TPMEG_LINUX,
+            TPMEventID::Pcr7KernelVendorDbCert => TPMEG_SECUREBOOT | TPMEG_LINUX,
+            TPMEventID::Pcr7KernelMokListCert => TPMEG_SECUREBOOT | TPMEG_LINUX | TPMEG_MOKVARS,
             TPMEventID::Pcr11Linux => TPMEG_UKI,
             TPMEventID::Pcr11LinuxContent => TPMEG_UKI,
             TPMEventID::Pcr11Osrel => TPMEG_UKI,
@@ -98,18 +118,255 @@ impl TPMEventID {
         }
     }
 
+    /// Type of the event logged for this event
+    pub fn event_type(&self) -> EventType {
+        match *self {
+            TPMEventID::PcrRootNodeEvent => EventType::NoAction,
+            TPMEventID::Pcr4EfiCall => EventType::EfiAction,
+            TPMEventID::Pcr4Separator => EventType::Separator,
+            TPMEventID::Pcr4Shim => EventType::EfiBootServicesApplication,
+            TPMEventID::Pcr4Grub => EventType::EfiBootServicesApplication,
+            TPMEventID::Pcr4Vmlinuz => EventType::EfiBootServicesApplication,
+            TPMEventID::Pcr7SecureBoot => EventType::EfiVariableDriverConfig,
+            TPMEventID::Pcr7Pk => EventType::EfiVariableDriverConfig,
+            TPMEventID::Pcr7Kek => EventType::EfiVariableDriverConfig,
+            TPMEventID::Pcr7Db => EventType::EfiVariableDriverConfig,
+            TPMEventID::Pcr7Dbx => EventType::EfiVariableDriverConfig,
+            TPMEventID::Pcr7Separator => EventType::Separator,
+            TPMEventID::Pcr7ShimCert => EventType::EfiVariableAuthority,
+            TPMEventID::Pcr7SbatLevel => EventType::EfiVariableAuthority,
+            TPMEventID::Pcr7GrubDbCert => EventType::EfiVariableAuthority,
+            TPMEventID::Pcr7GrubVendorDbCert => EventType::EfiVariableAuthority,
+            TPMEventID::Pcr7GrubMokListCert => EventType::EfiVariableAuthority,
+            TPMEventID::Pcr7KernelDbCert => EventType::EfiVariableAuthority,
+            TPMEventID::Pcr7KernelVendorDbCert => EventType::EfiVariableAuthority,
+            TPMEventID::Pcr7KernelMokListCert => EventType::EfiVariableAuthority,
+            TPMEventID::Pcr11Linux => EventType::Ipl,
+            TPMEventID::Pcr11LinuxContent => EventType::Ipl,
+            TPMEventID::Pcr11Osrel => EventType::Ipl,
+            TPMEventID::Pcr11OsrelContent => EventType::Ipl,
+            TPMEventID::Pcr11Cmdline => EventType::Ipl,
+            TPMEventID::Pcr11CmdlineContent => EventType::Ipl,
+            TPMEventID::Pcr11Initrd => EventType::Ipl,
+            TPMEventID::Pcr11InitrdContent => EventType::Ipl,
+            TPMEventID::Pcr11Uname => EventType::Ipl,
+            TPMEventID::Pcr11UnameContent => EventType::Ipl,
+            TPMEventID::Pcr11Sbat => EventType::Ipl,
+            TPMEventID::Pcr11SbatContent => EventType::Ipl,
+            TPMEventID::Pcr14MokList => EventType::Ipl,
+            TPMEventID::Pcr14MokListX => EventType::Ipl,
+            TPMEventID::Pcr14MokListTrusted => EventType::Ipl,
+            TPMEventID::PcrLastNodeEvent => EventType::NoAction,
+        }
+    }
+
+    /// Name of PCR 11 events: the UKI section they measure, suffixed with
+    /// _CONTENT for the measurement of its contents
+    pub fn section_name(&self) -> Option<&'static str> {
+        match *self {
+            TPMEventID::Pcr11Linux => Some(".linux"),
+            TPMEventID::Pcr11LinuxContent => Some(".linux_CONTENT"),
+            TPMEventID::Pcr11Osrel => Some(".osrel"),
+            TPMEventID::Pcr11OsrelContent => Some(".osrel_CONTENT"),
+            TPMEventID::Pcr11Cmdline => Some(".cmdline"),
+            TPMEventID::Pcr11CmdlineContent => Some(".cmdline_CONTENT"),
+            TPMEventID::Pcr11Initrd => Some(".initrd"),
+            TPMEventID::Pcr11InitrdContent => Some(".initrd_CONTENT"),
+            TPMEventID::Pcr11Uname => Some(".uname"),
+            TPMEventID::Pcr11UnameContent => Some(".uname_CONTENT"),
+            TPMEventID::Pcr11Sbat => Some(".sbat"),
+            TPMEventID::Pcr11SbatContent => Some(".sbat_CONTENT"),
+            _ => None,
+        }
+    }
+
     pub fn next(&self) -> Option<Self> {
         let self_val = self.clone() as usize;
         Self::from_repr(self_val + 1)
     }
 }
 
-#[serde_as]
-#[derive(Clone, Serialize, Deserialize, PartialEq, Debug, Eq, Hash)]
+/// TCG PC Client event types, serialised with their specification names
+#[derive(Clone, Copy, Serialize, Deserialize, Hash, PartialEq, Eq, FromRepr, Debug)]
+#[repr(u32)]
+pub enum EventType {
+    #[serde(rename = "EV_PREBOOT_CERT")]
+    PrebootCert = 0x0,
+    #[serde(rename = "EV_POST_CODE")]
+    PostCode = 0x1,
+    #[serde(rename = "EV_NO_ACTION")]
+    NoAction = 0x3,
+    #[serde(rename = "EV_SEPARATOR")]
+    Separator = 0x4,
+    #[serde(rename = "EV_ACTION")]
+    Action = 0x5,
+    #[serde(rename = "EV_EVENT_TAG")]
+    EventTag = 0x6,
+    #[serde(rename = "EV_S_CRTM_CONTENTS")]
+    SCrtmContents = 0x7,
+    #[serde(rename = "EV_S_CRTM_VERSION")]
+    SCrtmVersion = 0x8,
+    #[serde(rename = "EV_CPU_MICROCODE")]
+    CpuMicrocode = 0x9,
+    #[serde(rename = "EV_PLATFORM_CONFIG_FLAGS")]
+    PlatformConfigFlags = 0xa,
+    #[serde(rename = "EV_TABLE_OF_DEVICES")]
+    TableOfDevices = 0xb,
+    #[serde(rename = "EV_COMPACT_HASH")]
+    CompactHash = 0xc,
+    #[serde(rename = "EV_IPL")]
+    Ipl = 0xd,
+    #[serde(rename = "EV_IPL_PARTITION_DATA")]
+    IplPartitionData = 0xe,
+    #[serde(rename = "EV_NONHOST_CODE")]
+    NonhostCode = 0xf,
+    #[serde(rename = "EV_NONHOST_CONFIG")]
+    NonhostConfig = 0x10,
+    #[serde(rename = "EV_NONHOST_INFO")]
+    NonhostInfo = 0x11,
+    #[serde(rename = "EV_OMIT_BOOT_DEVICE_EVENTS")]
+    OmitBootDeviceEvents = 0x12,
+    #[serde(rename = "EV_POST_CODE2")]
+    PostCode2 = 0x13,
+    #[serde(rename = "EV_EFI_VARIABLE_DRIVER_CONFIG")]
+    EfiVariableDriverConfig = 0x80000001,
+    #[serde(rename = "EV_EFI_VARIABLE_BOOT")]
+    EfiVariableBoot = 0x80000002,
+    #[serde(rename = "EV_EFI_BOOT_SERVICES_APPLICATION")]
+    EfiBootServicesApplication = 0x80000003,
+    #[serde(rename = "EV_EFI_BOOT_SERVICES_DRIVER")]
+    EfiBootServicesDriver = 0x80000004,
+    #[serde(rename = "EV_EFI_RUNTIME_SERVICES_DRIVER")]
+    EfiRuntimeServicesDriver = 0x80000005,
+    #[serde(rename = "EV_EFI_GPT_EVENT")]
+    EfiGptEvent = 0x80000006,
+    #[serde(rename = "EV_EFI_ACTION")]
+    EfiAction = 0x80000007,
+    #[serde(rename = "EV_EFI_PLATFORM_FIRMWARE_BLOB")]
+    EfiPlatformFirmwareBlob = 0x80000008,
+    #[serde(rename = "EV_EFI_HANDOFF_TABLES")]
+    EfiHandoffTables = 0x80000009,
+    #[serde(rename = "EV_EFI_PLATFORM_FIRMWARE_BLOB2")]
+    EfiPlatformFirmwareBlob2 = 0x8000000a,
+    #[serde(rename = "EV_EFI_HANDOFF_TABLES2")]
+    EfiHandoffTables2 = 0x8000000b,
+    #[serde(rename = "EV_EFI_VARIABLE_BOOT2")]
+    EfiVariableBoot2 = 0x8000000c,
+    #[serde(rename = "EV_EFI_GPT_EVENT2")]
+    EfiGptEvent2 = 0x8000000d,
+    #[serde(rename = "EV_EFI_HCRTM_EVENT")]
+    EfiHcrtmEvent = 0x80000010,
+    #[serde(rename = "EV_EFI_VARIABLE_AUTHORITY")]
+    EfiVariableAuthority = 0x800000e0,
+    #[serde(rename = "EV_EFI_SPDM_FIRMWARE_BLOB")]
+    EfiSpdmFirmwareBlob = 0x800000e1,
+    #[serde(rename = "EV_EFI_SPDM_FIRMWARE_CONFIG")]
+    EfiSpdmFirmwareConfig = 0x800000e2,
+}
+
+#[derive(Clone, Debug)]
 pub struct TPMEvent {
-    pub name: String,
+    /// Serialised as "name", see EventName
+    pub event_type: EventType,
     pub pcr: u8,
-    #[serde_as(as = "serde_with::hex::Hex")]
     pub hash: Vec<u8>,
     pub id: TPMEventID,
+    /// Bytes hashed into the digest, e.g. the UEFI_VARIABLE_DATA of variable
+    /// events or the MokList contents, so sha256(data) is the hash. It may
+    /// differ from what the event is logged with: shim logs "MokList\0"
+    /// along the hash of the MokList contents. Left empty for PE images, as
+    /// their Authenticode hash skips parts of the image.
+    ///
+    /// Not compared nor hashed: events are the same if their digests are.
+    pub data: Option<Vec<u8>>,
+}
+
+/// Name events are serialised with: the TCG event type, except for PCR 11
+/// events, which are named after the UKI section they measure. Names that
+/// are not event types, as PCR 11 ones, are read as the event type of the
+/// TPMEventID of the event.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum EventName {
+    EventType(EventType),
+    Other(String),
+}
+
+#[serde_as]
+#[derive(Serialize)]
+struct SerializedTPMEvent<'a> {
+    name: EventName,
+    pcr: u8,
+    #[serde_as(as = "serde_with::hex::Hex")]
+    hash: &'a [u8],
+    id: &'a TPMEventID,
+    #[serde_as(as = "Option<serde_with::hex::Hex>")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<&'a [u8]>,
+}
+
+#[serde_as]
+#[derive(Deserialize)]
+struct DeserializedTPMEvent {
+    name: EventName,
+    pcr: u8,
+    #[serde_as(as = "serde_with::hex::Hex")]
+    hash: Vec<u8>,
+    id: TPMEventID,
+    #[serde_as(as = "Option<serde_with::hex::Hex>")]
+    #[serde(default)]
+    data: Option<Vec<u8>>,
+}
+
+impl Serialize for TPMEvent {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let name = match self.id.section_name() {
+            Some(name) => EventName::Other(name.into()),
+            None => EventName::EventType(self.event_type),
+        };
+        SerializedTPMEvent {
+            name,
+            pcr: self.pcr,
+            hash: &self.hash,
+            id: &self.id,
+            data: self.data.as_deref(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TPMEvent {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let event = DeserializedTPMEvent::deserialize(deserializer)?;
+        Ok(TPMEvent {
+            event_type: match event.name {
+                EventName::EventType(event_type) => event_type,
+                EventName::Other(_) => event.id.event_type(),
+            },
+            pcr: event.pcr,
+            hash: event.hash,
+            id: event.id,
+            data: event.data,
+        })
+    }
+}
+
+impl PartialEq for TPMEvent {
+    fn eq(&self, other: &Self) -> bool {
+        self.event_type == other.event_type
+            && self.pcr == other.pcr
+            && self.hash == other.hash
+            && self.id == other.id
+    }
+}
+
+impl Eq for TPMEvent {}
+
+impl Hash for TPMEvent {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.event_type.hash(state);
+        self.pcr.hash(state);
+        self.hash.hash(state);
+        self.id.hash(state);
+    }
 }
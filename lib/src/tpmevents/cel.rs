@@ -0,0 +1,369 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+
+//! TCG Canonical Event Log (CEL) import and export.
+//!
+//! Computed events are written as CEL records of the `pcclient_std` content
+//! type, in their JSON or CBOR encodings. When reading a CEL log back, the
+//! TPMEventID of each record is inferred from its PCR, its event type and,
+//! for UEFI variable events, the variable name in its event data. Records
+//! of NV indices and of PCRs that are not computed by this crate are
+//! skipped.
+//!
+//! CEL-JSON names record fields, content types and hash algorithms, while
+//! CEL-CBOR identifies them by number: record fields and content types by
+//! their CEL type, and hash algorithms by their TPM_ALG_ID.
+use ciborium::Value;
+use serde::{Deserialize, Serialize};
+use serde_with::base64::Base64;
+use serde_with::hex::Hex;
+use serde_with::serde_as;
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+use crate::tpmevents::model::{EventMatcher, EventModel};
+use crate::tpmevents::{TPMEvent, TPMEventID};
+
+const CEL_HASH_ALG_SHA256: &str = "sha256";
+const CEL_CONTENT_PCCLIENT_STD: &str = "pcclient_std";
+
+/// CEL types of the record fields in CEL-CBOR
+const CEL_TYPE_RECNUM: u64 = 0;
+const CEL_TYPE_PCR: u64 = 1;
+const CEL_TYPE_NV_INDEX: u64 = 2;
+const CEL_TYPE_DIGESTS: u64 = 3;
+/// CEL types of the content types in CEL-CBOR, keying the record content
+const CEL_CONTENT_TYPES: [(u64, &str); 4] = [
+    (4, "cel_mgmt"),
+    (5, CEL_CONTENT_PCCLIENT_STD),
+    (6, "ima_template"),
+    (7, "ima_tlv"),
+];
+/// Fields of pcclient_std contents in CEL-CBOR
+const PCCLIENT_STD_EVENT_TYPE: u64 = 0;
+const PCCLIENT_STD_EVENT_DATA: u64 = 1;
+/// TPM_ALG_ID of the hash algorithms, which key the digests in CEL-CBOR
+const TPM_ALG_IDS: [(u64, &str); 5] = [
+    (0x0004, "sha1"),
+    (0x000B, CEL_HASH_ALG_SHA256),
+    (0x000C, "sha384"),
+    (0x000D, "sha512"),
+    (0x0012, "sm3_256"),
+];
+
+#[serde_as]
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct CelDigest {
+    #[serde(rename = "hashAlg")]
+    pub hash_alg: String,
+    #[serde_as(as = "Hex")]
+    pub digest: Vec<u8>,
+}
+
+/// Content of `pcclient_std` records. Other content types, such as
+/// `cel_mgmt` or `ima_template`, are parsed with both fields unset.
+#[serde_as]
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct CelContent {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_type: Option<u32>,
+    #[serde_as(as = "Option<Base64>")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_data: Option<Vec<u8>>,
+}
+
+/// A CEL record, which extends either a PCR or an NV index
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct CelRecord {
+    pub recnum: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pcr: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nv_index: Option<u32>,
+    pub digests: Vec<CelDigest>,
+    pub content_type: String,
+    pub content: CelContent,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CelError {
+    /// The log could not be decoded from its JSON or CBOR encoding
+    Decode(String),
+    /// The record has no sha256 digest
+    MissingDigest(u64),
+    /// The pcclient_std record has no event type
+    MissingEventType(u64),
+    /// The record does not match any event expected at its position
+    UnknownEvent(u64),
+    /// The event is not logged as such by any component, e.g. the root node
+    /// of event trees
+    UnloggableEvent(TPMEventID),
+}
+
+impl fmt::Display for CelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CelError::Decode(e) => write!(f, "Error decoding CEL log: {e}"),
+            CelError::MissingDigest(recnum) => {
+                write!(f, "CEL record {recnum} has no sha256 digest")
+            }
+            CelError::MissingEventType(recnum) => {
+                write!(f, "CEL record {recnum} has no event type")
+            }
+            CelError::UnknownEvent(recnum) => {
+                write!(f, "CEL record {recnum} does not match any expected event")
+            }
+            CelError::UnloggableEvent(id) => write!(f, "{id:?} is not a loggable event"),
+        }
+    }
+}
+
+impl std::error::Error for CelError {}
+
+/// Converts the events into CEL records, numbered in the order given, with
+/// the event data the event would be logged with.
+pub fn to_cel(events: &[TPMEvent]) -> Result<Vec<CelRecord>, CelError> {
+    events
+        .iter()
+        .enumerate()
+        .map(|(recnum, event)| {
+            let model = EventModel::of(&event.id)
+                .ok_or_else(|| CelError::UnloggableEvent(event.id.clone()))?;
+            Ok(CelRecord {
+                recnum: recnum as u64,
+                pcr: Some(event.pcr),
+                nv_index: None,
+                digests: vec![CelDigest {
+                    hash_alg: CEL_HASH_ALG_SHA256.into(),
+                    digest: event.hash.clone(),
+                }],
+                content_type: CEL_CONTENT_PCCLIENT_STD.into(),
+                content: CelContent {
+                    event_type: Some(event.event_type as u32),
+                    event_data: Some(model.event_data(event)),
+                },
+            })
+        })
+        .collect()
+}
+
+/// Converts CEL records back into events.
+///
+/// Records are expected in the order the TPM logged them, though records of
+/// different PCRs can be interleaved.
+pub fn from_cel(records: &[CelRecord]) -> Result<Vec<TPMEvent>, CelError> {
+    let mut matcher = EventMatcher::default();
+    let mut events = vec![];
+
+    for record in records {
+        let Some(pcr) = record.pcr else {
+            continue;
+        };
+        if record.content_type != CEL_CONTENT_PCCLIENT_STD || !EventMatcher::is_computed_pcr(pcr) {
+            continue;
+        }
+        let event_type = record
+            .content
+            .event_type
+            .ok_or(CelError::MissingEventType(record.recnum))?;
+        let event_data = record.content.event_data.as_deref().unwrap_or_default();
+        let hash = record
+            .digests
+            .iter()
+            .find(|d| d.hash_alg == CEL_HASH_ALG_SHA256)
+            .ok_or(CelError::MissingDigest(record.recnum))?
+            .digest
+            .clone();
+
+        let (id, model) = matcher
+            .next_match(pcr, event_type, event_data)
+            .ok_or(CelError::UnknownEvent(record.recnum))?;
+        events.push(TPMEvent {
+            event_type: model.event_type,
+            pcr,
+            hash: hash.clone(),
+            id,
+            // Only keep the event data when it is what was hashed
+            data: (Sha256::digest(event_data).as_slice() == hash.as_slice())
+                .then(|| event_data.to_vec()),
+        });
+    }
+
+    Ok(events)
+}
+
+/// Serialises the events as a CEL-JSON log
+pub fn to_cel_json(events: &[TPMEvent]) -> Result<String, CelError> {
+    Ok(serde_json::to_string_pretty(&to_cel(events)?).unwrap())
+}
+
+/// Parses the events of a CEL-JSON log
+pub fn from_cel_json(log: &str) -> Result<Vec<TPMEvent>, CelError> {
+    let records: Vec<CelRecord> =
+        serde_json::from_str(log).map_err(|e| CelError::Decode(e.to_string()))?;
+    from_cel(&records)
+}
+
+fn cbor_uint(value: u64) -> Value {
+    Value::Integer(value.into())
+}
+
+/// Encodes a record as a CEL-CBOR map keyed by CEL types. Digests of hash
+/// algorithms without a TPM_ALG_ID are left out.
+fn record_to_cbor(record: &CelRecord) -> Value {
+    let mut fields = vec![(cbor_uint(CEL_TYPE_RECNUM), cbor_uint(record.recnum))];
+    if let Some(pcr) = record.pcr {
+        fields.push((cbor_uint(CEL_TYPE_PCR), cbor_uint(pcr.into())));
+    }
+    if let Some(nv_index) = record.nv_index {
+        fields.push((cbor_uint(CEL_TYPE_NV_INDEX), cbor_uint(nv_index.into())));
+    }
+    let digests = record
+        .digests
+        .iter()
+        .filter_map(|d| {
+            let (alg_id, _) = TPM_ALG_IDS.iter().find(|(_, name)| *name == d.hash_alg)?;
+            Some((cbor_uint(*alg_id), Value::Bytes(d.digest.clone())))
+        })
+        .collect();
+    fields.push((cbor_uint(CEL_TYPE_DIGESTS), Value::Map(digests)));
+
+    if let Some((content_type, _)) = CEL_CONTENT_TYPES
+        .iter()
+        .find(|(_, name)| *name == record.content_type)
+    {
+        let mut content = vec![];
+        if let Some(event_type) = record.content.event_type {
+            content.push((
+                cbor_uint(PCCLIENT_STD_EVENT_TYPE),
+                cbor_uint(event_type.into()),
+            ));
+        }
+        if let Some(event_data) = &record.content.event_data {
+            content.push((
+                cbor_uint(PCCLIENT_STD_EVENT_DATA),
+                Value::Bytes(event_data.clone()),
+            ));
+        }
+        fields.push((cbor_uint(*content_type), Value::Map(content)));
+    }
+    Value::Map(fields)
+}
+
+/// Map entries keyed by unsigned integers, as all CEL-CBOR maps are
+fn cbor_map(value: Value) -> Result<Vec<(u64, Value)>, CelError> {
+    value
+        .into_map()
+        .map_err(|_| CelError::Decode("Expected a map".into()))?
+        .into_iter()
+        .map(|(k, v)| Ok((cbor_to_uint(k)?, v)))
+        .collect()
+}
+
+fn cbor_to_uint(value: Value) -> Result<u64, CelError> {
+    value
+        .as_integer()
+        .and_then(|i| u64::try_from(i).ok())
+        .ok_or_else(|| CelError::Decode(format!("Expected an unsigned integer, got {value:?}")))
+}
+
+fn cbor_to_bytes(value: Value) -> Result<Vec<u8>, CelError> {
+    value
+        .into_bytes()
+        .map_err(|v| CelError::Decode(format!("Expected a byte string, got {v:?}")))
+}
+
+/// Decodes a CEL-CBOR record. Contents other than pcclient_std are parsed
+/// with both content fields unset.
+fn record_from_cbor(value: Value) -> Result<CelRecord, CelError> {
+    let mut recnum = None;
+    let mut record = CelRecord {
+        recnum: 0,
+        pcr: None,
+        nv_index: None,
+        digests: vec![],
+        content_type: String::new(),
+        content: CelContent {
+            event_type: None,
+            event_data: None,
+        },
+    };
+    let integer = |value: Value| -> Result<u32, CelError> {
+        let value = cbor_to_uint(value)?;
+        u32::try_from(value).map_err(|_| CelError::Decode(format!("{value} is out of range")))
+    };
+
+    for (key, value) in cbor_map(value)? {
+        match key {
+            CEL_TYPE_RECNUM => recnum = Some(cbor_to_uint(value)?),
+            CEL_TYPE_PCR => {
+                let pcr = integer(value)?;
+                record.pcr = Some(
+                    u8::try_from(pcr)
+                        .map_err(|_| CelError::Decode(format!("Invalid PCR {pcr}")))?,
+                );
+            }
+            CEL_TYPE_NV_INDEX => record.nv_index = Some(integer(value)?),
+            CEL_TYPE_DIGESTS => {
+                for (alg_id, digest) in cbor_map(value)? {
+                    let hash_alg = TPM_ALG_IDS
+                        .iter()
+                        .find(|(id, _)| *id == alg_id)
+                        .map_or_else(|| format!("{alg_id:#06x}"), |(_, name)| name.to_string());
+                    record.digests.push(CelDigest {
+                        hash_alg,
+                        digest: cbor_to_bytes(digest)?,
+                    });
+                }
+            }
+            _ => {
+                let Some((_, content_type)) = CEL_CONTENT_TYPES.iter().find(|(t, _)| *t == key)
+                else {
+                    return Err(CelError::Decode(format!("Unknown CEL type {key}")));
+                };
+                record.content_type = content_type.to_string();
+                if *content_type != CEL_CONTENT_PCCLIENT_STD {
+                    continue;
+                }
+                for (field, value) in cbor_map(value)? {
+                    match field {
+                        PCCLIENT_STD_EVENT_TYPE => {
+                            record.content.event_type = Some(integer(value)?)
+                        }
+                        PCCLIENT_STD_EVENT_DATA => {
+                            record.content.event_data = Some(cbor_to_bytes(value)?)
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    record.recnum = recnum.ok_or_else(|| CelError::Decode("Record without recnum".into()))?;
+    Ok(record)
+}
+
+/// Serialises the events as a CEL-CBOR log
+pub fn to_cel_cbor(events: &[TPMEvent]) -> Result<Vec<u8>, CelError> {
+    let records = to_cel(events)?.iter().map(record_to_cbor).collect();
+    let mut log = vec![];
+    ciborium::into_writer(&Value::Array(records), &mut log).unwrap();
+    Ok(log)
+}
+
+/// Parses the events of a CEL-CBOR log
+pub fn from_cel_cbor(log: &[u8]) -> Result<Vec<TPMEvent>, CelError> {
+    let value: Value = ciborium::from_reader(log).map_err(|e| CelError::Decode(e.to_string()))?;
+    let records = value
+        .into_array()
+        .map_err(|_| CelError::Decode("Expected an array of records".into()))?
+        .into_iter()
+        .map(record_from_cbor)
+        .collect::<Result<Vec<_>, _>>()?;
+    from_cel(&records)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,281 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+use super::*;
+use crate::tpmevents::{EventType, TPMEventID};
+use crate::uefi;
+
+fn event(pcr: u8, hash: u8, id: TPMEventID) -> TPMEvent {
+    TPMEvent {
+        event_type: id.event_type(),
+        pcr,
+        hash: vec![hash; 32],
+        id,
+        data: None,
+    }
+}
+
+fn events() -> Vec<TPMEvent> {
+    vec![
+        event(4, 0, TPMEventID::Pcr4EfiCall),
+        event(4, 1, TPMEventID::Pcr4Separator),
+        event(4, 2, TPMEventID::Pcr4Shim),
+        event(4, 3, TPMEventID::Pcr4Grub),
+        event(7, 4, TPMEventID::Pcr7SecureBoot),
+        event(7, 5, TPMEventID::Pcr7Separator),
+        event(7, 6, TPMEventID::Pcr7ShimCert),
+        event(7, 7, TPMEventID::Pcr7SbatLevel),
+        event(14, 8, TPMEventID::Pcr14MokList),
+        event(14, 9, TPMEventID::Pcr14MokListX),
+    ]
+}
+
+fn record(recnum: u64, pcr: u8, event_type: EventType, event_data: Vec<u8>) -> CelRecord {
+    CelRecord {
+        recnum,
+        pcr: Some(pcr),
+        nv_index: None,
+        digests: vec![CelDigest {
+            hash_alg: "sha256".into(),
+            digest: vec![recnum as u8; 32],
+        }],
+        content_type: "pcclient_std".into(),
+        content: CelContent {
+            event_type: Some(event_type as u32),
+            event_data: Some(event_data),
+        },
+    }
+}
+
+#[test]
+fn test_to_cel_json() {
+    let events = vec![TPMEvent {
+        event_type: EventType::Separator,
+        pcr: 7,
+        hash: vec![0xdf, 0x3f],
+        id: TPMEventID::Pcr7Separator,
+        data: None,
+    }];
+    let records: serde_json::Value = serde_json::from_str(&to_cel_json(&events).unwrap()).unwrap();
+
+    assert_eq!(
+        records,
+        serde_json::json!([{
+            "recnum": 0,
+            "pcr": 7,
+            "digests": [{"hashAlg": "sha256", "digest": "df3f"}],
+            "content_type": "pcclient_std",
+            "content": {"event_type": 4, "event_data": "AAAAAA=="},
+        }])
+    );
+}
+
+#[test]
+fn test_cel_json_roundtrip() {
+    assert_eq!(
+        from_cel_json(&to_cel_json(&events()).unwrap()).unwrap(),
+        events()
+    );
+}
+
+#[test]
+fn test_cel_cbor_roundtrip() {
+    assert_eq!(
+        from_cel_cbor(&to_cel_cbor(&events()).unwrap()).unwrap(),
+        events()
+    );
+}
+
+#[test]
+fn test_to_cel_cbor() {
+    let events = vec![TPMEvent {
+        event_type: EventType::Separator,
+        pcr: 7,
+        hash: vec![0xdf, 0x3f],
+        id: TPMEventID::Pcr7Separator,
+        data: None,
+    }];
+    let log: Value = ciborium::from_reader(to_cel_cbor(&events).unwrap().as_slice()).unwrap();
+    let uint = |v: u64| Value::Integer(v.into());
+
+    assert_eq!(
+        log,
+        Value::Array(vec![Value::Map(vec![
+            (uint(0), uint(0)),
+            (uint(1), uint(7)),
+            (
+                uint(3),
+                Value::Map(vec![(uint(0x000B), Value::Bytes(vec![0xdf, 0x3f]))])
+            ),
+            (
+                uint(5),
+                Value::Map(vec![
+                    (uint(0), uint(4)),
+                    (uint(1), Value::Bytes(vec![0; 4])),
+                ])
+            ),
+        ])])
+    );
+}
+
+#[test]
+fn test_from_cel_cbor_nv_index() {
+    let uint = |v: u64| Value::Integer(v.into());
+    let pcclient_std = |event_type: u32| {
+        Value::Map(vec![
+            (uint(0), uint(event_type.into())),
+            (uint(1), Value::Bytes(vec![])),
+        ])
+    };
+    let log = Value::Array(vec![
+        Value::Map(vec![
+            (uint(0), uint(0)),
+            (uint(2), uint(0x01c1_0102)),
+            (
+                uint(3),
+                Value::Map(vec![(uint(0x000B), Value::Bytes(vec![0]))]),
+            ),
+            (uint(5), pcclient_std(3)),
+        ]),
+        Value::Map(vec![
+            (uint(0), uint(1)),
+            (uint(1), uint(4)),
+            (
+                uint(3),
+                Value::Map(vec![
+                    (uint(0x0004), Value::Bytes(vec![1])),
+                    (uint(0x000B), Value::Bytes(vec![1, 1])),
+                ]),
+            ),
+            (uint(5), pcclient_std(EventType::EfiAction as u32)),
+        ]),
+    ]);
+    let mut cbor = vec![];
+    ciborium::into_writer(&log, &mut cbor).unwrap();
+
+    assert_eq!(
+        from_cel_cbor(&cbor).unwrap(),
+        vec![TPMEvent {
+            event_type: EventType::EfiAction,
+            pcr: 4,
+            hash: vec![1, 1],
+            id: TPMEventID::Pcr4EfiCall,
+            data: None,
+        }]
+    );
+}
+
+#[test]
+fn test_to_cel_unloggable() {
+    let events = vec![event(0, 0, TPMEventID::PcrRootNodeEvent)];
+    assert_eq!(
+        to_cel(&events),
+        Err(CelError::UnloggableEvent(TPMEventID::PcrRootNodeEvent))
+    );
+}
+
+#[test]
+fn test_cel_event_data() {
+    let sbatlevel = uefi::UEFIVariableData::new(uefi::GUID_SHIM_LOCK, "SbatLevel", vec![1]);
+    let events = vec![
+        TPMEvent {
+            event_type: EventType::EfiVariableAuthority,
+            pcr: 7,
+            hash: sbatlevel.hash(),
+            id: TPMEventID::Pcr7SbatLevel,
+            data: Some(sbatlevel.encode()),
+        },
+        // MokList events log the variable name, not the hashed data
+        TPMEvent {
+            event_type: EventType::Ipl,
+            pcr: 14,
+            hash: vec![0; 32],
+            id: TPMEventID::Pcr14MokList,
+            data: Some(vec![1]),
+        },
+    ];
+    let records = to_cel(&events).unwrap();
+    let imported = from_cel(&records).unwrap();
+
+    assert_eq!(
+        records[1].content.event_data.as_deref(),
+        Some(b"MokList\0".as_slice())
+    );
+    assert_eq!(imported[0], events[0]);
+    assert_eq!(imported[1].data, None);
+}
+
+#[test]
+fn test_from_cel_skips_foreign_records() {
+    let log = r#"[
+        {"recnum": 0, "pcr": 0, "digests": [{"hashAlg": "sha256", "digest": "00"}],
+         "content_type": "pcclient_std", "content": {"event_type": 8, "event_data": ""}},
+        {"recnum": 1, "pcr": 4, "digests": [{"hashAlg": "sha1", "digest": "01"},
+                                             {"hashAlg": "sha256", "digest": "0101"}],
+         "content_type": "pcclient_std", "content": {"event_type": 2147483655, "event_data": ""}},
+        {"recnum": 2, "pcr": 10, "digests": [{"hashAlg": "sha256", "digest": "02"}],
+         "content_type": "ima_template",
+         "content": {"template_name": "ima-ng", "template_data": "AA=="}},
+        {"recnum": 3, "nv_index": 29425922, "digests": [{"hashAlg": "sha256", "digest": "03"}],
+         "content_type": "pcclient_std", "content": {"event_type": 3, "event_data": ""}}
+    ]"#;
+
+    assert_eq!(
+        from_cel_json(log).unwrap(),
+        vec![TPMEvent {
+            event_type: EventType::EfiAction,
+            pcr: 4,
+            hash: vec![1, 1],
+            id: TPMEventID::Pcr4EfiCall,
+            data: None,
+        }]
+    );
+}
+
+#[test]
+fn test_from_cel_variable_names() {
+    // Secure boot disabled: shim's certificate is not logged
+    let sbatlevel = uefi::UEFIVariableData::new(uefi::GUID_SHIM_LOCK, "SbatLevel", vec![]);
+    let records = vec![
+        record(0, 7, EventType::Separator, vec![0; 4]),
+        record(1, 4, EventType::EfiAction, vec![]),
+        record(2, 7, EventType::EfiVariableAuthority, sbatlevel.encode()),
+    ];
+    let ids: Vec<TPMEventID> = from_cel(&records)
+        .unwrap()
+        .into_iter()
+        .map(|e| e.id)
+        .collect();
+
+    assert_eq!(
+        ids,
+        vec![
+            TPMEventID::Pcr7Separator,
+            TPMEventID::Pcr4EfiCall,
+            TPMEventID::Pcr7SbatLevel,
+        ]
+    );
+}
+
+#[test]
+fn test_from_cel_errors() {
+    let mut no_sha256 = record(0, 4, EventType::EfiAction, vec![]);
+    no_sha256.digests[0].hash_alg = "sha1".into();
+    assert_eq!(from_cel(&[no_sha256]), Err(CelError::MissingDigest(0)));
+
+    // Separator can't be logged before the EFI action
+    let records = vec![
+        record(0, 4, EventType::Separator, vec![]),
+        record(1, 4, EventType::EfiAction, vec![]),
+    ];
+    assert_eq!(from_cel(&records), Err(CelError::UnknownEvent(1)));
+
+    let mut no_event_type = record(0, 4, EventType::EfiAction, vec![]);
+    no_event_type.content.event_type = None;
+    assert_eq!(
+        from_cel(&[no_event_type]),
+        Err(CelError::MissingEventType(0))
+    );
+
+    assert!(matches!(from_cel_json("{}"), Err(CelError::Decode(_))));
+}
@@ -38,16 +38,15 @@
  *     Could it be possible, in that case, that a weird mix happens?
  *
  *  Solution:
- *    - Right now, empty EventCombinationError instances are pushed into
- *      the tree when conflicts are hit.
+ *    - Right now, EventCombinationError instances holding the event_id
+ *      and the state of groups when the conflict was hit are pushed into
+ *      the tree. They can be inspected by dumping the tree.
  *    - Currently those are simply ignored when final PCR combinations
  *      are computed as we don't expect those to be hit under the
  *      use-cases accepted.
  *    - In the future, if this is a case that could happen, we should:
- *      - Add some fields to EventCombinationError containing: the state
- *        · The index of images that lead to the conflict.
- *        · The state of groups when the conflict was hit.
- *        · The event_id for which the conflict was hit.
+ *      - Add the index of images that lead to the conflict to
+ *        EventCombinationError.
  *      - Add recovery functions to EventCombinationError. We could
  *        model this process in two steps:
  *        · Let the operator know which information is needed (e.g.
@@ -63,66 +62,614 @@
  *        functions follow a similar interface.
  *
 */
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::hash::{BuildHasher, RandomState};
 
 use super::*;
 use crate::pcrs::{Pcr, compile_pcrs};
 
 use itertools::Itertools;
 use log::warn;
+use serde_with::serde_as;
 
 #[cfg(test)]
 mod tests;
 
-#[derive(Clone, Debug)]
-pub struct EventCombinationError {}
+#[derive(Clone, Debug, PartialEq)]
+pub struct EventCombinationError {
+    pub event_id: TPMEventID,
+    pub group_this: u32,
+    pub group_that: u32,
+}
+
+/// Event picked while combining two images, along with the groups that were
+/// already bound to each of the images when it was picked.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CombinedEvent {
+    pub event: TPMEvent,
+    pub group_this: u32,
+    pub group_that: u32,
+}
+
+pub type EventNode = tree::ResultNode<CombinedEvent, EventCombinationError>;
+
+/// Maps event group names to the labels of the images they were taken from
+pub type Provenance = BTreeMap<String, BTreeSet<String>>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CombineError {
+    /// More branches than allowed were found while walking event trees
+    BranchLimit(usize),
+    /// A constraint refers to an image label that is not being combined
+    UnknownImage(String),
+    /// An upgrade path with less than two images, which has no steps
+    ShortUpgradePath(usize),
+}
+
+impl fmt::Display for CombineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CombineError::BranchLimit(limit) => {
+                write!(
+                    f,
+                    "Event combination exceeded the limit of {limit} branches"
+                )
+            }
+            CombineError::UnknownImage(label) => {
+                write!(f, "Unknown image in combination constraint: {label}")
+            }
+            CombineError::ShortUpgradePath(len) => {
+                write!(f, "An upgrade path needs at least two images, got {len}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CombineError {}
+
+/// Output formats of the event trees walked when combining images
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TreeFormat {
+    Dot,
+    Text,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CombineOptions {
+    /// Maximum number of event tree branches to walk before giving up.
+    /// Unbounded if None.
+    pub max_branches: Option<usize>,
+    /// Restrictions on which image each event group can be taken from
+    pub constraints: Vec<CombineConstraint>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CombineConstraint {
+    /// All the groups in the mask must be taken from the same image, e.g.
+    /// the bootloader is never updated unless the kernel is also updated.
+    SameImage(u32),
+    /// All the groups in the mask must have the events of the image with the
+    /// given label, e.g. secure boot variables are not updated along with
+    /// the images.
+    Pinned(u32, String),
+}
+
+/// Combination constraints resolved against the images being combined
+struct Constraints {
+    same_image: Vec<u32>,
+    pinned: Vec<(u32, HashMap<TPMEventID, TPMEvent>)>,
+}
+
+impl Constraints {
+    fn new(
+        constraints: &[CombineConstraint],
+        images: &[LabeledImage],
+    ) -> Result<Constraints, CombineError> {
+        let mut same_image = vec![];
+        let mut pinned = vec![];
+        for constraint in constraints {
+            match constraint {
+                CombineConstraint::SameImage(groups) => same_image.push(*groups),
+                CombineConstraint::Pinned(groups, label) => {
+                    let image = images
+                        .iter()
+                        .find(|i| &i.label == label)
+                        .ok_or_else(|| CombineError::UnknownImage(label.clone()))?;
+                    pinned.push((*groups, tpm_event_id_hashmap(&image.events)));
+                }
+            }
+        }
+        Ok(Constraints { same_image, pinned })
+    }
 
-pub type EventNode = tree::ResultNode<TPMEvent, EventCombinationError>;
+    /// Extends groups with every group that must be taken from the same
+    /// image as them
+    fn link(&self, groups: u32) -> u32 {
+        let mut linked = groups;
+        loop {
+            let next = self
+                .same_image
+                .iter()
+                .filter(|g| *g & linked != 0)
+                .fold(linked, |l, g| l | g);
+            if next == linked {
+                return linked;
+            }
+            linked = next;
+        }
+    }
+
+    /// Whether the event can be logged given the pinned groups
+    fn allows(&self, event: &TPMEvent) -> bool {
+        self.pinned.iter().all(|(groups, events)| {
+            event.id.groups() & groups == 0 || events.get(&event.id) == Some(event)
+        })
+    }
+}
+
+/// Events computed for an image, and the label that identifies the image
+#[derive(Clone, Debug, PartialEq)]
+pub struct LabeledImage {
+    pub label: String,
+    pub events: Vec<TPMEvent>,
+}
+
+impl LabeledImage {
+    pub fn new(label: &str, events: Vec<TPMEvent>) -> LabeledImage {
+        LabeledImage {
+            label: label.into(),
+            events,
+        }
+    }
+}
+
+/// A combined set of PCRs and the images each event group came from.
+/// A group that has the same events in several images is attributed to all
+/// of them.
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub struct CombinedPcrs {
+    pub pcrs: Vec<Pcr>,
+    pub provenance: Provenance,
+}
+
+/// PCR combinations without duplicates. The provenance of duplicates is
+/// merged into the first occurrence.
+#[derive(Default)]
+struct DistinctCombinations {
+    /// Indices of the results by the hash of their PCRs, so that PCR sets are
+    /// only stored once
+    seen: HashMap<u64, Vec<usize>>,
+    hasher: RandomState,
+    results: Vec<CombinedPcrs>,
+}
+
+impl DistinctCombinations {
+    fn push(&mut self, combined: CombinedPcrs) {
+        let indices = self
+            .seen
+            .entry(self.hasher.hash_one(&combined.pcrs))
+            .or_default();
+        match indices
+            .iter()
+            .find(|&&i| self.results[i].pcrs == combined.pcrs)
+        {
+            Some(&i) => {
+                for (group, labels) in combined.provenance {
+                    self.results[i]
+                        .provenance
+                        .entry(group)
+                        .or_default()
+                        .extend(labels);
+                }
+            }
+            None => {
+                indices.push(self.results.len());
+                self.results.push(combined);
+            }
+        }
+    }
+}
+
+/// Accumulates the PCR combinations compiled from event tree branches,
+/// dropping duplicates as soon as they are found so that memory is bounded
+/// by the amount of distinct results.
+struct Combinations<'a> {
+    options: &'a CombineOptions,
+    constraints: Constraints,
+    branches: usize,
+    distinct: DistinctCombinations,
+}
+
+impl Combinations<'_> {
+    fn new<'a>(
+        options: &'a CombineOptions,
+        images: &[LabeledImage],
+    ) -> Result<Combinations<'a>, CombineError> {
+        Ok(Combinations {
+            options,
+            constraints: Constraints::new(&options.constraints, images)?,
+            branches: 0,
+            distinct: DistinctCombinations::default(),
+        })
+    }
+
+    fn add_image(&mut self, image: &LabeledImage) {
+        if !image.events.iter().all(|e| self.constraints.allows(e)) {
+            return;
+        }
+        let groups = image.events.iter().fold(0, |g, e| g | e.id.groups());
+        self.distinct.push(CombinedPcrs {
+            pcrs: compile_pcrs(&image.events),
+            provenance: provenance(groups, 0, 0, &image.label, &image.label),
+        });
+    }
+
+    fn add_pair(&mut self, this: &LabeledImage, that: &LabeledImage) -> Result<(), CombineError> {
+        let map_this = tpm_event_id_hashmap(&this.events);
+        let map_that = tpm_event_id_hashmap(&that.events);
+
+        let branches = EventBranches::new(&map_this, &map_that, &self.constraints);
+        for (events, group_this, group_that) in branches {
+            self.branches += 1;
+            if let Some(limit) = self.options.max_branches
+                && self.branches > limit
+            {
+                return Err(CombineError::BranchLimit(limit));
+            }
+            let groups = events.iter().fold(0, |g, e| g | e.id.groups());
+            self.distinct.push(CombinedPcrs {
+                pcrs: compile_pcrs(&events),
+                provenance: provenance(groups, group_this, group_that, &this.label, &that.label),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Attributes each of the groups to the images it was taken from. Groups
+/// that were not bound to any image have the same events in both.
+fn provenance(
+    groups: u32,
+    group_this: u32,
+    group_that: u32,
+    label_this: &str,
+    label_that: &str,
+) -> Provenance {
+    TPMEG_NAMED
+        .iter()
+        .filter(|(g, _)| groups & g != 0)
+        .map(|(g, name)| {
+            let mut labels = BTreeSet::new();
+            if g & group_that == 0 {
+                labels.insert(label_this.to_string());
+            }
+            if g & group_this == 0 {
+                labels.insert(label_that.to_string());
+            }
+            (name.to_string(), labels)
+        })
+        .collect()
+}
 
 pub fn combine_images(images: &[Vec<TPMEvent>]) -> Vec<Vec<Pcr>> {
+    combine_images_with(images, &CombineOptions::default())
+        .expect("Unbounded combinations can't exceed a branch limit")
+}
+
+/// Same as combine_images, but walks the event trees as configured in
+/// options.
+pub fn combine_images_with(
+    images: &[Vec<TPMEvent>],
+    options: &CombineOptions,
+) -> Result<Vec<Vec<Pcr>>, CombineError> {
+    let labeled: Vec<LabeledImage> = images
+        .iter()
+        .enumerate()
+        .map(|(i, e)| LabeledImage::new(&i.to_string(), e.clone()))
+        .collect();
+    Ok(combine_labeled_images(&labeled, options)?
+        .into_iter()
+        .map(|c| c.pcrs)
+        .collect())
+}
+
+/// Combines the images, annotating each result with the images each event
+/// group was sourced from.
+pub fn combine_labeled_images(
+    images: &[LabeledImage],
+    options: &CombineOptions,
+) -> Result<Vec<CombinedPcrs>, CombineError> {
+    let mut combinations = Combinations::new(options, images)?;
     if images.len() == 1 {
-        return vec![compile_pcrs(&images[0])];
+        combinations.add_image(&images[0]);
+    }
+    for p in images.iter().combinations(2) {
+        combinations.add_pair(p[0], p[1])?;
+    }
+    Ok(combinations.distinct.results)
+}
+
+/// Reference values for the boots that can happen while a node is upgraded
+/// from an image to the next one in an upgrade path
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub struct UpgradeStep {
+    pub from: String,
+    pub to: String,
+    /// Names of the event groups whose events differ between both images
+    pub changed_groups: Vec<String>,
+    pub pcrs: Vec<CombinedPcrs>,
+    /// PCR sets that neither a later step nor the last image of the path
+    /// need, and can be retired once every node is past this step
+    pub retirable: Vec<Vec<Pcr>>,
+}
+
+/// Combines the images of a linear upgrade path, e.g. A -> B -> C, step by
+/// step (A -> B, B -> C) instead of combining every pair of images.
+/// The branch limit in options applies to each step.
+/// Paths with less than two images have no steps and are rejected.
+pub fn combine_upgrade_path(
+    images: &[LabeledImage],
+    options: &CombineOptions,
+) -> Result<Vec<UpgradeStep>, CombineError> {
+    if images.len() < 2 {
+        return Err(CombineError::ShortUpgradePath(images.len()));
+    }
+    let mut steps = vec![];
+    for (from, to) in images.iter().tuple_windows() {
+        let mut combinations = Combinations::new(options, images)?;
+        combinations.add_pair(from, to)?;
+        steps.push(UpgradeStep {
+            from: from.label.clone(),
+            to: to.label.clone(),
+            changed_groups: changed_groups(&from.events, &to.events),
+            pcrs: combinations.distinct.results,
+            retirable: vec![],
+        });
     }
 
-    images
+    // Index of the last step producing each PCR set. The PCR sets of the
+    // last image are never retired, nodes stay on it.
+    let last_image = images.last().map(|i| compile_pcrs(&i.events));
+    let mut last_step: HashMap<&Vec<Pcr>, usize> = HashMap::new();
+    for (i, step) in steps.iter().enumerate() {
+        for c in &step.pcrs {
+            last_step.insert(&c.pcrs, i);
+        }
+    }
+    let retirable: Vec<Vec<Vec<Pcr>>> = steps
         .iter()
-        .combinations(2)
-        .flat_map(|p| combine(p[0], p[1]))
-        .unique()
-        .collect()
+        .enumerate()
+        .map(|(i, step)| {
+            step.pcrs
+                .iter()
+                .filter(|c| last_step[&c.pcrs] == i && Some(&c.pcrs) != last_image.as_ref())
+                .map(|c| c.pcrs.clone())
+                .collect()
+        })
+        .collect();
+    for (step, retirable) in steps.iter_mut().zip(retirable) {
+        step.retirable = retirable;
+    }
+
+    Ok(steps)
 }
 
-pub fn combine(this: &[TPMEvent], that: &[TPMEvent]) -> Vec<Vec<Pcr>> {
+/// Names of the groups of the events that differ between both images
+fn changed_groups(this: &[TPMEvent], that: &[TPMEvent]) -> Vec<String> {
     let map_this = tpm_event_id_hashmap(this);
     let map_that = tpm_event_id_hashmap(that);
+    let groups = map_this
+        .keys()
+        .chain(map_that.keys())
+        .filter(|id| map_this.get(id) != map_that.get(id))
+        .fold(0, |g, id| g | id.groups());
 
-    let event = TPMEventID::PcrRootNodeEvent.next().unwrap();
-    match event_subtree(&event, &map_this, &map_that, 0, 0) {
-        Some(st) => st
-            .iter()
-            .flat_map(|t| t.valid_branches())
-            .map(|e| compile_pcrs(&e))
-            .collect(),
-        None => vec![],
+    TPMEG_NAMED
+        .iter()
+        .filter(|(g, _)| groups & g != 0)
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
+
+/// Allowed values of each PCR, for verifiers that check every PCR value
+/// independently instead of checking whole PCR sets
+#[serde_as]
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct PcrAllowedValues {
+    #[serde_as(as = "BTreeMap<_, BTreeSet<serde_with::hex::Hex>>")]
+    pub values: BTreeMap<u64, BTreeSet<Vec<u8>>>,
+    pub looseness: Looseness,
+}
+
+/// Maximum amount of PCR sets sampled in Looseness::extra_sample
+pub const EXTRA_SAMPLE_SIZE: usize = 16;
+
+/// How much looser allowing values per PCR is compared to allowing the
+/// combined PCR sets
+#[serde_as]
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct Looseness {
+    /// Amount of PCR sets that the combination produced
+    pub combined: usize,
+    /// Amount of PCR sets that per PCR values admit. PCR sets only combine
+    /// values of the PCRs that some combined set has. Saturates at
+    /// usize::MAX.
+    pub admitted: usize,
+    /// Amount of PCR sets admitted by per PCR values that the combination
+    /// forbade
+    pub extra: usize,
+    /// Some of the extra PCR sets, at most EXTRA_SAMPLE_SIZE
+    #[serde_as(as = "Vec<BTreeMap<_, serde_with::hex::Hex>>")]
+    pub extra_sample: Vec<BTreeMap<u64, Vec<u8>>>,
+}
+
+/// Collapses combined PCR sets into the allowed values of each PCR
+pub fn per_pcr_values(combinations: &[Vec<Pcr>]) -> PcrAllowedValues {
+    let mut values: BTreeMap<u64, BTreeSet<Vec<u8>>> = BTreeMap::new();
+    for pcr in combinations.iter().flatten() {
+        values.entry(pcr.id).or_default().insert(pcr.value.clone());
+    }
+
+    let combined: HashSet<BTreeMap<u64, Vec<u8>>> = combinations
+        .iter()
+        .map(|c| c.iter().map(|p| (p.id, p.value.clone())).collect())
+        .collect();
+    // Per PCR values admit any mix of values for each set of PCRs a
+    // combined PCR set covers
+    let id_sets: BTreeSet<Vec<u64>> = combined
+        .iter()
+        .map(|c| c.keys().copied().collect())
+        .collect();
+    let admitted = id_sets
+        .iter()
+        .map(|ids| {
+            ids.iter()
+                .try_fold(1usize, |n, id| n.checked_mul(values[id].len()))
+                .unwrap_or(usize::MAX)
+        })
+        .fold(0usize, usize::saturating_add);
+    // The product is walked lazily, and at most the combined sets are
+    // skipped before the sample is full
+    let extra_sample = id_sets
+        .iter()
+        .flat_map(|ids| {
+            ids.iter()
+                .map(|id| values[id].iter().map(move |value| (*id, value.clone())))
+                .multi_cartesian_product()
+                .map(BTreeMap::from_iter)
+        })
+        .filter(|admitted| !combined.contains(admitted))
+        .take(EXTRA_SAMPLE_SIZE)
+        .collect();
+
+    PcrAllowedValues {
+        looseness: Looseness {
+            combined: combined.len(),
+            admitted,
+            extra: admitted.saturating_sub(combined.len()),
+            extra_sample,
+        },
+        values,
     }
 }
 
-fn event_subtree(
+/// Same as combine_images_with, but returns the allowed values of each PCR
+pub fn combine_images_per_pcr(
+    images: &[Vec<TPMEvent>],
+    options: &CombineOptions,
+) -> Result<PcrAllowedValues, CombineError> {
+    Ok(per_pcr_values(&combine_images_with(images, options)?))
+}
+
+/// Describes an event tree node: the event id, a prefix of its digest and
+/// the groups bound to each image. Errors are group conflicts.
+fn event_node_label(node: &Result<CombinedEvent, EventCombinationError>) -> String {
+    match node {
+        Ok(e) => format!(
+            "{:?} {}.. this={:#x} that={:#x}",
+            e.event.id,
+            hex::encode(e.event.hash.iter().take(4).copied().collect::<Vec<u8>>()),
+            e.group_this,
+            e.group_that
+        ),
+        Err(e) => format!(
+            "CONFLICT {:?} this={:#x} that={:#x}",
+            e.event_id, e.group_this, e.group_that
+        ),
+    }
+}
+
+fn event_node_dot_attrs(node: &Result<CombinedEvent, EventCombinationError>) -> String {
+    let label = event_node_label(node).replace('"', "\\\"");
+    match node {
+        Ok(_) => format!("label=\"{label}\""),
+        Err(_) => format!("label=\"{label}\", color=red"),
+    }
+}
+
+/// Renders the event trees walked to combine each pair of images, for
+/// debugging why some PCR set was or wasn't produced.
+pub fn render_event_trees(
+    images: &[LabeledImage],
+    options: &CombineOptions,
+    format: TreeFormat,
+) -> Result<String, CombineError> {
+    let constraints = Constraints::new(&options.constraints, images)?;
+    let mut out = match format {
+        TreeFormat::Dot => String::from("digraph {\n"),
+        TreeFormat::Text => String::new(),
+    };
+
+    for (i, p) in images.iter().combinations(2).enumerate() {
+        let map_this = tpm_event_id_hashmap(&p[0].events);
+        let map_that = tpm_event_id_hashmap(&p[1].events);
+        let event = TPMEventID::PcrRootNodeEvent.next().unwrap();
+        let trees =
+            event_subtree(&event, &map_this, &map_that, 0, 0, &constraints).unwrap_or_default();
+        let title = format!("this={} that={}", p[0].label, p[1].label);
+
+        match format {
+            TreeFormat::Dot => {
+                out.push_str(&format!(
+                    "subgraph cluster_{i} {{\nlabel=\"{}\";\n",
+                    title.replace('"', "\\\"")
+                ));
+                for (j, tree) in trees.iter().enumerate() {
+                    out.push_str(&tree.dot_statements(&format!("p{i}t{j}"), &event_node_dot_attrs));
+                }
+                out.push_str("}\n");
+            }
+            TreeFormat::Text => {
+                out.push_str(&format!("{title}\n"));
+                for tree in &trees {
+                    out.push_str(&tree.to_text(event_node_label));
+                }
+            }
+        }
+    }
+
+    if format == TreeFormat::Dot {
+        out.push_str("}\n");
+    }
+    Ok(out)
+}
+
+pub fn combine(this: &[TPMEvent], that: &[TPMEvent]) -> Vec<Vec<Pcr>> {
+    combine_images_with(&[this.to_vec(), that.to_vec()], &CombineOptions::default())
+        .expect("Unbounded combinations can't exceed a branch limit")
+}
+
+/// What combining the events of both images with a given id leads to
+enum EventStep<'a> {
+    /// Neither image logs the event, or it can't be logged in this branch
+    /// and isn't required
+    Skip,
+    /// The event is required, but its groups are bound to different images
+    Conflict,
+    /// Events that can be logged, with the groups bound to each image after
+    /// logging them. Empty if the combination constraints rule all out.
+    Choices(Vec<(&'a TPMEvent, u32, u32)>),
+}
+
+fn event_step<'a>(
     event_id: &TPMEventID,
-    map_this: &HashMap<TPMEventID, TPMEvent>,
-    map_that: &HashMap<TPMEventID, TPMEvent>,
+    map_this: &'a HashMap<TPMEventID, TPMEvent>,
+    map_that: &'a HashMap<TPMEventID, TPMEvent>,
     group_this: u32,
     group_that: u32,
-) -> Option<Vec<EventNode>> {
+    constraints: &Constraints,
+) -> EventStep<'a> {
     // Groups can't overlap
     assert_eq!(group_this & group_that, 0);
     let opt_this = map_this.get(event_id);
     let opt_that = map_that.get(event_id);
     // Divergences contains tuples with events, and this/that masked groups
     let mut divs: Vec<(&TPMEvent, u32, u32)> = vec![];
-    let mut nodes: Vec<EventNode> = vec![];
     let mut event_required = false;
-    let event_groups = event_id.groups();
+    let event_groups = constraints.link(event_id.groups());
 
     if let Some(event_this) = opt_this
         && let Some(event_that) = opt_that
@@ -150,45 +697,208 @@ fn event_subtree(
         }
     }
 
-    if divs.is_empty() {
+    let conflict = divs.is_empty();
+    divs.retain(|(event, _, _)| constraints.allows(event));
+
+    match (event_required, divs.is_empty()) {
         // Event is required but wasn't pushed to divergences...
         // Means we met an event id/tree branching group conflict
-        if event_required {
-            warn!("Event group conflict hit combining {event_id:?}");
+        (true, true) if conflict => EventStep::Conflict,
+        (false, true) => EventStep::Skip,
+        _ => EventStep::Choices(divs),
+    }
+}
 
-            let mut node = EventNode::new_err(EventCombinationError {});
+/// Position in the walk of the event tree: the choices of a branching
+/// event and the one currently taken
+struct EventBranchFrame<'a> {
+    event_id: TPMEventID,
+    choices: Vec<(&'a TPMEvent, u32, u32)>,
+    index: usize,
+}
 
-            if let Some(children) = event_subtree(
-                &event_id.next()?,
-                map_this,
-                map_that,
+/// Depth-first walk of the event tree of two images, without building it.
+/// Yields the events of each valid branch along with the groups bound to
+/// each image at its leaf. Only the choices along the current branch are
+/// kept in memory, so callers can bound the walk by stopping early.
+struct EventBranches<'a> {
+    map_this: &'a HashMap<TPMEventID, TPMEvent>,
+    map_that: &'a HashMap<TPMEventID, TPMEvent>,
+    constraints: &'a Constraints,
+    frames: Vec<EventBranchFrame<'a>>,
+    start: Option<TPMEventID>,
+}
+
+impl<'a> EventBranches<'a> {
+    fn new(
+        map_this: &'a HashMap<TPMEventID, TPMEvent>,
+        map_that: &'a HashMap<TPMEventID, TPMEvent>,
+        constraints: &'a Constraints,
+    ) -> EventBranches<'a> {
+        EventBranches {
+            map_this,
+            map_that,
+            constraints,
+            frames: vec![],
+            start: TPMEventID::PcrRootNodeEvent.next(),
+        }
+    }
+
+    /// Takes the first choice of every event from event_id on. Returns
+    /// whether the end of the events was reached, or false if the branch hit
+    /// a conflict or was pruned.
+    fn descend(&mut self, mut event_id: Option<TPMEventID>, mut groups: (u32, u32)) -> bool {
+        while let Some(id) = event_id {
+            let (group_this, group_that) = groups;
+            match event_step(
+                &id,
+                self.map_this,
+                self.map_that,
                 group_this,
                 group_that,
+                self.constraints,
             ) {
-                for c in children {
-                    node.add_child(c);
+                EventStep::Skip => {}
+                EventStep::Conflict => {
+                    warn!("Event group conflict hit combining {id:?}");
+                    return false;
+                }
+                EventStep::Choices(choices) if choices.is_empty() => return false,
+                EventStep::Choices(choices) => {
+                    let (_, g_this, g_that) = choices[0];
+                    groups = (g_this, g_that);
+                    self.frames.push(EventBranchFrame {
+                        event_id: id.clone(),
+                        choices,
+                        index: 0,
+                    });
                 }
             }
+            event_id = id.next();
+        }
+        true
+    }
 
-            nodes.push(node);
-        } else {
+    /// Moves to the next choice of the deepest event that has one left,
+    /// returning where the walk resumes from
+    fn advance(&mut self) -> Option<(Option<TPMEventID>, (u32, u32))> {
+        while let Some(frame) = self.frames.last_mut() {
+            frame.index += 1;
+            if let Some(&(_, g_this, g_that)) = frame.choices.get(frame.index) {
+                return Some((frame.event_id.next(), (g_this, g_that)));
+            }
+            self.frames.pop();
+        }
+        None
+    }
+}
+
+impl Iterator for EventBranches<'_> {
+    type Item = (Vec<TPMEvent>, u32, u32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (mut event_id, mut groups) = match self.start.take() {
+            Some(id) => (Some(id), (0, 0)),
+            None => self.advance()?,
+        };
+        while !self.descend(event_id, groups) {
+            (event_id, groups) = self.advance()?;
+        }
+
+        // Groups only grow along the branch, the leaf has them all
+        let (_, group_this, group_that) = self.frames.last()?.choices[self.frames.last()?.index];
+        let events = self
+            .frames
+            .iter()
+            .map(|f| f.choices[f.index].0.clone())
+            .collect();
+        Some((events, group_this, group_that))
+    }
+}
+
+/// Builds the subtrees of the events that follow event_id, for rendering.
+/// Returns None once the last event is passed, and an empty vector if all the
+/// subtrees were pruned by the combination constraints.
+fn event_subtree(
+    event_id: &TPMEventID,
+    map_this: &HashMap<TPMEventID, TPMEvent>,
+    map_that: &HashMap<TPMEventID, TPMEvent>,
+    group_this: u32,
+    group_that: u32,
+    constraints: &Constraints,
+) -> Option<Vec<EventNode>> {
+    let divs = match event_step(
+        event_id,
+        map_this,
+        map_that,
+        group_this,
+        group_that,
+        constraints,
+    ) {
+        EventStep::Skip => {
             return event_subtree(
                 &event_id.next()?,
                 map_this,
                 map_that,
                 group_this,
                 group_that,
+                constraints,
             );
         }
-    }
+        EventStep::Conflict => {
+            warn!("Event group conflict hit combining {event_id:?}");
+
+            let mut node = EventNode::new_err(EventCombinationError {
+                event_id: event_id.clone(),
+                group_this,
+                group_that,
+            });
+
+            match event_subtree(
+                &event_id.next()?,
+                map_this,
+                map_that,
+                group_this,
+                group_that,
+                constraints,
+            ) {
+                Some(children) if children.is_empty() => return Some(vec![]),
+                Some(children) => {
+                    for c in children {
+                        node.add_child(c);
+                    }
+                }
+                None => {}
+            }
+
+            return Some(vec![node]);
+        }
+        EventStep::Choices(divs) => divs,
+    };
 
+    let mut nodes: Vec<EventNode> = vec![];
     for (event, g_this, g_that) in divs {
-        let mut node = EventNode::new_ok(event.clone());
-        if let Some(children) = event_subtree(&event_id.next()?, map_this, map_that, g_this, g_that)
-        {
-            for c in children {
-                node.add_child(c);
+        let mut node = EventNode::new_ok(CombinedEvent {
+            event: event.clone(),
+            group_this: g_this,
+            group_that: g_that,
+        });
+        match event_subtree(
+            &event_id.next()?,
+            map_this,
+            map_that,
+            g_this,
+            g_that,
+            constraints,
+        ) {
+            // Every branch below was pruned
+            Some(children) if children.is_empty() => continue,
+            Some(children) => {
+                for c in children {
+                    node.add_child(c);
+                }
             }
+            None => {}
         }
         nodes.push(node);
     }
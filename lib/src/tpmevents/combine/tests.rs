@@ -3,32 +3,35 @@
 // SPDX-License-Identifier: MIT
 use super::*;
 use crate::pcrs::{Pcr, compile_pcrs};
-use crate::tpmevents::{TPMEvent, TPMEventID};
+use crate::tpmevents::{EventType, TPMEvent, TPMEventID};
 
 use hex::decode;
 use log::Level;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use testing_logger;
 
 #[test]
 fn test_tpm_event_id_hashmap() {
     let foo = TPMEvent {
-        name: "FOO".into(),
+        event_type: EventType::NoAction,
         pcr: 0x00,
         hash: vec![0, 0, 0],
         id: TPMEventID::PcrRootNodeEvent,
+        data: None,
     };
     let bar = TPMEvent {
-        name: "BAR".into(),
+        event_type: EventType::Ipl,
         pcr: 0xFF,
         hash: vec![4, 5, 6],
         id: TPMEventID::Pcr11Sbat,
+        data: None,
     };
     let foobar = TPMEvent {
-        name: "FOOBAR".into(),
+        event_type: EventType::Ipl,
         pcr: 0xe8,
         hash: vec![1, 2, 3, 4, 5],
         id: TPMEventID::Pcr11UnameContent,
+        data: None,
     };
     let events = vec![foo.clone(), bar.clone(), foobar.clone()];
 
@@ -47,76 +50,86 @@ fn test_tpm_event_id_hashmap() {
 fn test_pcr4_kernel_update() {
     let this = vec![
         TPMEvent {
-            name: "EV_EFI_ACTION".into(),
+            event_type: EventType::EfiAction,
             pcr: 4,
             hash: decode("3d6772b4f84ed47595d72a2c4c5ffd15f5bb72c7507fe26f2aaee2c69d5633ba")
                 .unwrap(),
             id: TPMEventID::Pcr4EfiCall,
+            data: None,
         },
         TPMEvent {
-            name: "EV_SEPARATOR".into(),
+            event_type: EventType::Separator,
             pcr: 4,
             hash: decode("df3f619804a92fdb4057192dc43dd748ea778adc52bc498ce80524c014b81119")
                 .unwrap(),
             id: TPMEventID::Pcr4Separator,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("94896c17d49fc8c8df0cc2836611586edab1615ce7cb58cf13fc5798de56b367")
                 .unwrap(),
             id: TPMEventID::Pcr4Shim,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("bc6844fc7b59b4f0c7da70a307fc578465411d7a2c34b0f4dc2cc154c873b644")
                 .unwrap(),
             id: TPMEventID::Pcr4Grub,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("2b1dc59bc61dbbc3db11a6f3b0708c948efd46cceb7f6c8ea2024b8d1b8c829a")
                 .unwrap(),
             id: TPMEventID::Pcr4Vmlinuz,
+            data: None,
         },
     ];
     let that = vec![
         TPMEvent {
-            name: "EV_EFI_ACTION".into(),
+            event_type: EventType::EfiAction,
             pcr: 4,
             hash: decode("3d6772b4f84ed47595d72a2c4c5ffd15f5bb72c7507fe26f2aaee2c69d5633ba")
                 .unwrap(),
             id: TPMEventID::Pcr4EfiCall,
+            data: None,
         },
         TPMEvent {
-            name: "EV_SEPARATOR".into(),
+            event_type: EventType::Separator,
             pcr: 4,
             hash: decode("df3f619804a92fdb4057192dc43dd748ea778adc52bc498ce80524c014b81119")
                 .unwrap(),
             id: TPMEventID::Pcr4Separator,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("94896c17d49fc8c8df0cc2836611586edab1615ce7cb58cf13fc5798de56b367")
                 .unwrap(),
             id: TPMEventID::Pcr4Shim,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("bc6844fc7b59b4f0c7da70a307fc578465411d7a2c34b0f4dc2cc154c873b644")
                 .unwrap(),
             id: TPMEventID::Pcr4Grub,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("6115ef506eecf507d43279d2b5f11573c4011fab0f5bba6e22bb72dbf1d1ffd9")
                 .unwrap(),
             id: TPMEventID::Pcr4Vmlinuz,
+            data: None,
         },
     ];
 
@@ -134,404 +147,460 @@ fn test_pcr4_kernel_update() {
 fn test_pcr4_pcr7_bootloader_and_kernel_update() {
     let this = vec![
         TPMEvent {
-            name: "EV_EFI_ACTION".into(),
+            event_type: EventType::EfiAction,
             pcr: 4,
             hash: decode("3d6772b4f84ed47595d72a2c4c5ffd15f5bb72c7507fe26f2aaee2c69d5633ba")
                 .unwrap(),
             id: TPMEventID::Pcr4EfiCall,
+            data: None,
         },
         TPMEvent {
-            name: "EV_SEPARATOR".into(),
+            event_type: EventType::Separator,
             pcr: 4,
             hash: decode("df3f619804a92fdb4057192dc43dd748ea778adc52bc498ce80524c014b81119")
                 .unwrap(),
             id: TPMEventID::Pcr4Separator,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("94896c17d49fc8c8df0cc2836611586edab1615ce7cb58cf13fc5798de56b367")
                 .unwrap(),
             id: TPMEventID::Pcr4Shim,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("bc6844fc7b59b4f0c7da70a307fc578465411d7a2c34b0f4dc2cc154c873b644")
                 .unwrap(),
             id: TPMEventID::Pcr4Grub,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("2b1dc59bc61dbbc3db11a6f3b0708c948efd46cceb7f6c8ea2024b8d1b8c829a")
                 .unwrap(),
             id: TPMEventID::Pcr4Vmlinuz,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("ccfc4bb32888a345bc8aeadaba552b627d99348c767681ab3141f5b01e40a40e")
                 .unwrap(),
             id: TPMEventID::Pcr7SecureBoot,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("adb6fc232943e39c374bf4782b6c697f43c39fca1f4b51dfceda21164e19a893")
                 .unwrap(),
             id: TPMEventID::Pcr7Pk,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("b5432fe20c624811cb0296391bfdf948ebd02f0705ab8229bea09774023f0ebf")
                 .unwrap(),
             id: TPMEventID::Pcr7Kek,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("4313e43de720194a0eabf4d6415d42b5a03a34fdc47bb1fc924cc4e665e6893d")
                 .unwrap(),
             id: TPMEventID::Pcr7Db,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("001004ba58a184f09be6c1f4ec75a246cc2eefa9637b48ee428b6aa9bce48c55")
                 .unwrap(),
             id: TPMEventID::Pcr7Dbx,
+            data: None,
         },
         TPMEvent {
-            name: "EV_SEPARATOR".into(),
+            event_type: EventType::Separator,
             pcr: 7,
             hash: decode("df3f619804a92fdb4057192dc43dd748ea778adc52bc498ce80524c014b81119")
                 .unwrap(),
             id: TPMEventID::Pcr7Separator,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
+            event_type: EventType::EfiVariableAuthority,
             pcr: 7,
             hash: decode("4d4a8e2c74133bbdc01a16eaf2dbb5d575afeb36f5d8dfcf609ae043909e2ee9")
                 .unwrap(),
             id: TPMEventID::Pcr7ShimCert,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
+            event_type: EventType::EfiVariableAuthority,
             pcr: 7,
             hash: decode("e8e9578f5951ef16b1c1aa18ef02944b8375ec45ed4b5d8cdb30428db4a31016")
                 .unwrap(),
             id: TPMEventID::Pcr7SbatLevel,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
+            event_type: EventType::EfiVariableAuthority,
             pcr: 7,
             hash: decode("ad5901fd581e6640c742c488083b9ac2c48255bd28a16c106c6f9df52702ee3f")
                 .unwrap(),
             id: TPMEventID::Pcr7GrubVendorDbCert,
+            data: None,
         },
     ];
     let that = vec![
         TPMEvent {
-            name: "EV_EFI_ACTION".into(),
+            event_type: EventType::EfiAction,
             pcr: 4,
             hash: decode("3d6772b4f84ed47595d72a2c4c5ffd15f5bb72c7507fe26f2aaee2c69d5633ba")
                 .unwrap(),
             id: TPMEventID::Pcr4EfiCall,
+            data: None,
         },
         TPMEvent {
-            name: "EV_SEPARATOR".into(),
+            event_type: EventType::Separator,
             pcr: 4,
             hash: decode("df3f619804a92fdb4057192dc43dd748ea778adc52bc498ce80524c014b81119")
                 .unwrap(),
             id: TPMEventID::Pcr4Separator,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("5ad8a618488664b5a909f08262a28354181e39ce9fc8c8df0cc2836611586eda")
                 .unwrap(),
             id: TPMEventID::Pcr4Shim,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("5f77e2690dab289917fe18116ed779941c32d316883d25f2e21ccd392926bf48")
                 .unwrap(),
             id: TPMEventID::Pcr4Grub,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("6115ef506eecf507d43279d2b5f11573c4011fab0f5bba6e22bb72dbf1d1ffd9")
                 .unwrap(),
             id: TPMEventID::Pcr4Vmlinuz,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("ccfc4bb32888a345bc8aeadaba552b627d99348c767681ab3141f5b01e40a40e")
                 .unwrap(),
             id: TPMEventID::Pcr7SecureBoot,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("adb6fc232943e39c374bf4782b6c697f43c39fca1f4b51dfceda21164e19a893")
                 .unwrap(),
             id: TPMEventID::Pcr7Pk,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("b5432fe20c624811cb0296391bfdf948ebd02f0705ab8229bea09774023f0ebf")
                 .unwrap(),
             id: TPMEventID::Pcr7Kek,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("4313e43de720194a0eabf4d6415d42b5a03a34fdc47bb1fc924cc4e665e6893d")
                 .unwrap(),
             id: TPMEventID::Pcr7Db,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("001004ba58a184f09be6c1f4ec75a246cc2eefa9637b48ee428b6aa9bce48c55")
                 .unwrap(),
             id: TPMEventID::Pcr7Dbx,
+            data: None,
         },
         TPMEvent {
-            name: "EV_SEPARATOR".into(),
+            event_type: EventType::Separator,
             pcr: 7,
             hash: decode("df3f619804a92fdb4057192dc43dd748ea778adc52bc498ce80524c014b81119")
                 .unwrap(),
             id: TPMEventID::Pcr7Separator,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
+            event_type: EventType::EfiVariableAuthority,
             pcr: 7,
             hash: decode("7b052cea168123d110d99d617a4a0d2723562a10909578c4b739afe245cc3903")
                 .unwrap(),
             id: TPMEventID::Pcr7ShimCert,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
+            event_type: EventType::EfiVariableAuthority,
             pcr: 7,
             hash: decode("e8e9578f5951ef16b1c1aa18ef02944b8375ec45ed4b5d8cdb30428db4a31016")
                 .unwrap(),
             id: TPMEventID::Pcr7SbatLevel,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
+            event_type: EventType::EfiVariableAuthority,
             pcr: 7,
             hash: decode("c3ab8ff13720e8ad9047dd39466b3c8974e592c2fa383d4a3960714caef0c4f2")
                 .unwrap(),
             id: TPMEventID::Pcr7GrubVendorDbCert,
+            data: None,
         },
     ];
 
     let expected_this_kernel_that_bootloader = vec![
         TPMEvent {
-            name: "EV_EFI_ACTION".into(),
+            event_type: EventType::EfiAction,
             pcr: 4,
             hash: decode("3d6772b4f84ed47595d72a2c4c5ffd15f5bb72c7507fe26f2aaee2c69d5633ba")
                 .unwrap(),
             id: TPMEventID::Pcr4EfiCall,
+            data: None,
         },
         TPMEvent {
-            name: "EV_SEPARATOR".into(),
+            event_type: EventType::Separator,
             pcr: 4,
             hash: decode("df3f619804a92fdb4057192dc43dd748ea778adc52bc498ce80524c014b81119")
                 .unwrap(),
             id: TPMEventID::Pcr4Separator,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("5ad8a618488664b5a909f08262a28354181e39ce9fc8c8df0cc2836611586eda")
                 .unwrap(),
             id: TPMEventID::Pcr4Shim,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("5f77e2690dab289917fe18116ed779941c32d316883d25f2e21ccd392926bf48")
                 .unwrap(),
             id: TPMEventID::Pcr4Grub,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("2b1dc59bc61dbbc3db11a6f3b0708c948efd46cceb7f6c8ea2024b8d1b8c829a")
                 .unwrap(),
             id: TPMEventID::Pcr4Vmlinuz,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("ccfc4bb32888a345bc8aeadaba552b627d99348c767681ab3141f5b01e40a40e")
                 .unwrap(),
             id: TPMEventID::Pcr7SecureBoot,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("adb6fc232943e39c374bf4782b6c697f43c39fca1f4b51dfceda21164e19a893")
                 .unwrap(),
             id: TPMEventID::Pcr7Pk,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("b5432fe20c624811cb0296391bfdf948ebd02f0705ab8229bea09774023f0ebf")
                 .unwrap(),
             id: TPMEventID::Pcr7Kek,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("4313e43de720194a0eabf4d6415d42b5a03a34fdc47bb1fc924cc4e665e6893d")
                 .unwrap(),
             id: TPMEventID::Pcr7Db,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("001004ba58a184f09be6c1f4ec75a246cc2eefa9637b48ee428b6aa9bce48c55")
                 .unwrap(),
             id: TPMEventID::Pcr7Dbx,
+            data: None,
         },
         TPMEvent {
-            name: "EV_SEPARATOR".into(),
+            event_type: EventType::Separator,
             pcr: 7,
             hash: decode("df3f619804a92fdb4057192dc43dd748ea778adc52bc498ce80524c014b81119")
                 .unwrap(),
             id: TPMEventID::Pcr7Separator,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
+            event_type: EventType::EfiVariableAuthority,
             pcr: 7,
             hash: decode("7b052cea168123d110d99d617a4a0d2723562a10909578c4b739afe245cc3903")
                 .unwrap(),
             id: TPMEventID::Pcr7ShimCert,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
+            event_type: EventType::EfiVariableAuthority,
             pcr: 7,
             hash: decode("e8e9578f5951ef16b1c1aa18ef02944b8375ec45ed4b5d8cdb30428db4a31016")
                 .unwrap(),
             id: TPMEventID::Pcr7SbatLevel,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
+            event_type: EventType::EfiVariableAuthority,
             pcr: 7,
             hash: decode("c3ab8ff13720e8ad9047dd39466b3c8974e592c2fa383d4a3960714caef0c4f2")
                 .unwrap(),
             id: TPMEventID::Pcr7GrubVendorDbCert,
+            data: None,
         },
     ];
 
     let expected_that_kernel_this_bootloader = vec![
         TPMEvent {
-            name: "EV_EFI_ACTION".into(),
+            event_type: EventType::EfiAction,
             pcr: 4,
             hash: decode("3d6772b4f84ed47595d72a2c4c5ffd15f5bb72c7507fe26f2aaee2c69d5633ba")
                 .unwrap(),
             id: TPMEventID::Pcr4EfiCall,
+            data: None,
         },
         TPMEvent {
-            name: "EV_SEPARATOR".into(),
+            event_type: EventType::Separator,
             pcr: 4,
             hash: decode("df3f619804a92fdb4057192dc43dd748ea778adc52bc498ce80524c014b81119")
                 .unwrap(),
             id: TPMEventID::Pcr4Separator,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("94896c17d49fc8c8df0cc2836611586edab1615ce7cb58cf13fc5798de56b367")
                 .unwrap(),
             id: TPMEventID::Pcr4Shim,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("bc6844fc7b59b4f0c7da70a307fc578465411d7a2c34b0f4dc2cc154c873b644")
                 .unwrap(),
             id: TPMEventID::Pcr4Grub,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("6115ef506eecf507d43279d2b5f11573c4011fab0f5bba6e22bb72dbf1d1ffd9")
                 .unwrap(),
             id: TPMEventID::Pcr4Vmlinuz,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("ccfc4bb32888a345bc8aeadaba552b627d99348c767681ab3141f5b01e40a40e")
                 .unwrap(),
             id: TPMEventID::Pcr7SecureBoot,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("adb6fc232943e39c374bf4782b6c697f43c39fca1f4b51dfceda21164e19a893")
                 .unwrap(),
             id: TPMEventID::Pcr7Pk,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("b5432fe20c624811cb0296391bfdf948ebd02f0705ab8229bea09774023f0ebf")
                 .unwrap(),
             id: TPMEventID::Pcr7Kek,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("4313e43de720194a0eabf4d6415d42b5a03a34fdc47bb1fc924cc4e665e6893d")
                 .unwrap(),
             id: TPMEventID::Pcr7Db,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("001004ba58a184f09be6c1f4ec75a246cc2eefa9637b48ee428b6aa9bce48c55")
                 .unwrap(),
             id: TPMEventID::Pcr7Dbx,
+            data: None,
         },
         TPMEvent {
-            name: "EV_SEPARATOR".into(),
+            event_type: EventType::Separator,
             pcr: 7,
             hash: decode("df3f619804a92fdb4057192dc43dd748ea778adc52bc498ce80524c014b81119")
                 .unwrap(),
             id: TPMEventID::Pcr7Separator,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
+            event_type: EventType::EfiVariableAuthority,
             pcr: 7,
             hash: decode("4d4a8e2c74133bbdc01a16eaf2dbb5d575afeb36f5d8dfcf609ae043909e2ee9")
                 .unwrap(),
             id: TPMEventID::Pcr7ShimCert,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
+            event_type: EventType::EfiVariableAuthority,
             pcr: 7,
             hash: decode("e8e9578f5951ef16b1c1aa18ef02944b8375ec45ed4b5d8cdb30428db4a31016")
                 .unwrap(),
             id: TPMEventID::Pcr7SbatLevel,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
+            event_type: EventType::EfiVariableAuthority,
             pcr: 7,
             hash: decode("ad5901fd581e6640c742c488083b9ac2c48255bd28a16c106c6f9df52702ee3f")
                 .unwrap(),
             id: TPMEventID::Pcr7GrubVendorDbCert,
+            data: None,
         },
     ];
 
@@ -550,404 +619,460 @@ fn test_pcr4_pcr7_bootloader_and_kernel_update() {
 fn test_pcr4_pcr7_bootloader_and_kernel_update_same_certs() {
     let this = vec![
         TPMEvent {
-            name: "EV_EFI_ACTION".into(),
+            event_type: EventType::EfiAction,
             pcr: 4,
             hash: decode("3d6772b4f84ed47595d72a2c4c5ffd15f5bb72c7507fe26f2aaee2c69d5633ba")
                 .unwrap(),
             id: TPMEventID::Pcr4EfiCall,
+            data: None,
         },
         TPMEvent {
-            name: "EV_SEPARATOR".into(),
+            event_type: EventType::Separator,
             pcr: 4,
             hash: decode("df3f619804a92fdb4057192dc43dd748ea778adc52bc498ce80524c014b81119")
                 .unwrap(),
             id: TPMEventID::Pcr4Separator,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("94896c17d49fc8c8df0cc2836611586edab1615ce7cb58cf13fc5798de56b367")
                 .unwrap(),
             id: TPMEventID::Pcr4Shim,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("bc6844fc7b59b4f0c7da70a307fc578465411d7a2c34b0f4dc2cc154c873b644")
                 .unwrap(),
             id: TPMEventID::Pcr4Grub,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("2b1dc59bc61dbbc3db11a6f3b0708c948efd46cceb7f6c8ea2024b8d1b8c829a")
                 .unwrap(),
             id: TPMEventID::Pcr4Vmlinuz,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("ccfc4bb32888a345bc8aeadaba552b627d99348c767681ab3141f5b01e40a40e")
                 .unwrap(),
             id: TPMEventID::Pcr7SecureBoot,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("adb6fc232943e39c374bf4782b6c697f43c39fca1f4b51dfceda21164e19a893")
                 .unwrap(),
             id: TPMEventID::Pcr7Pk,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("b5432fe20c624811cb0296391bfdf948ebd02f0705ab8229bea09774023f0ebf")
                 .unwrap(),
             id: TPMEventID::Pcr7Kek,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("4313e43de720194a0eabf4d6415d42b5a03a34fdc47bb1fc924cc4e665e6893d")
                 .unwrap(),
             id: TPMEventID::Pcr7Db,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("001004ba58a184f09be6c1f4ec75a246cc2eefa9637b48ee428b6aa9bce48c55")
                 .unwrap(),
             id: TPMEventID::Pcr7Dbx,
+            data: None,
         },
         TPMEvent {
-            name: "EV_SEPARATOR".into(),
+            event_type: EventType::Separator,
             pcr: 7,
             hash: decode("df3f619804a92fdb4057192dc43dd748ea778adc52bc498ce80524c014b81119")
                 .unwrap(),
             id: TPMEventID::Pcr7Separator,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
+            event_type: EventType::EfiVariableAuthority,
             pcr: 7,
             hash: decode("4d4a8e2c74133bbdc01a16eaf2dbb5d575afeb36f5d8dfcf609ae043909e2ee9")
                 .unwrap(),
             id: TPMEventID::Pcr7ShimCert,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
+            event_type: EventType::EfiVariableAuthority,
             pcr: 7,
             hash: decode("e8e9578f5951ef16b1c1aa18ef02944b8375ec45ed4b5d8cdb30428db4a31016")
                 .unwrap(),
             id: TPMEventID::Pcr7SbatLevel,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
+            event_type: EventType::EfiVariableAuthority,
             pcr: 7,
             hash: decode("ad5901fd581e6640c742c488083b9ac2c48255bd28a16c106c6f9df52702ee3f")
                 .unwrap(),
             id: TPMEventID::Pcr7GrubVendorDbCert,
+            data: None,
         },
     ];
     let that = vec![
         TPMEvent {
-            name: "EV_EFI_ACTION".into(),
+            event_type: EventType::EfiAction,
             pcr: 4,
             hash: decode("3d6772b4f84ed47595d72a2c4c5ffd15f5bb72c7507fe26f2aaee2c69d5633ba")
                 .unwrap(),
             id: TPMEventID::Pcr4EfiCall,
+            data: None,
         },
         TPMEvent {
-            name: "EV_SEPARATOR".into(),
+            event_type: EventType::Separator,
             pcr: 4,
             hash: decode("df3f619804a92fdb4057192dc43dd748ea778adc52bc498ce80524c014b81119")
                 .unwrap(),
             id: TPMEventID::Pcr4Separator,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("5ad8a618488664b5a909f08262a28354181e39ce9fc8c8df0cc2836611586eda")
                 .unwrap(),
             id: TPMEventID::Pcr4Shim,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("5f77e2690dab289917fe18116ed779941c32d316883d25f2e21ccd392926bf48")
                 .unwrap(),
             id: TPMEventID::Pcr4Grub,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("6115ef506eecf507d43279d2b5f11573c4011fab0f5bba6e22bb72dbf1d1ffd9")
                 .unwrap(),
             id: TPMEventID::Pcr4Vmlinuz,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("ccfc4bb32888a345bc8aeadaba552b627d99348c767681ab3141f5b01e40a40e")
                 .unwrap(),
             id: TPMEventID::Pcr7SecureBoot,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("adb6fc232943e39c374bf4782b6c697f43c39fca1f4b51dfceda21164e19a893")
                 .unwrap(),
             id: TPMEventID::Pcr7Pk,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("b5432fe20c624811cb0296391bfdf948ebd02f0705ab8229bea09774023f0ebf")
                 .unwrap(),
             id: TPMEventID::Pcr7Kek,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("4313e43de720194a0eabf4d6415d42b5a03a34fdc47bb1fc924cc4e665e6893d")
                 .unwrap(),
             id: TPMEventID::Pcr7Db,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("001004ba58a184f09be6c1f4ec75a246cc2eefa9637b48ee428b6aa9bce48c55")
                 .unwrap(),
             id: TPMEventID::Pcr7Dbx,
+            data: None,
         },
         TPMEvent {
-            name: "EV_SEPARATOR".into(),
+            event_type: EventType::Separator,
             pcr: 7,
             hash: decode("df3f619804a92fdb4057192dc43dd748ea778adc52bc498ce80524c014b81119")
                 .unwrap(),
             id: TPMEventID::Pcr7Separator,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
+            event_type: EventType::EfiVariableAuthority,
             pcr: 7,
             hash: decode("4d4a8e2c74133bbdc01a16eaf2dbb5d575afeb36f5d8dfcf609ae043909e2ee9")
                 .unwrap(),
             id: TPMEventID::Pcr7ShimCert,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
+            event_type: EventType::EfiVariableAuthority,
             pcr: 7,
             hash: decode("e8e9578f5951ef16b1c1aa18ef02944b8375ec45ed4b5d8cdb30428db4a31016")
                 .unwrap(),
             id: TPMEventID::Pcr7SbatLevel,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
+            event_type: EventType::EfiVariableAuthority,
             pcr: 7,
             hash: decode("ad5901fd581e6640c742c488083b9ac2c48255bd28a16c106c6f9df52702ee3f")
                 .unwrap(),
             id: TPMEventID::Pcr7GrubVendorDbCert,
+            data: None,
         },
     ];
 
     let expected_this_kernel_that_bootloader = vec![
         TPMEvent {
-            name: "EV_EFI_ACTION".into(),
+            event_type: EventType::EfiAction,
             pcr: 4,
             hash: decode("3d6772b4f84ed47595d72a2c4c5ffd15f5bb72c7507fe26f2aaee2c69d5633ba")
                 .unwrap(),
             id: TPMEventID::Pcr4EfiCall,
+            data: None,
         },
         TPMEvent {
-            name: "EV_SEPARATOR".into(),
+            event_type: EventType::Separator,
             pcr: 4,
             hash: decode("df3f619804a92fdb4057192dc43dd748ea778adc52bc498ce80524c014b81119")
                 .unwrap(),
             id: TPMEventID::Pcr4Separator,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("5ad8a618488664b5a909f08262a28354181e39ce9fc8c8df0cc2836611586eda")
                 .unwrap(),
             id: TPMEventID::Pcr4Shim,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("5f77e2690dab289917fe18116ed779941c32d316883d25f2e21ccd392926bf48")
                 .unwrap(),
             id: TPMEventID::Pcr4Grub,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("2b1dc59bc61dbbc3db11a6f3b0708c948efd46cceb7f6c8ea2024b8d1b8c829a")
                 .unwrap(),
             id: TPMEventID::Pcr4Vmlinuz,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("ccfc4bb32888a345bc8aeadaba552b627d99348c767681ab3141f5b01e40a40e")
                 .unwrap(),
             id: TPMEventID::Pcr7SecureBoot,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("adb6fc232943e39c374bf4782b6c697f43c39fca1f4b51dfceda21164e19a893")
                 .unwrap(),
             id: TPMEventID::Pcr7Pk,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("b5432fe20c624811cb0296391bfdf948ebd02f0705ab8229bea09774023f0ebf")
                 .unwrap(),
             id: TPMEventID::Pcr7Kek,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("4313e43de720194a0eabf4d6415d42b5a03a34fdc47bb1fc924cc4e665e6893d")
                 .unwrap(),
             id: TPMEventID::Pcr7Db,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("001004ba58a184f09be6c1f4ec75a246cc2eefa9637b48ee428b6aa9bce48c55")
                 .unwrap(),
             id: TPMEventID::Pcr7Dbx,
+            data: None,
         },
         TPMEvent {
-            name: "EV_SEPARATOR".into(),
+            event_type: EventType::Separator,
             pcr: 7,
             hash: decode("df3f619804a92fdb4057192dc43dd748ea778adc52bc498ce80524c014b81119")
                 .unwrap(),
             id: TPMEventID::Pcr7Separator,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
+            event_type: EventType::EfiVariableAuthority,
             pcr: 7,
             hash: decode("4d4a8e2c74133bbdc01a16eaf2dbb5d575afeb36f5d8dfcf609ae043909e2ee9")
                 .unwrap(),
             id: TPMEventID::Pcr7ShimCert,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
+            event_type: EventType::EfiVariableAuthority,
             pcr: 7,
             hash: decode("e8e9578f5951ef16b1c1aa18ef02944b8375ec45ed4b5d8cdb30428db4a31016")
                 .unwrap(),
             id: TPMEventID::Pcr7SbatLevel,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
+            event_type: EventType::EfiVariableAuthority,
             pcr: 7,
             hash: decode("ad5901fd581e6640c742c488083b9ac2c48255bd28a16c106c6f9df52702ee3f")
                 .unwrap(),
             id: TPMEventID::Pcr7GrubVendorDbCert,
+            data: None,
         },
     ];
 
     let expected_that_kernel_this_bootloader = vec![
         TPMEvent {
-            name: "EV_EFI_ACTION".into(),
+            event_type: EventType::EfiAction,
             pcr: 4,
             hash: decode("3d6772b4f84ed47595d72a2c4c5ffd15f5bb72c7507fe26f2aaee2c69d5633ba")
                 .unwrap(),
             id: TPMEventID::Pcr4EfiCall,
+            data: None,
         },
         TPMEvent {
-            name: "EV_SEPARATOR".into(),
+            event_type: EventType::Separator,
             pcr: 4,
             hash: decode("df3f619804a92fdb4057192dc43dd748ea778adc52bc498ce80524c014b81119")
                 .unwrap(),
             id: TPMEventID::Pcr4Separator,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("94896c17d49fc8c8df0cc2836611586edab1615ce7cb58cf13fc5798de56b367")
                 .unwrap(),
             id: TPMEventID::Pcr4Shim,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("bc6844fc7b59b4f0c7da70a307fc578465411d7a2c34b0f4dc2cc154c873b644")
                 .unwrap(),
             id: TPMEventID::Pcr4Grub,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("6115ef506eecf507d43279d2b5f11573c4011fab0f5bba6e22bb72dbf1d1ffd9")
                 .unwrap(),
             id: TPMEventID::Pcr4Vmlinuz,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("ccfc4bb32888a345bc8aeadaba552b627d99348c767681ab3141f5b01e40a40e")
                 .unwrap(),
             id: TPMEventID::Pcr7SecureBoot,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("adb6fc232943e39c374bf4782b6c697f43c39fca1f4b51dfceda21164e19a893")
                 .unwrap(),
             id: TPMEventID::Pcr7Pk,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("b5432fe20c624811cb0296391bfdf948ebd02f0705ab8229bea09774023f0ebf")
                 .unwrap(),
             id: TPMEventID::Pcr7Kek,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("4313e43de720194a0eabf4d6415d42b5a03a34fdc47bb1fc924cc4e665e6893d")
                 .unwrap(),
             id: TPMEventID::Pcr7Db,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("001004ba58a184f09be6c1f4ec75a246cc2eefa9637b48ee428b6aa9bce48c55")
                 .unwrap(),
             id: TPMEventID::Pcr7Dbx,
+            data: None,
         },
         TPMEvent {
-            name: "EV_SEPARATOR".into(),
+            event_type: EventType::Separator,
             pcr: 7,
             hash: decode("df3f619804a92fdb4057192dc43dd748ea778adc52bc498ce80524c014b81119")
                 .unwrap(),
             id: TPMEventID::Pcr7Separator,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
+            event_type: EventType::EfiVariableAuthority,
             pcr: 7,
             hash: decode("4d4a8e2c74133bbdc01a16eaf2dbb5d575afeb36f5d8dfcf609ae043909e2ee9")
                 .unwrap(),
             id: TPMEventID::Pcr7ShimCert,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
+            event_type: EventType::EfiVariableAuthority,
             pcr: 7,
             hash: decode("e8e9578f5951ef16b1c1aa18ef02944b8375ec45ed4b5d8cdb30428db4a31016")
                 .unwrap(),
             id: TPMEventID::Pcr7SbatLevel,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
+            event_type: EventType::EfiVariableAuthority,
             pcr: 7,
             hash: decode("ad5901fd581e6640c742c488083b9ac2c48255bd28a16c106c6f9df52702ee3f")
                 .unwrap(),
             id: TPMEventID::Pcr7GrubVendorDbCert,
+            data: None,
         },
     ];
 
@@ -966,48 +1091,54 @@ fn test_pcr4_pcr7_bootloader_and_kernel_update_same_certs() {
 fn test_pcr4_pcr7_bootloader_secureboot_update() {
     let this = vec![
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("94896c17d49fc8c8df0cc2836611586edab1615ce7cb58cf13fc5798de56b367")
                 .unwrap(),
             id: TPMEventID::Pcr4Shim,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("ccfc4bb32888a345bc8aeadaba552b627d99348c767681ab3141f5b01e40a40e")
                 .unwrap(),
             id: TPMEventID::Pcr7SecureBoot,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
+            event_type: EventType::EfiVariableAuthority,
             pcr: 7,
             hash: decode("4d4a8e2c74133bbdc01a16eaf2dbb5d575afeb36f5d8dfcf609ae043909e2ee9")
                 .unwrap(),
             id: TPMEventID::Pcr7ShimCert,
+            data: None,
         },
     ];
     let that = vec![
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("6d1b10555d58f566c4f7fd9356ce7ffa9ecc614aa04498c5db7666a577106e08")
                 .unwrap(),
             id: TPMEventID::Pcr4Shim,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("d1893345c5196d4ad661ad0ef3d87f7de0ad0343134f1296ae853b7cb8067518")
                 .unwrap(),
             id: TPMEventID::Pcr7SecureBoot,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
+            event_type: EventType::EfiVariableAuthority,
             pcr: 7,
             hash: decode("d25488faf16c53c9ba858cdb31158c35c66c637977e565117ad5c79a18fecb86")
                 .unwrap(),
             id: TPMEventID::Pcr7ShimCert,
+            data: None,
         },
     ];
     testing_logger::setup();
@@ -1033,188 +1164,214 @@ fn test_pcr4_pcr7_bootloader_secureboot_update() {
 fn test_pcr7_enable_secureboot() {
     let this = vec![
         TPMEvent {
-            name: "EV_EFI_ACTION".into(),
+            event_type: EventType::EfiAction,
             pcr: 4,
             hash: decode("3d6772b4f84ed47595d72a2c4c5ffd15f5bb72c7507fe26f2aaee2c69d5633ba")
                 .unwrap(),
             id: TPMEventID::Pcr4EfiCall,
+            data: None,
         },
         TPMEvent {
-            name: "EV_SEPARATOR".into(),
+            event_type: EventType::Separator,
             pcr: 4,
             hash: decode("df3f619804a92fdb4057192dc43dd748ea778adc52bc498ce80524c014b81119")
                 .unwrap(),
             id: TPMEventID::Pcr4Separator,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("94896c17d49fc8c8df0cc2836611586edab1615ce7cb58cf13fc5798de56b367")
                 .unwrap(),
             id: TPMEventID::Pcr4Shim,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("bc6844fc7b59b4f0c7da70a307fc578465411d7a2c34b0f4dc2cc154c873b644")
                 .unwrap(),
             id: TPMEventID::Pcr4Grub,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("2b1dc59bc61dbbc3db11a6f3b0708c948efd46cceb7f6c8ea2024b8d1b8c829a")
                 .unwrap(),
             id: TPMEventID::Pcr4Vmlinuz,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("115aa827dbccfb44d216ad9ecfda56bdea620b860a94bed5b7a27bba1c4d02d8")
                 .unwrap(),
             id: TPMEventID::Pcr7SecureBoot,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("dea7b80ab53a3daaa24d5cc46c64e1fa9ffd03739f90aadbd8c0867c4a5b4890")
                 .unwrap(),
             id: TPMEventID::Pcr7Pk,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("e670e121fcebd473b8bc41bb801301fc1d9afa33904f06f7149b74f12c47a68f")
                 .unwrap(),
             id: TPMEventID::Pcr7Kek,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("baf89a3ccace52750c5f0128351e0422a41597a1adfd50822aa363b9d124ea7c")
                 .unwrap(),
             id: TPMEventID::Pcr7Db,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("9f75b6823bff6af1024a4e2036719cdd548d3cbc2bf1de8e7ef4d0ed01f94bf9")
                 .unwrap(),
             id: TPMEventID::Pcr7Dbx,
+            data: None,
         },
         TPMEvent {
-            name: "EV_SEPARATOR".into(),
+            event_type: EventType::Separator,
             pcr: 7,
             hash: decode("df3f619804a92fdb4057192dc43dd748ea778adc52bc498ce80524c014b81119")
                 .unwrap(),
             id: TPMEventID::Pcr7Separator,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
+            event_type: EventType::EfiVariableAuthority,
             pcr: 7,
             hash: decode("922e939a5565798a5ef12fe09d8b49bf951a8e7f89a0cca7a51636693d41a34d")
                 .unwrap(),
             id: TPMEventID::Pcr7SbatLevel,
+            data: None,
         },
     ];
     let that = vec![
         TPMEvent {
-            name: "EV_EFI_ACTION".into(),
+            event_type: EventType::EfiAction,
             pcr: 4,
             hash: decode("3d6772b4f84ed47595d72a2c4c5ffd15f5bb72c7507fe26f2aaee2c69d5633ba")
                 .unwrap(),
             id: TPMEventID::Pcr4EfiCall,
+            data: None,
         },
         TPMEvent {
-            name: "EV_SEPARATOR".into(),
+            event_type: EventType::Separator,
             pcr: 4,
             hash: decode("df3f619804a92fdb4057192dc43dd748ea778adc52bc498ce80524c014b81119")
                 .unwrap(),
             id: TPMEventID::Pcr4Separator,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("94896c17d49fc8c8df0cc2836611586edab1615ce7cb58cf13fc5798de56b367")
                 .unwrap(),
             id: TPMEventID::Pcr4Shim,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("bc6844fc7b59b4f0c7da70a307fc578465411d7a2c34b0f4dc2cc154c873b644")
                 .unwrap(),
             id: TPMEventID::Pcr4Grub,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: 4,
             hash: decode("2b1dc59bc61dbbc3db11a6f3b0708c948efd46cceb7f6c8ea2024b8d1b8c829a")
                 .unwrap(),
             id: TPMEventID::Pcr4Vmlinuz,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("ccfc4bb32888a345bc8aeadaba552b627d99348c767681ab3141f5b01e40a40e")
                 .unwrap(),
             id: TPMEventID::Pcr7SecureBoot,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("adb6fc232943e39c374bf4782b6c697f43c39fca1f4b51dfceda21164e19a893")
                 .unwrap(),
             id: TPMEventID::Pcr7Pk,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("b5432fe20c624811cb0296391bfdf948ebd02f0705ab8229bea09774023f0ebf")
                 .unwrap(),
             id: TPMEventID::Pcr7Kek,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("4313e43de720194a0eabf4d6415d42b5a03a34fdc47bb1fc924cc4e665e6893d")
                 .unwrap(),
             id: TPMEventID::Pcr7Db,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("001004ba58a184f09be6c1f4ec75a246cc2eefa9637b48ee428b6aa9bce48c55")
                 .unwrap(),
             id: TPMEventID::Pcr7Dbx,
+            data: None,
         },
         TPMEvent {
-            name: "EV_SEPARATOR".into(),
+            event_type: EventType::Separator,
             pcr: 7,
             hash: decode("df3f619804a92fdb4057192dc43dd748ea778adc52bc498ce80524c014b81119")
                 .unwrap(),
             id: TPMEventID::Pcr7Separator,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
+            event_type: EventType::EfiVariableAuthority,
             pcr: 7,
             hash: decode("4d4a8e2c74133bbdc01a16eaf2dbb5d575afeb36f5d8dfcf609ae043909e2ee9")
                 .unwrap(),
             id: TPMEventID::Pcr7ShimCert,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
+            event_type: EventType::EfiVariableAuthority,
             pcr: 7,
             hash: decode("e8e9578f5951ef16b1c1aa18ef02944b8375ec45ed4b5d8cdb30428db4a31016")
                 .unwrap(),
             id: TPMEventID::Pcr7SbatLevel,
+            data: None,
         },
         TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
+            event_type: EventType::EfiVariableAuthority,
             pcr: 7,
             hash: decode("ad5901fd581e6640c742c488083b9ac2c48255bd28a16c106c6f9df52702ee3f")
                 .unwrap(),
             id: TPMEventID::Pcr7GrubVendorDbCert,
+            data: None,
         },
     ];
 
@@ -1227,40 +1384,46 @@ fn test_pcr7_enable_secureboot() {
 #[test]
 fn test_image_combinations() {
     let shim1 = TPMEvent {
-        name: "shim1".into(),
+        event_type: EventType::EfiBootServicesApplication,
         pcr: 4,
         hash: decode("f6f919856f814f30c2043b567c9434b73b658f2360175f18e49da81112216be0").unwrap(),
         id: TPMEventID::Pcr4Shim,
+        data: None,
     };
     let shim2 = TPMEvent {
-        name: "shim2".into(),
+        event_type: EventType::EfiBootServicesApplication,
         pcr: 4,
         hash: decode("5921135eb8f625f3050a92d66551ef0a6682b8c393af8ef8379a1332f1f1872f").unwrap(),
         id: TPMEventID::Pcr4Shim,
+        data: None,
     };
     let kernel1 = TPMEvent {
-        name: "kernel1".into(),
+        event_type: EventType::EfiBootServicesApplication,
         pcr: 4,
         hash: decode("2b1dc59bc61dbbc3db11a6f3b0708c948efd46cceb7f6c8ea2024b8d1b8c829a").unwrap(),
         id: TPMEventID::Pcr4Vmlinuz,
+        data: None,
     };
     let kernel2 = TPMEvent {
-        name: "kernel2".into(),
+        event_type: EventType::EfiBootServicesApplication,
         pcr: 4,
         hash: decode("d4735e3a265e16eee03f59718b9b5d03019c07d8b6c51f90da3a666eec13ab35").unwrap(),
         id: TPMEventID::Pcr4Vmlinuz,
+        data: None,
     };
     let kernel3 = TPMEvent {
-        name: "kernel3".into(),
+        event_type: EventType::EfiBootServicesApplication,
         pcr: 4,
         hash: decode("4e07408562bedb8b60ce05c1decfe3ad16b72230967de01f640b7e4729b49fce").unwrap(),
         id: TPMEventID::Pcr4Vmlinuz,
+        data: None,
     };
     let kernel4 = TPMEvent {
-        name: "kernel4".into(),
+        event_type: EventType::EfiBootServicesApplication,
         pcr: 4,
         hash: decode("4b227777d4dd1fc61c6f884f48641d02b4d121d3fd328cb08b5531fcacdabf8a").unwrap(),
         id: TPMEventID::Pcr4Vmlinuz,
+        data: None,
     };
 
     let images = vec![
@@ -1289,22 +1452,492 @@ fn test_image_combinations() {
     );
 }
 
+#[test]
+fn test_image_combinations_branch_limit() {
+    let kernels: Vec<TPMEvent> = (0..4u8)
+        .map(|i| TPMEvent {
+            event_type: EventType::EfiBootServicesApplication,
+            pcr: 4,
+            hash: vec![i; 32],
+            id: TPMEventID::Pcr4Vmlinuz,
+            data: None,
+        })
+        .collect();
+    let images: Vec<Vec<TPMEvent>> = kernels.iter().map(|k| vec![k.clone()]).collect();
+
+    // 6 pairs of images, 2 branches per pair
+    let options = CombineOptions {
+        max_branches: Some(11),
+        ..Default::default()
+    };
+    assert_eq!(
+        combine_images_with(&images, &options),
+        Err(CombineError::BranchLimit(11))
+    );
+
+    let options = CombineOptions {
+        max_branches: Some(12),
+        ..Default::default()
+    };
+    let res = combine_images_with(&images, &options).unwrap();
+    // Duplicates across pairs are dropped
+    let image_pcrs: Vec<Vec<Pcr>> = images.iter().map(|e| compile_pcrs(e)).collect();
+    assert_eq!(res, image_pcrs);
+}
+
+#[test]
+fn test_combine_labeled_images_provenance() {
+    let shim1 = TPMEvent {
+        event_type: EventType::EfiBootServicesApplication,
+        pcr: 4,
+        hash: vec![1; 32],
+        id: TPMEventID::Pcr4Shim,
+        data: None,
+    };
+    let shim2 = TPMEvent {
+        event_type: EventType::EfiBootServicesApplication,
+        pcr: 4,
+        hash: vec![2; 32],
+        id: TPMEventID::Pcr4Shim,
+        data: None,
+    };
+    let kernel1 = TPMEvent {
+        event_type: EventType::EfiBootServicesApplication,
+        pcr: 4,
+        hash: vec![3; 32],
+        id: TPMEventID::Pcr4Vmlinuz,
+        data: None,
+    };
+    let kernel2 = TPMEvent {
+        event_type: EventType::EfiBootServicesApplication,
+        pcr: 4,
+        hash: vec![4; 32],
+        id: TPMEventID::Pcr4Vmlinuz,
+        data: None,
+    };
+    let images = vec![
+        LabeledImage::new("a", vec![shim1.clone(), kernel1.clone()]),
+        LabeledImage::new("b", vec![shim1.clone(), kernel2.clone()]),
+        LabeledImage::new("c", vec![shim2.clone(), kernel2.clone()]),
+    ];
+    let labels = |l: &[&str]| BTreeSet::from_iter(l.iter().map(|s| s.to_string()));
+
+    let res = combine_labeled_images(&images, &CombineOptions::default()).unwrap();
+    let find = |events: &[TPMEvent]| {
+        res.iter()
+            .find(|c| c.pcrs == compile_pcrs(events))
+            .unwrap()
+            .provenance
+            .clone()
+    };
+
+    assert_eq!(res.len(), 4);
+    // Same shim in a and b, and merged from the a-b and a-c combinations
+    assert_eq!(
+        find(&[shim1.clone(), kernel1.clone()]),
+        Provenance::from([
+            ("TPMEG_BOOTLOADER".into(), labels(&["a", "b"])),
+            ("TPMEG_LINUX".into(), labels(&["a"])),
+        ])
+    );
+    assert_eq!(
+        find(&[shim2.clone(), kernel1.clone()]),
+        Provenance::from([
+            ("TPMEG_BOOTLOADER".into(), labels(&["c"])),
+            ("TPMEG_LINUX".into(), labels(&["a"])),
+        ])
+    );
+    assert_eq!(
+        find(&[shim1.clone(), kernel2.clone()]),
+        Provenance::from([
+            ("TPMEG_BOOTLOADER".into(), labels(&["a", "b"])),
+            ("TPMEG_LINUX".into(), labels(&["b", "c"])),
+        ])
+    );
+}
+
+fn constrained_images() -> Vec<LabeledImage> {
+    let event = |hash: u8, id: TPMEventID| TPMEvent {
+        event_type: id.event_type(),
+        pcr: 4,
+        hash: vec![hash; 32],
+        id,
+        data: None,
+    };
+    vec![
+        LabeledImage::new(
+            "a",
+            vec![
+                event(1, TPMEventID::Pcr4Shim),
+                event(3, TPMEventID::Pcr4Vmlinuz),
+            ],
+        ),
+        LabeledImage::new(
+            "b",
+            vec![
+                event(2, TPMEventID::Pcr4Shim),
+                event(4, TPMEventID::Pcr4Vmlinuz),
+            ],
+        ),
+    ]
+}
+
+#[test]
+fn test_event_branches_match_event_tree() {
+    let images = constrained_images();
+    let map_this = tpm_event_id_hashmap(&images[0].events);
+    let map_that = tpm_event_id_hashmap(&images[1].events);
+    let constraints = Constraints::new(&[], &images).unwrap();
+
+    let first = TPMEventID::PcrRootNodeEvent.next().unwrap();
+    let tree_branches: Vec<(Vec<TPMEvent>, u32, u32)> =
+        event_subtree(&first, &map_this, &map_that, 0, 0, &constraints)
+            .unwrap()
+            .iter()
+            .flat_map(|t| t.valid_branches())
+            .map(|b| {
+                let last = b.last().unwrap().clone();
+                let events = b.into_iter().map(|e| e.event).collect();
+                (events, last.group_this, last.group_that)
+            })
+            .collect();
+    let branches: Vec<_> = EventBranches::new(&map_this, &map_that, &constraints).collect();
+    assert_eq!(branches.len(), 4);
+    assert_eq!(branches, tree_branches);
+
+    // Branches are walked one at a time
+    let mut branches = EventBranches::new(&map_this, &map_that, &constraints);
+    assert_eq!(branches.next().unwrap(), tree_branches[0]);
+    assert_eq!(branches.frames.len(), 2);
+}
+
+#[test]
+fn test_combine_constraint_same_image() {
+    let images = constrained_images();
+    let options = CombineOptions {
+        constraints: vec![CombineConstraint::SameImage(TPMEG_BOOTLOADER | TPMEG_LINUX)],
+        ..Default::default()
+    };
+
+    let res: Vec<Vec<Pcr>> = combine_labeled_images(&images, &options)
+        .unwrap()
+        .into_iter()
+        .map(|c| c.pcrs)
+        .collect();
+    let expected: Vec<Vec<Pcr>> = images.iter().map(|i| compile_pcrs(&i.events)).collect();
+    assert_eq!(res, expected);
+}
+
+#[test]
+fn test_combine_constraint_pinned() {
+    let images = constrained_images();
+    let options = CombineOptions {
+        constraints: vec![CombineConstraint::Pinned(TPMEG_LINUX, "a".into())],
+        ..Default::default()
+    };
+
+    let res: Vec<Vec<Pcr>> = combine_labeled_images(&images, &options)
+        .unwrap()
+        .into_iter()
+        .map(|c| c.pcrs)
+        .collect();
+    let kernel = images[0].events[1].clone();
+    assert_eq!(
+        res,
+        vec![
+            compile_pcrs(&[images[0].events[0].clone(), kernel.clone()]),
+            compile_pcrs(&[images[1].events[0].clone(), kernel]),
+        ]
+    );
+}
+
+#[test]
+fn test_combine_constraint_pinned_prunes_all() {
+    let mut images = constrained_images();
+    images.push(LabeledImage::new(
+        "c",
+        vec![TPMEvent {
+            event_type: EventType::EfiBootServicesApplication,
+            pcr: 4,
+            hash: vec![5; 32],
+            id: TPMEventID::Pcr4Vmlinuz,
+            data: None,
+        }],
+    ));
+    let options = CombineOptions {
+        constraints: vec![CombineConstraint::Pinned(TPMEG_LINUX, "c".into())],
+        ..Default::default()
+    };
+
+    // Only combinations with image c can log its kernel
+    let res = combine_labeled_images(&images, &options).unwrap();
+    assert_eq!(res.len(), 2);
+    assert!(
+        res.iter()
+            .all(|c| c.provenance["TPMEG_LINUX"] == BTreeSet::from(["c".to_string()]))
+    );
+}
+
+#[test]
+fn test_combine_constraint_unknown_image() {
+    let options = CombineOptions {
+        constraints: vec![CombineConstraint::Pinned(TPMEG_LINUX, "z".into())],
+        ..Default::default()
+    };
+    assert_eq!(
+        combine_labeled_images(&constrained_images(), &options),
+        Err(CombineError::UnknownImage("z".into()))
+    );
+}
+
+#[test]
+fn test_combine_upgrade_path() {
+    let mut images = constrained_images();
+    images.push(LabeledImage::new(
+        "c",
+        vec![
+            images[1].events[0].clone(),
+            TPMEvent {
+                event_type: EventType::EfiBootServicesApplication,
+                pcr: 4,
+                hash: vec![5; 32],
+                id: TPMEventID::Pcr4Vmlinuz,
+                data: None,
+            },
+        ],
+    ));
+
+    let steps = combine_upgrade_path(&images, &CombineOptions::default()).unwrap();
+    assert_eq!(steps.len(), 2);
+
+    assert_eq!((steps[0].from.as_str(), steps[0].to.as_str()), ("a", "b"));
+    assert_eq!(
+        steps[0].changed_groups,
+        vec!["TPMEG_LINUX", "TPMEG_BOOTLOADER"]
+    );
+    assert_eq!(steps[0].pcrs.len(), 4);
+    // Only image b is needed to go from b to c
+    let image_b = compile_pcrs(&images[1].events);
+    assert_eq!(steps[0].retirable.len(), 3);
+    assert!(!steps[0].retirable.contains(&image_b));
+
+    assert_eq!((steps[1].from.as_str(), steps[1].to.as_str()), ("b", "c"));
+    assert_eq!(steps[1].changed_groups, vec!["TPMEG_LINUX"]);
+    assert_eq!(
+        steps[1]
+            .pcrs
+            .iter()
+            .map(|c| c.pcrs.clone())
+            .collect::<Vec<_>>(),
+        vec![image_b.clone(), compile_pcrs(&images[2].events)]
+    );
+    // Nodes stay on image c
+    assert_eq!(steps[1].retirable, vec![image_b]);
+}
+
+#[test]
+fn test_combine_upgrade_path_retirable() {
+    let images = constrained_images();
+    // a -> b -> a: the values of image a are needed until the end
+    let path = [images[0].clone(), images[1].clone(), images[0].clone()];
+    let steps = combine_upgrade_path(&path, &CombineOptions::default()).unwrap();
+    let image_a = compile_pcrs(&images[0].events);
+    let image_b = compile_pcrs(&images[1].events);
+    assert_eq!(steps[0].retirable.len(), 0);
+    assert_eq!(steps[1].pcrs.len(), 4);
+    assert_eq!(steps[1].retirable.len(), 3);
+    assert!(steps[1].retirable.contains(&image_b));
+    assert!(!steps[1].retirable.contains(&image_a));
+}
+
+#[test]
+fn test_combine_upgrade_path_short() {
+    let images = constrained_images();
+    assert_eq!(
+        combine_upgrade_path(&images[..1], &CombineOptions::default()),
+        Err(CombineError::ShortUpgradePath(1))
+    );
+    assert_eq!(
+        combine_upgrade_path(&[], &CombineOptions::default()),
+        Err(CombineError::ShortUpgradePath(0))
+    );
+}
+
+#[test]
+fn test_combine_images_per_pcr() {
+    let event = |hash: u8, pcr: u8, id: TPMEventID| TPMEvent {
+        event_type: id.event_type(),
+        pcr,
+        hash: vec![hash; 32],
+        id,
+        data: None,
+    };
+    // Shim and SbatLevel always change together
+    let images = vec![
+        vec![
+            event(1, 4, TPMEventID::Pcr4Shim),
+            event(2, 7, TPMEventID::Pcr7SbatLevel),
+        ],
+        vec![
+            event(3, 4, TPMEventID::Pcr4Shim),
+            event(4, 7, TPMEventID::Pcr7SbatLevel),
+        ],
+    ];
+    let pcrs: Vec<Vec<Pcr>> = images.iter().map(|i| compile_pcrs(i)).collect();
+
+    let res = combine_images_per_pcr(&images, &CombineOptions::default()).unwrap();
+    assert_eq!(
+        res.values,
+        BTreeMap::from([
+            (
+                4,
+                BTreeSet::from([pcrs[0][0].value.clone(), pcrs[1][0].value.clone()])
+            ),
+            (
+                7,
+                BTreeSet::from([pcrs[0][1].value.clone(), pcrs[1][1].value.clone()])
+            ),
+        ])
+    );
+    assert_eq!(res.looseness.combined, 2);
+    assert_eq!(res.looseness.admitted, 4);
+    assert_eq!(res.looseness.extra, 2);
+    assert_eq!(res.looseness.extra_sample.len(), 2);
+    assert!(res.looseness.extra_sample.contains(&BTreeMap::from([
+        (4, pcrs[0][0].value.clone()),
+        (7, pcrs[1][1].value.clone()),
+    ])));
+    assert!(res.looseness.extra_sample.contains(&BTreeMap::from([
+        (4, pcrs[1][0].value.clone()),
+        (7, pcrs[0][1].value.clone()),
+    ])));
+}
+
+#[test]
+fn test_per_pcr_values_different_pcrs() {
+    let pcr = |id: u64, value: u8| Pcr {
+        id,
+        value: vec![value; 32],
+        events: vec![],
+    };
+    // PCR 11 is only measured by the second image
+    let combinations = vec![
+        vec![pcr(4, 1), pcr(7, 2)],
+        vec![pcr(4, 3), pcr(7, 2), pcr(11, 4)],
+    ];
+    let res = per_pcr_values(&combinations);
+    // {4, 7}: 2 * 1 sets, {4, 7, 11}: 2 * 1 * 1 sets
+    assert_eq!(res.looseness.combined, 2);
+    assert_eq!(res.looseness.admitted, 4);
+    assert_eq!(res.looseness.extra, 2);
+    assert!(
+        res.looseness
+            .extra_sample
+            .contains(&BTreeMap::from([(4, vec![3; 32]), (7, vec![2; 32])]))
+    );
+    assert!(res.looseness.extra_sample.contains(&BTreeMap::from([
+        (4, vec![1; 32]),
+        (7, vec![2; 32]),
+        (11, vec![4; 32])
+    ])));
+}
+
+#[test]
+fn test_per_pcr_values_sample() {
+    let pcr = |id: u64, value: u8| Pcr {
+        id,
+        value: vec![value; 32],
+        events: vec![],
+    };
+    // 4 values for each of 8 PCRs, paired up: 4^8 sets admitted
+    let combinations: Vec<Vec<Pcr>> = (0..4u8)
+        .map(|v| (0..8).map(|id| pcr(id, v)).collect())
+        .collect();
+    let res = per_pcr_values(&combinations);
+    assert_eq!(res.looseness.admitted, 65536);
+    assert_eq!(res.looseness.extra, 65532);
+    assert_eq!(res.looseness.extra_sample.len(), EXTRA_SAMPLE_SIZE);
+
+    let res = per_pcr_values(&[]);
+    assert!(res.values.is_empty());
+    assert_eq!(res.looseness.admitted, 0);
+    assert_eq!(res.looseness.extra, 0);
+    assert!(res.looseness.extra_sample.is_empty());
+}
+
+#[test]
+fn test_render_event_trees() {
+    let images = constrained_images();
+    let text = render_event_trees(&images, &CombineOptions::default(), TreeFormat::Text).unwrap();
+    assert_eq!(
+        text,
+        "this=a that=b\n\
+         Pcr4Shim 01010101.. this=0x4 that=0x0\n\
+         ├── Pcr4Vmlinuz 03030303.. this=0x6 that=0x0\n\
+         └── Pcr4Vmlinuz 04040404.. this=0x4 that=0x2\n\
+         Pcr4Shim 02020202.. this=0x0 that=0x4\n\
+         ├── Pcr4Vmlinuz 03030303.. this=0x2 that=0x4\n\
+         └── Pcr4Vmlinuz 04040404.. this=0x0 that=0x6\n"
+    );
+
+    let dot = render_event_trees(&images, &CombineOptions::default(), TreeFormat::Dot).unwrap();
+    assert!(dot.starts_with("digraph {\nsubgraph cluster_0 {\nlabel=\"this=a that=b\";\n"));
+    assert!(dot.contains("p0t1_2 [label=\"Pcr4Vmlinuz 04040404.. this=0x0 that=0x6\"];\n"));
+    assert!(dot.ends_with("}\n}\n"));
+}
+
+#[test]
+fn test_render_event_trees_conflict() {
+    let event = |hash: u8, pcr: u8, id: TPMEventID| TPMEvent {
+        event_type: id.event_type(),
+        pcr,
+        hash: vec![hash; 32],
+        id,
+        data: None,
+    };
+    let images = vec![
+        LabeledImage::new(
+            "a",
+            vec![
+                event(1, 4, TPMEventID::Pcr4Shim),
+                event(2, 7, TPMEventID::Pcr7SecureBoot),
+                event(3, 7, TPMEventID::Pcr7ShimCert),
+            ],
+        ),
+        LabeledImage::new(
+            "b",
+            vec![
+                event(4, 4, TPMEventID::Pcr4Shim),
+                event(5, 7, TPMEventID::Pcr7SecureBoot),
+                event(6, 7, TPMEventID::Pcr7ShimCert),
+            ],
+        ),
+    ];
+
+    let text = render_event_trees(&images, &CombineOptions::default(), TreeFormat::Text).unwrap();
+    assert!(text.contains("└── CONFLICT Pcr7ShimCert this=0x4 that=0x8\n"));
+    let dot = render_event_trees(&images, &CombineOptions::default(), TreeFormat::Dot).unwrap();
+    assert!(dot.contains("[label=\"CONFLICT Pcr7ShimCert this=0x4 that=0x8\", color=red];"));
+}
+
 #[test]
 fn test_combine_one_image() {
     let images = vec![vec![
         TPMEvent {
-            name: "pcr4".into(),
+            event_type: EventType::EfiAction,
             pcr: 4,
             hash: decode("f6f919856f814f30c2043b567c9434b73b658f2360175f18e49da81112216be0")
                 .unwrap(),
             id: TPMEventID::Pcr4EfiCall,
+            data: None,
         },
         TPMEvent {
-            name: "pcr7".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: 7,
             hash: decode("1111111111111111111111111111111111111111111111111111111111111111")
                 .unwrap(),
             id: TPMEventID::Pcr7SecureBoot,
+            data: None,
         },
     ]];
 
@@ -1318,326 +1951,395 @@ fn test_combine_one_image() {
 fn test_pcr14_mok_list_trusted() {
     let event = TPMEvent {
         pcr: 14,
-        name: "EV_IPL".to_string(),
+        event_type: EventType::Ipl,
         hash: hex::decode("4bf5122f344554c53bde2ebb8cd2b7e3d1600ad631c385a5d7cce23c7785459a")
             .unwrap(),
         id: TPMEventID::Pcr14MokListTrusted,
+        data: None,
     };
     let res = combine_images(&[vec![event.clone()], vec![event.clone()]]);
     assert_eq!(res, vec![compile_pcrs(&[event])]);
 }
 
+#[test]
+fn test_combine_deduplicates() {
+    // Three kernel versions, each pair yields both of its images' PCRs
+    let images: Vec<Vec<TPMEvent>> = (1..=3)
+        .map(|hash| {
+            vec![TPMEvent {
+                pcr: 4,
+                event_type: EventType::EfiBootServicesApplication,
+                hash: vec![hash; 32],
+                id: TPMEventID::Pcr4Vmlinuz,
+                data: None,
+            }]
+        })
+        .collect();
+
+    let constraints = Constraints::new(&[], &[]).unwrap();
+    let branches: usize = images
+        .iter()
+        .combinations(2)
+        .map(|p| {
+            let map_this = tpm_event_id_hashmap(p[0]);
+            let map_that = tpm_event_id_hashmap(p[1]);
+            EventBranches::new(&map_this, &map_that, &constraints).count()
+        })
+        .sum();
+    assert_eq!(branches, 6);
+
+    let res = combine_images(&images);
+    assert_eq!(res.len(), 3);
+    let image_pcrs: Vec<Vec<Pcr>> = images.iter().map(|e| compile_pcrs(e)).collect();
+    assert_eq!(res, image_pcrs);
+    assert_eq!(combine(&images[0], &images[1]), image_pcrs[..2]);
+}
+
 #[test]
 fn test_all_pcrs_2_images() {
     let images = vec![
         vec![
             TPMEvent {
                 pcr: 4,
-                name: "EV_EFI_ACTION".to_string(),
+                event_type: EventType::EfiAction,
                 hash: hex::decode(
                     "3d6772b4f84ed47595d72a2c4c5ffd15f5bb72c7507fe26f2aaee2c69d5633ba",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr4EfiCall,
+                data: None,
             },
             TPMEvent {
                 pcr: 4,
-                name: "EV_SEPARATOR".to_string(),
+                event_type: EventType::Separator,
                 hash: hex::decode(
                     "df3f619804a92fdb4057192dc43dd748ea778adc52bc498ce80524c014b81119",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr4Separator,
+                data: None,
             },
             TPMEvent {
                 pcr: 4,
-                name: "EV_EFI_BOOT_SERVICES_APPLICATION".to_string(),
+                event_type: EventType::EfiBootServicesApplication,
                 hash: hex::decode(
                     "94896c17d49fc8c8df0cc2836611586edab1615ce7cb58cf13fc5798de56b367",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr4Shim,
+                data: None,
             },
             TPMEvent {
                 pcr: 4,
-                name: "EV_EFI_BOOT_SERVICES_APPLICATION".to_string(),
+                event_type: EventType::EfiBootServicesApplication,
                 hash: hex::decode(
                     "bc6844fc7b59b4f0c7da70a307fc578465411d7a2c34b0f4dc2cc154c873b644",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr4Grub,
+                data: None,
             },
             TPMEvent {
                 pcr: 4,
-                name: "EV_EFI_BOOT_SERVICES_APPLICATION".to_string(),
+                event_type: EventType::EfiBootServicesApplication,
                 hash: hex::decode(
                     "2b1dc59bc61dbbc3db11a6f3b0708c948efd46cceb7f6c8ea2024b8d1b8c829a",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr4Vmlinuz,
+                data: None,
             },
             TPMEvent {
                 pcr: 7,
-                name: "EV_EFI_VARIABLE_DRIVER_CONFIG".to_string(),
+                event_type: EventType::EfiVariableDriverConfig,
                 hash: hex::decode(
                     "ccfc4bb32888a345bc8aeadaba552b627d99348c767681ab3141f5b01e40a40e",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr7SecureBoot,
+                data: None,
             },
             TPMEvent {
                 pcr: 7,
-                name: "EV_EFI_VARIABLE_DRIVER_CONFIG".to_string(),
+                event_type: EventType::EfiVariableDriverConfig,
                 hash: hex::decode(
                     "adb6fc232943e39c374bf4782b6c697f43c39fca1f4b51dfceda21164e19a893",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr7Pk,
+                data: None,
             },
             TPMEvent {
                 pcr: 7,
-                name: "EV_EFI_VARIABLE_DRIVER_CONFIG".to_string(),
+                event_type: EventType::EfiVariableDriverConfig,
                 hash: hex::decode(
                     "b5432fe20c624811cb0296391bfdf948ebd02f0705ab8229bea09774023f0ebf",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr7Kek,
+                data: None,
             },
             TPMEvent {
                 pcr: 7,
-                name: "EV_EFI_VARIABLE_DRIVER_CONFIG".to_string(),
+                event_type: EventType::EfiVariableDriverConfig,
                 hash: hex::decode(
                     "4313e43de720194a0eabf4d6415d42b5a03a34fdc47bb1fc924cc4e665e6893d",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr7Db,
+                data: None,
             },
             TPMEvent {
                 pcr: 7,
-                name: "EV_EFI_VARIABLE_DRIVER_CONFIG".to_string(),
+                event_type: EventType::EfiVariableDriverConfig,
                 hash: hex::decode(
                     "001004ba58a184f09be6c1f4ec75a246cc2eefa9637b48ee428b6aa9bce48c55",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr7Dbx,
+                data: None,
             },
             TPMEvent {
                 pcr: 7,
-                name: "EV_SEPARATOR".to_string(),
+                event_type: EventType::Separator,
                 hash: hex::decode(
                     "df3f619804a92fdb4057192dc43dd748ea778adc52bc498ce80524c014b81119",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr7Separator,
+                data: None,
             },
             TPMEvent {
                 pcr: 7,
-                name: "EV_EFI_VARIABLE_AUTHORITY".to_string(),
+                event_type: EventType::EfiVariableAuthority,
                 hash: hex::decode(
                     "4d4a8e2c74133bbdc01a16eaf2dbb5d575afeb36f5d8dfcf609ae043909e2ee9",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr7ShimCert,
+                data: None,
             },
             TPMEvent {
                 pcr: 7,
-                name: "EV_EFI_VARIABLE_AUTHORITY".to_string(),
+                event_type: EventType::EfiVariableAuthority,
                 hash: hex::decode(
                     "e8e9578f5951ef16b1c1aa18ef02944b8375ec45ed4b5d8cdb30428db4a31016",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr7SbatLevel,
+                data: None,
             },
             TPMEvent {
                 pcr: 7,
-                name: "EV_EFI_VARIABLE_AUTHORITY".to_string(),
+                event_type: EventType::EfiVariableAuthority,
                 hash: hex::decode(
                     "ad5901fd581e6640c742c488083b9ac2c48255bd28a16c106c6f9df52702ee3f",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr7GrubMokListCert,
+                data: None,
             },
             TPMEvent {
                 pcr: 14,
-                name: "EV_IPL".to_string(),
+                event_type: EventType::Ipl,
                 hash: hex::decode(
                     "e8e48e3ad10bc243341b4663c0057aef0ec7894ccc9ecb0598f0830fa57f7220",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr14MokList,
+                data: None,
             },
             TPMEvent {
                 pcr: 14,
-                name: "EV_IPL".to_string(),
+                event_type: EventType::Ipl,
                 hash: hex::decode(
                     "8d8a3aae50d5d25838c95c034aadce7b548c9a952eb7925e366eda537c59c3b0",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr14MokListX,
+                data: None,
             },
             TPMEvent {
                 pcr: 14,
-                name: "EV_IPL".to_string(),
+                event_type: EventType::Ipl,
                 hash: hex::decode(
                     "4bf5122f344554c53bde2ebb8cd2b7e3d1600ad631c385a5d7cce23c7785459a",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr14MokListTrusted,
+                data: None,
             },
         ],
         vec![
             TPMEvent {
                 pcr: 4,
-                name: "EV_EFI_ACTION".to_string(),
+                event_type: EventType::EfiAction,
                 hash: hex::decode(
                     "3d6772b4f84ed47595d72a2c4c5ffd15f5bb72c7507fe26f2aaee2c69d5633ba",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr4EfiCall,
+                data: None,
             },
             TPMEvent {
                 pcr: 4,
-                name: "EV_SEPARATOR".to_string(),
+                event_type: EventType::Separator,
                 hash: hex::decode(
                     "df3f619804a92fdb4057192dc43dd748ea778adc52bc498ce80524c014b81119",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr4Separator,
+                data: None,
             },
             TPMEvent {
                 pcr: 4,
-                name: "EV_EFI_BOOT_SERVICES_APPLICATION".to_string(),
+                event_type: EventType::EfiBootServicesApplication,
                 hash: hex::decode(
                     "94896c17d49fc8c8df0cc2836611586edab1615ce7cb58cf13fc5798de56b367",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr4Shim,
+                data: None,
             },
             TPMEvent {
                 pcr: 4,
-                name: "EV_EFI_BOOT_SERVICES_APPLICATION".to_string(),
+                event_type: EventType::EfiBootServicesApplication,
                 hash: hex::decode(
                     "f45c2c974192366a5391e077c3cbf91e735e86eba2037fd86a1f1501818f73f4",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr4Grub,
+                data: None,
             },
             TPMEvent {
                 pcr: 4,
-                name: "EV_EFI_BOOT_SERVICES_APPLICATION".to_string(),
+                event_type: EventType::EfiBootServicesApplication,
                 hash: hex::decode(
                     "f31e645e5e9ed131eea5dca0a18893a21e5625b4a56314fa39587ddc33a7fa91",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr4Vmlinuz,
+                data: None,
             },
             TPMEvent {
                 pcr: 7,
-                name: "EV_EFI_VARIABLE_DRIVER_CONFIG".to_string(),
+                event_type: EventType::EfiVariableDriverConfig,
                 hash: hex::decode(
                     "ccfc4bb32888a345bc8aeadaba552b627d99348c767681ab3141f5b01e40a40e",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr7SecureBoot,
+                data: None,
             },
             TPMEvent {
                 pcr: 7,
-                name: "EV_EFI_VARIABLE_DRIVER_CONFIG".to_string(),
+                event_type: EventType::EfiVariableDriverConfig,
                 hash: hex::decode(
                     "adb6fc232943e39c374bf4782b6c697f43c39fca1f4b51dfceda21164e19a893",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr7Pk,
+                data: None,
             },
             TPMEvent {
                 pcr: 7,
-                name: "EV_EFI_VARIABLE_DRIVER_CONFIG".to_string(),
+                event_type: EventType::EfiVariableDriverConfig,
                 hash: hex::decode(
                     "b5432fe20c624811cb0296391bfdf948ebd02f0705ab8229bea09774023f0ebf",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr7Kek,
+                data: None,
             },
             TPMEvent {
                 pcr: 7,
-                name: "EV_EFI_VARIABLE_DRIVER_CONFIG".to_string(),
+                event_type: EventType::EfiVariableDriverConfig,
                 hash: hex::decode(
                     "4313e43de720194a0eabf4d6415d42b5a03a34fdc47bb1fc924cc4e665e6893d",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr7Db,
+                data: None,
             },
             TPMEvent {
                 pcr: 7,
-                name: "EV_EFI_VARIABLE_DRIVER_CONFIG".to_string(),
+                event_type: EventType::EfiVariableDriverConfig,
                 hash: hex::decode(
                     "001004ba58a184f09be6c1f4ec75a246cc2eefa9637b48ee428b6aa9bce48c55",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr7Dbx,
+                data: None,
             },
             TPMEvent {
                 pcr: 7,
-                name: "EV_SEPARATOR".to_string(),
+                event_type: EventType::Separator,
                 hash: hex::decode(
                     "df3f619804a92fdb4057192dc43dd748ea778adc52bc498ce80524c014b81119",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr7Separator,
+                data: None,
             },
             TPMEvent {
                 pcr: 7,
-                name: "EV_EFI_VARIABLE_AUTHORITY".to_string(),
+                event_type: EventType::EfiVariableAuthority,
                 hash: hex::decode(
                     "4d4a8e2c74133bbdc01a16eaf2dbb5d575afeb36f5d8dfcf609ae043909e2ee9",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr7ShimCert,
+                data: None,
             },
             TPMEvent {
                 pcr: 7,
-                name: "EV_EFI_VARIABLE_AUTHORITY".to_string(),
+                event_type: EventType::EfiVariableAuthority,
                 hash: hex::decode(
                     "e8e9578f5951ef16b1c1aa18ef02944b8375ec45ed4b5d8cdb30428db4a31016",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr7SbatLevel,
+                data: None,
             },
             TPMEvent {
                 pcr: 7,
-                name: "EV_EFI_VARIABLE_AUTHORITY".to_string(),
+                event_type: EventType::EfiVariableAuthority,
                 hash: hex::decode(
                     "ad5901fd581e6640c742c488083b9ac2c48255bd28a16c106c6f9df52702ee3f",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr7GrubMokListCert,
+                data: None,
             },
             TPMEvent {
                 pcr: 14,
-                name: "EV_IPL".to_string(),
+                event_type: EventType::Ipl,
                 hash: hex::decode(
                     "e8e48e3ad10bc243341b4663c0057aef0ec7894ccc9ecb0598f0830fa57f7220",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr14MokList,
+                data: None,
             },
             TPMEvent {
                 pcr: 14,
-                name: "EV_IPL".to_string(),
+                event_type: EventType::Ipl,
                 hash: hex::decode(
                     "8d8a3aae50d5d25838c95c034aadce7b548c9a952eb7925e366eda537c59c3b0",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr14MokListX,
+                data: None,
             },
             TPMEvent {
                 pcr: 14,
-                name: "EV_IPL".to_string(),
+                event_type: EventType::Ipl,
                 hash: hex::decode(
                     "4bf5122f344554c53bde2ebb8cd2b7e3d1600ad631c385a5d7cce23c7785459a",
                 )
                 .unwrap(),
                 id: TPMEventID::Pcr14MokListTrusted,
+                data: None,
             },
         ],
     ];
@@ -5,13 +5,19 @@
 use lief::generic::Section;
 use sha2::{Digest, Sha256};
 use std::collections::HashSet;
+use std::fmt;
 
+use crate::authenticode::AuthenticodeError;
+use crate::certs::{self, CertDbParsingError, X509Cert};
 use crate::esp;
 use crate::linux;
 use crate::mok;
+use crate::pefile::PeFile;
 use crate::shim;
+use crate::tpmevents::EventType;
 use crate::tpmevents::TPMEvent;
 use crate::tpmevents::TPMEventID;
+use crate::tpmevents::model;
 use crate::uefi;
 use crate::uefi::efivars;
 
@@ -19,6 +25,7 @@ const EV_SEPARATOR_HASH: [u8; 32] = [
     223, 63, 97, 152, 4, 169, 47, 219, 64, 87, 25, 45, 196, 61, 215, 72, 234, 119, 138, 220, 82,
     188, 73, 140, 232, 5, 36, 192, 20, 184, 17, 25,
 ];
+const EV_SEPARATOR_DATA: [u8; 4] = [0; 4];
 const MODELS_SB_VARIABLES: [TPMEventID; 4] = [
     TPMEventID::Pcr7Pk,
     TPMEventID::Pcr7Kek,
@@ -46,62 +53,152 @@ const MODELS_MOKVARS: [TPMEventID; 3] = [
     TPMEventID::Pcr14MokListX,
     TPMEventID::Pcr14MokListTrusted,
 ];
+/// Authority events of grub and vmlinuz, in the order of the databases shim
+/// verifies images against
+const MODELS_GRUB_AUTHORITY: [TPMEventID; 3] = [
+    TPMEventID::Pcr7GrubDbCert,
+    TPMEventID::Pcr7GrubVendorDbCert,
+    TPMEventID::Pcr7GrubMokListCert,
+];
+const MODELS_KERNEL_AUTHORITY: [TPMEventID; 3] = [
+    TPMEventID::Pcr7KernelDbCert,
+    TPMEventID::Pcr7KernelVendorDbCert,
+    TPMEventID::Pcr7KernelMokListCert,
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ComputeError {
+    /// The Authenticode hash of the image at the path could not be computed,
+    /// or does not match the digest its signatures were made over
+    Image(String, AuthenticodeError),
+    /// The named signature database could not be parsed
+    Database(String, CertDbParsingError),
+    /// No certificate firmware or shim trusts verifies the image at the path
+    Unverified(String),
+    Io(String),
+}
+
+impl fmt::Display for ComputeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ComputeError::Image(path, e) => write!(f, "{path}: {e}"),
+            ComputeError::Database(name, e) => write!(f, "{name}: {e}"),
+            ComputeError::Unverified(path) => {
+                write!(f, "{path}: no trusted certificate verifies the image")
+            }
+            ComputeError::Io(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for ComputeError {}
+
+/// Authenticode hash of the image, reporting the image if it fails
+fn authenticode(pe: &PeFile) -> Result<Vec<u8>, ComputeError> {
+    pe.authenticode()
+        .map_err(|e| ComputeError::Image(pe.path().into(), e))
+}
 
 pub fn pcr4_events(
     kernels_dir: &str,
     esp_path: &str,
     uki: bool,
     secureboot: bool,
-) -> Vec<TPMEvent> {
+) -> Result<Vec<TPMEvent>, ComputeError> {
     let mut events: Vec<TPMEvent> = vec![];
     let esp = esp::Esp::new(esp_path).unwrap();
     let n_pcr = 4;
 
     // Calling EFI App
     events.push(TPMEvent {
-        name: "EV_EFI_ACTION".into(),
+        event_type: EventType::EfiAction,
         pcr: n_pcr,
-        hash: Sha256::digest(b"Calling EFI Application from Boot Option").to_vec(),
+        hash: Sha256::digest(model::EFI_CALLING_EFI_APPLICATION).to_vec(),
         id: TPMEventID::Pcr4EfiCall,
+        data: Some(model::EFI_CALLING_EFI_APPLICATION.into()),
     });
 
     // Separator
     events.push(TPMEvent {
-        name: "EV_SEPARATOR".into(),
+        event_type: EventType::Separator,
         pcr: n_pcr,
         hash: EV_SEPARATOR_HASH.to_vec(),
         id: TPMEventID::Pcr4Separator,
+        data: Some(EV_SEPARATOR_DATA.to_vec()),
     });
 
     // Binaries
     events.push(TPMEvent {
-        name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+        event_type: EventType::EfiBootServicesApplication,
         pcr: n_pcr,
-        hash: esp.shim().authenticode(),
+        hash: authenticode(&esp.shim())?,
         id: TPMEventID::Pcr4Shim,
+        data: None,
     });
 
     events.push(TPMEvent {
-        name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+        event_type: EventType::EfiBootServicesApplication,
         pcr: n_pcr,
-        hash: esp.grub().authenticode(),
+        hash: authenticode(&esp.grub())?,
         id: TPMEventID::Pcr4Grub,
+        data: None,
     });
 
     if secureboot && !uki {
         events.push(TPMEvent {
-            name: "EV_EFI_BOOT_SERVICES_APPLICATION".into(),
+            event_type: EventType::EfiBootServicesApplication,
             pcr: n_pcr,
-            hash: linux::load_vmlinuz(kernels_dir).unwrap().authenticode(),
+            hash: authenticode(&linux::load_vmlinuz(kernels_dir).unwrap())?,
             id: TPMEventID::Pcr4Vmlinuz,
+            data: None,
         });
     }
 
     // TODO: write condition for uki and implement logic
-    events
+    Ok(events)
 }
 
-pub fn pcr7_events(efivars_path: &str, esp_path: &str, secureboot_enabled: bool) -> Vec<TPMEvent> {
+/// Event logging the certificate that verifies an image shim loads. The
+/// databases are tried in order and, as shim does, only the first
+/// certificate that verifies a signature of the image is logged, even if
+/// others would verify it too. The image Authenticode hash must have been
+/// verified. Fails if nothing verifies the image, as shim would refuse to
+/// load it.
+fn authority_event(
+    path: &str,
+    image: &[u8],
+    databases: &[(uuid::Uuid, &str, &[X509Cert])],
+    ids: &[TPMEventID],
+) -> Result<TPMEvent, ComputeError> {
+    let signatures =
+        crate::authenticode::signatures(image).map_err(|e| ComputeError::Image(path.into(), e))?;
+    databases
+        .iter()
+        .zip(ids)
+        .find_map(|((guid, name, db), id)| {
+            let cert = certs::find_signer(&signatures, db)?;
+            let var = uefi::UEFIVariableData::new(*guid, name, cert.raw.clone());
+            Some(TPMEvent {
+                event_type: EventType::EfiVariableAuthority,
+                pcr: 7,
+                hash: var.hash(),
+                id: id.clone(),
+                data: Some(var.encode()),
+            })
+        })
+        .ok_or_else(|| ComputeError::Unverified(path.into()))
+}
+
+/// Computes the PCR 7 events. The authority of vmlinuz is only logged if a
+/// kernels directory is given, as shim does not verify UKIs the same way.
+pub fn pcr7_events(
+    efivars_path: &str,
+    mok_variables_path: Option<&str>,
+    esp_path: &str,
+    kernels_dir: Option<&str>,
+    secureboot_enabled: bool,
+    sbat_level: &shim::SbatLevelSelection,
+) -> Result<Vec<TPMEvent>, ComputeError> {
     let n_pcr = 7;
     let sb_var_loader =
         efivars::EFIVarsLoader::new(efivars_path, efivars::SECURE_BOOT_ATTR_HEADER_LENGTH);
@@ -109,132 +206,118 @@ pub fn pcr7_events(efivars_path: &str, esp_path: &str, secureboot_enabled: bool)
     let shim_bin = esp.shim();
     let sbatlevel_raw = shim_bin.section(shim::SHIM_SBATLEVEL_SECTION);
     let sb_db = sb_var_loader.secureboot_db();
-    let sb_db_certs = crate::certs::get_db_certs(&sb_db).unwrap();
+    let sb_db_certs =
+        certs::get_db_certs(&sb_db).map_err(|e| ComputeError::Database("db".into(), e))?;
     let mut events: Vec<TPMEvent> = vec![];
 
     // Secure boot state: enabled/disabled
+    let secureboot_state = uefi::get_secureboot_state_event(secureboot_enabled);
     events.push(TPMEvent {
-        name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+        event_type: EventType::EfiVariableDriverConfig,
         pcr: n_pcr,
-        hash: uefi::get_secureboot_state_event(secureboot_enabled).hash(),
+        hash: secureboot_state.hash(),
         id: TPMEventID::Pcr7SecureBoot,
+        data: Some(secureboot_state.encode()),
     });
 
     // Secure boot variables: PK, KEK, db, dbx
-    for (id, var) in MODELS_SB_VARIABLES.iter().zip(sb_var_loader) {
+    for (id, var) in MODELS_SB_VARIABLES.iter().zip(sb_var_loader.clone()) {
         events.push(TPMEvent {
-            name: "EV_EFI_VARIABLE_DRIVER_CONFIG".into(),
+            event_type: EventType::EfiVariableDriverConfig,
             pcr: n_pcr,
             hash: var.hash(),
             id: id.clone(),
+            data: Some(var.encode()),
         });
     }
 
     // Separator
     events.push(TPMEvent {
-        name: "EV_SEPARATOR".into(),
+        event_type: EventType::Separator,
         pcr: n_pcr,
         hash: EV_SEPARATOR_HASH.to_vec(),
         id: TPMEventID::Pcr7Separator,
+        data: Some(EV_SEPARATOR_DATA.to_vec()),
     });
 
     // Shim certs
     if secureboot_enabled {
-        match shim_bin.find_cert_in_db(&sb_db_certs) {
-            Some(cert) => events.push(TPMEvent {
-                name: "EV_EFI_VARIABLE_AUTHORITY".into(),
-                pcr: n_pcr,
-                hash: uefi::UEFIVariableData::new(uefi::GUID_SECURITY_DATABASE, "db", cert).hash(),
-                id: TPMEventID::Pcr7ShimCert,
-            }),
-            None => panic!("Can't find shim signature certificate in secure boot db"),
-        }
+        authenticode(&shim_bin)?;
+        events.push(authority_event(
+            shim_bin.path(),
+            shim_bin.raw(),
+            &[(uefi::GUID_SECURITY_DATABASE, "db", &sb_db_certs)],
+            &[TPMEventID::Pcr7ShimCert],
+        )?);
     }
 
     // Sbat level
-    if sbatlevel_raw.is_none() || !secureboot_enabled {
-        events.push(TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
-            pcr: n_pcr,
-            hash: shim::get_sbat_var_original_uefivar().hash(),
-            id: TPMEventID::Pcr7SbatLevel,
-        });
-    } else if let Some(data) = sbatlevel_raw {
-        let sbatlevel = shim::get_sbatlevel_uefivar(&data, &shim::SbatLevelPolicyType::PREVIOUS);
-        events.push(TPMEvent {
-            name: "EV_EFI_VARIABLE_AUTHORITY".into(),
-            pcr: n_pcr,
-            hash: sbatlevel.hash(),
-            id: TPMEventID::Pcr7SbatLevel,
-        });
-    }
+    let sbatlevel = match sbatlevel_raw {
+        Some(data) if secureboot_enabled => match sbat_level {
+            shim::SbatLevelSelection::Policy(policy) => shim::get_sbatlevel_uefivar(&data, policy),
+            shim::SbatLevelSelection::Efivars => uefi::UEFIVariableData::new(
+                uefi::GUID_SHIM_LOCK,
+                "SbatLevel",
+                shim::get_effective_sbatlevel(
+                    &data,
+                    &sb_var_loader.sbat_policy(),
+                    &sb_var_loader.sbat_level(),
+                ),
+            ),
+        },
+        _ => shim::get_sbat_var_original_uefivar(),
+    };
+    events.push(TPMEvent {
+        event_type: EventType::EfiVariableAuthority,
+        pcr: n_pcr,
+        hash: sbatlevel.hash(),
+        id: TPMEventID::Pcr7SbatLevel,
+        data: Some(sbatlevel.encode()),
+    });
 
-    // Certs used to verify binaries loaded by shim
+    // Certs used to verify binaries loaded by shim. shim tries db, its
+    // vendor_db and then MokListRT, which holds the enrolled MOKs and its
+    // vendor_cert, and logs each certificate once.
     if secureboot_enabled {
         let mut logged_cert_hashes = HashSet::new();
-        let shim_vendor_cert = shim_bin.vendor_cert();
-        let shim_vendor_db = shim_bin.vendor_db();
-        // TODO: In the case of UKI, the UKI and UKI addons should be processed
-        let binaries = vec![esp.grub()];
-        for bin in binaries {
-            // look for cert in secureboot
-            if let Some(sb_cert) = bin.find_cert_in_db(&sb_db_certs) {
-                let hash =
-                    uefi::UEFIVariableData::new(uefi::GUID_SECURITY_DATABASE, "db", sb_cert).hash();
-                if !logged_cert_hashes.contains(&hash) {
-                    logged_cert_hashes.insert(hash.clone());
-                    events.push(TPMEvent {
-                        name: "EV_EFI_VARIABLE_AUTHORITY".into(),
-                        pcr: n_pcr,
-                        hash,
-                        id: TPMEventID::Pcr7GrubDbCert,
-                    });
-                }
+        let mut mok_list_certs = match mok_variables_path {
+            Some(dir) => {
+                let mok_list = mok::load_mok_variable(dir, mok::MOK_LIST)
+                    .map_err(|e| ComputeError::Io(format!("{dir}: {e}")))?;
+                certs::get_db_certs(&mok_list)
+                    .map_err(|e| ComputeError::Database("MokListRT".into(), e))?
             }
-
-            // look for cert in shim vendor db
-            if let Some(vendor_db) = bin.find_cert_in_db(&shim_vendor_db) {
-                let hash = uefi::UEFIVariableData::new(
-                    uefi::GUID_SECURITY_DATABASE,
-                    "vendor_db",
-                    vendor_db,
-                )
-                .hash();
-                if !logged_cert_hashes.contains(&hash) {
-                    logged_cert_hashes.insert(hash.clone());
-                    events.push(TPMEvent {
-                        name: "EV_EFI_VARIABLE_AUTHORITY".into(),
-                        pcr: n_pcr,
-                        hash,
-                        id: TPMEventID::Pcr7GrubVendorDbCert,
-                    });
-                }
-            }
-
-            // look for cert in shim vendor cert
-            if let Some(vendor_cert) = bin.find_cert_in_db(&shim_vendor_cert) {
-                let mut vendor_cert_data = uefi::guid_to_le_bytes(&uefi::GUID_SHIM_LOCK);
-                vendor_cert_data.extend(&vendor_cert);
-                let hash = uefi::UEFIVariableData::new(
-                    uefi::GUID_SHIM_LOCK,
-                    "MokListRT",
-                    vendor_cert_data,
-                )
-                .hash();
-                if !logged_cert_hashes.contains(&hash) {
-                    logged_cert_hashes.insert(hash.clone());
-                    events.push(TPMEvent {
-                        name: "EV_EFI_VARIABLE_AUTHORITY".into(),
-                        pcr: n_pcr,
-                        hash,
-                        id: TPMEventID::Pcr7GrubMokListCert,
-                    });
-                }
+            None => vec![],
+        };
+        // vendor_cert is mirrored into MokListRT owned by the shim lock GUID
+        mok_list_certs.extend(shim_bin.vendor_cert().into_iter().map(|cert| {
+            let mut raw = uefi::guid_to_le_bytes(&uefi::GUID_SHIM_LOCK);
+            raw.extend(&cert.raw);
+            X509Cert { raw, ..cert }
+        }));
+        let vendor_db = shim_bin.vendor_db();
+        let databases = [
+            (uefi::GUID_SECURITY_DATABASE, "db", sb_db_certs.as_slice()),
+            (uefi::GUID_SECURITY_DATABASE, "vendor_db", &vendor_db),
+            (uefi::GUID_SHIM_LOCK, "MokListRT", &mok_list_certs),
+        ];
+        // TODO: In the case of UKI, the UKI and UKI addons should be processed
+        let mut binaries = vec![(esp.grub(), MODELS_GRUB_AUTHORITY)];
+        if let Some(kernels_dir) = kernels_dir {
+            let vmlinuz =
+                linux::load_vmlinuz(kernels_dir).map_err(|e| ComputeError::Io(e.to_string()))?;
+            binaries.push((vmlinuz, MODELS_KERNEL_AUTHORITY));
+        }
+        for (bin, ids) in binaries {
+            authenticode(&bin)?;
+            let event = authority_event(bin.path(), bin.raw(), &databases, &ids)?;
+            if logged_cert_hashes.insert(event.hash.clone()) {
+                events.push(event);
             }
         }
     }
 
-    events
+    Ok(events)
 }
 
 pub fn pcr11_events(uki: &str) -> Vec<TPMEvent> {
@@ -249,17 +332,20 @@ pub fn pcr11_events(uki: &str) -> Vec<TPMEvent> {
         .zip(MODELS_UKI_SECTION_CONTENT)
         .for_each(|((s, nid), cid)| {
             let section = pe.section_by_name(s).unwrap();
+            let name = format!("{s}\0");
             events.push(TPMEvent {
-                name: (*s).into(),
+                event_type: EventType::Ipl,
                 pcr: n_pcr,
-                hash: Sha256::digest(format!("{s}\0")).to_vec(),
+                hash: Sha256::digest(&name).to_vec(),
                 id: nid,
+                data: Some(name.into()),
             });
             events.push(TPMEvent {
-                name: format!("{}_CONTENT", *s),
+                event_type: EventType::Ipl,
                 pcr: n_pcr,
                 hash: Sha256::digest(section.content()).to_vec(),
                 id: cid,
+                data: Some(section.content().to_vec()),
             });
         });
 
@@ -268,13 +354,17 @@ pub fn pcr11_events(uki: &str) -> Vec<TPMEvent> {
 
 pub fn pcr14_events(mok_variables: &str) -> Vec<TPMEvent> {
     let n_pcr = 14;
-    mok::MokEventHashes::new(mok_variables)
+    mok::MokEventData::new(mok_variables)
         .zip(MODELS_MOKVARS)
-        .map(|(h, id)| TPMEvent {
-            name: "EV_IPL".into(),
+        .map(|(data, id)| TPMEvent {
+            event_type: EventType::Ipl,
             pcr: n_pcr,
-            hash: h,
+            hash: Sha256::digest(&data).to_vec(),
             id,
+            data: Some(data),
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests;
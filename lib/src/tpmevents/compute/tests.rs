@@ -0,0 +1,98 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+use super::*;
+use crate::test_support::{
+    OWNER, authenticode_signature, cert, expected_digest, image, key, signature_list, temp_dir,
+};
+use crate::uefi::sigdb::SignatureType;
+use crate::uefi::{GUID_SECURITY_DATABASE, GUID_SHIM_LOCK, UEFIVariableData};
+
+/// db, vendor_db and MokListRT, as shim tries them
+fn databases<'a>(
+    db: &'a [X509Cert],
+    mok_list: &'a [X509Cert],
+) -> [(uuid::Uuid, &'static str, &'a [X509Cert]); 3] {
+    [
+        (GUID_SECURITY_DATABASE, "db", db),
+        (GUID_SECURITY_DATABASE, "vendor_db", &[]),
+        (GUID_SHIM_LOCK, "MokListRT", mok_list),
+    ]
+}
+
+#[test]
+fn test_authority_event() {
+    let (mok_key, signer_key, db_key) = (key(), key(), key());
+    let mok = cert("MOK CA", &mok_key, None);
+    let signer = cert("MOK Signer", &signer_key, Some((&mok, &mok_key)));
+    let other = cert("db CA", &db_key, None);
+    let digest = expected_digest(&image(None));
+    let signed = image(Some(&authenticode_signature(&digest, &signer, &signer_key)));
+    let mok_der = mok.to_der().unwrap();
+    let db_certs = |certs: &[Vec<u8>]| {
+        let lists: Vec<_> = certs
+            .iter()
+            .map(|c| signature_list(SignatureType::X509, std::slice::from_ref(c)))
+            .collect();
+        certs::get_db_certs(&lists.concat()).unwrap()
+    };
+    let other_db = db_certs(&[other.to_der().unwrap()]);
+    let mok_list = db_certs(std::slice::from_ref(&mok_der));
+    let both_db = db_certs(&[other.to_der().unwrap(), mok_der.clone()]);
+    let signature_data = [OWNER.to_bytes_le().as_slice(), &mok_der].concat();
+    let grub = "EFI/fedora/grubx64.efi";
+
+    // grub signed by an enrolled MOK logs its MokListRT EFI_SIGNATURE_DATA
+    let enrolled = databases(&other_db, &mok_list);
+    let event = authority_event(grub, &signed, &enrolled, &MODELS_GRUB_AUTHORITY).unwrap();
+    let var = UEFIVariableData::new(GUID_SHIM_LOCK, "MokListRT", signature_data.clone());
+    assert_eq!(event.id, TPMEventID::Pcr7GrubMokListCert);
+    assert_eq!(event.event_type, EventType::EfiVariableAuthority);
+    assert_eq!(event.pcr, 7);
+    assert_eq!(event.hash, var.hash());
+    assert_eq!(event.data, Some(var.encode()));
+    assert_eq!(
+        authority_event(grub, &signed, &enrolled, &MODELS_KERNEL_AUTHORITY).map(|e| e.id),
+        Ok(TPMEventID::Pcr7KernelMokListCert)
+    );
+
+    // Only the first match is logged: db is tried before MokListRT
+    let both = databases(&both_db, &mok_list);
+    let event = authority_event(grub, &signed, &both, &MODELS_GRUB_AUTHORITY).unwrap();
+    let var = UEFIVariableData::new(GUID_SECURITY_DATABASE, "db", signature_data);
+    assert_eq!(event.id, TPMEventID::Pcr7GrubDbCert);
+    assert_eq!(event.data, Some(var.encode()));
+
+    // shim refuses to load a grub nothing trusted verifies
+    assert_eq!(
+        authority_event(
+            grub,
+            &signed,
+            &databases(&other_db, &[]),
+            &MODELS_GRUB_AUTHORITY
+        ),
+        Err(ComputeError::Unverified(grub.into()))
+    );
+    assert_eq!(
+        authority_event(grub, &image(None), &enrolled, &MODELS_GRUB_AUTHORITY),
+        Err(ComputeError::Unverified(grub.into()))
+    );
+}
+
+#[test]
+fn test_pcr14_events_data() {
+    let dir = temp_dir();
+    for name in ["MokListRT", "MokListXRT", "MokListTrustedRT"] {
+        std::fs::write(dir.path().join(name), name.as_bytes()).unwrap();
+    }
+    let events = pcr14_events(dir.path().to_str().unwrap());
+    assert_eq!(events.len(), 3);
+    for (event, name) in events
+        .iter()
+        .zip(["MokListRT", "MokListXRT", "MokListTrustedRT"])
+    {
+        // The MokList contents are hashed, not the "MokList\0" logged
+        assert_eq!(event.data.as_deref(), Some(name.as_bytes()));
+        assert_eq!(event.hash, Sha256::digest(name).to_vec());
+    }
+}
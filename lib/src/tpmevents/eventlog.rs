@@ -0,0 +1,260 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+
+//! TCG2 binary event logs.
+//!
+//! Writes computed events as the crypto agile log format defined by the TCG
+//! PC Client Platform Firmware Profile, the format firmware exposes in
+//! /sys/kernel/security/tpm0/binary_bios_measurements. The log starts with
+//! the Spec ID event in the SHA1 log format, followed by one TCG_PCR_EVENT2
+//! per event with its sha256 digest.
+//!
+//! Logs recorded on a platform can be read back, either as raw entries or
+//! as events matched onto the TPMEventIDs computed by this crate.
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+use crate::tpmevents::model::{EventMatcher, EventModel};
+use crate::tpmevents::{EventType, TPMEvent, TPMEventID};
+
+const TPM_ALG_SHA256: u16 = 0x000b;
+const SHA256_DIGEST_SIZE: u16 = 32;
+const SHA1_DIGEST_SIZE: usize = 20;
+const SPEC_ID_EVENT_SIGNATURE: &[u8; 16] = b"Spec ID Event03\0";
+// TCG_EfiSpecIdEvent fields for a PC Client spec 2.0 errata 0 log of a
+// platform using 64-bit UINTN
+const SPEC_VERSION_MAJOR: u8 = 2;
+const SPEC_VERSION_MINOR: u8 = 0;
+const SPEC_ERRATA: u8 = 0;
+const UINTN_SIZE_64: u8 = 2;
+
+/// TCG_EfiSpecIdEvent logged as the first event of the log, declaring the
+/// sha256 bank as the only one in the log
+fn spec_id_event() -> Vec<u8> {
+    let mut spec_id = SPEC_ID_EVENT_SIGNATURE.to_vec();
+    spec_id.extend(0u32.to_le_bytes()); // platformClass
+    spec_id.extend([
+        SPEC_VERSION_MINOR,
+        SPEC_VERSION_MAJOR,
+        SPEC_ERRATA,
+        UINTN_SIZE_64,
+    ]);
+    spec_id.extend(1u32.to_le_bytes()); // numberOfAlgorithms
+    spec_id.extend(TPM_ALG_SHA256.to_le_bytes());
+    spec_id.extend(SHA256_DIGEST_SIZE.to_le_bytes());
+    spec_id.push(0); // vendorInfoSize
+
+    let mut event = 0u32.to_le_bytes().to_vec(); // PCRIndex
+    event.extend((EventType::NoAction as u32).to_le_bytes());
+    event.extend([0; SHA1_DIGEST_SIZE]);
+    event.extend((spec_id.len() as u32).to_le_bytes());
+    event.extend(spec_id);
+    event
+}
+
+/// Writes the events as a TCG2 crypto agile binary event log, in the order
+/// given. Replaying the log yields the values compile_pcrs computes from
+/// the same events.
+///
+/// UEFI variable events are logged with the UEFI_VARIABLE_DATA that was
+/// hashed, so that verifiers can check it against the digest, and need to
+/// carry their event data.
+pub fn event_log(events: &[TPMEvent]) -> Result<Vec<u8>, EventLogError> {
+    let mut log = spec_id_event();
+
+    for event in events {
+        let model = EventModel::of(&event.id)
+            .ok_or_else(|| EventLogError::UnloggableEvent(event.id.clone()))?;
+        if model.variable.is_some() && event.data.is_none() {
+            return Err(EventLogError::MissingEventData(event.id.clone()));
+        }
+        let data = model.event_data(event);
+
+        log.extend(u32::from(event.pcr).to_le_bytes());
+        log.extend((event.event_type as u32).to_le_bytes());
+        log.extend(1u32.to_le_bytes()); // TPML_DIGEST_VALUES count
+        log.extend(TPM_ALG_SHA256.to_le_bytes());
+        log.extend(&event.hash);
+        log.extend((data.len() as u32).to_le_bytes());
+        log.extend(data);
+    }
+
+    Ok(log)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EventLogError {
+    /// The log is truncated or is not a crypto agile log
+    Parse(String),
+    /// The event at the given position in the log has no sha256 digest
+    MissingDigest(usize),
+    /// The event at the given position in the log does not match any event
+    /// expected at its position
+    UnknownEvent(usize),
+    /// The event is not logged as such by any component, e.g. the root node
+    /// of event trees
+    UnloggableEvent(TPMEventID),
+    /// The UEFI variable event lacks the event data it has to be logged with
+    MissingEventData(TPMEventID),
+}
+
+impl fmt::Display for EventLogError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EventLogError::Parse(e) => write!(f, "Error parsing event log: {e}"),
+            EventLogError::MissingDigest(num) => {
+                write!(f, "Event {num} of the log has no sha256 digest")
+            }
+            EventLogError::UnknownEvent(num) => {
+                write!(
+                    f,
+                    "Event {num} of the log does not match any expected event"
+                )
+            }
+            EventLogError::UnloggableEvent(id) => write!(f, "{id:?} is not a loggable event"),
+            EventLogError::MissingEventData(id) => {
+                write!(f, "{id:?} has no event data to log")
+            }
+        }
+    }
+}
+
+impl std::error::Error for EventLogError {}
+
+/// A TCG_PCR_EVENT2 of a recorded log, as logged
+#[derive(Clone, Debug, PartialEq)]
+pub struct EventLogEntry {
+    pub pcr: u32,
+    pub event_type: u32,
+    /// sha256 digest, if the sha256 bank was logged
+    pub digest: Option<Vec<u8>>,
+    pub data: Vec<u8>,
+}
+
+/// Little endian reader over a log
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], EventLogError> {
+        if self.buf.len() < n {
+            return Err(EventLogError::Parse("unexpected end of log".into()));
+        }
+        let (head, tail) = self.buf.split_at(n);
+        self.buf = tail;
+        Ok(head)
+    }
+
+    fn u16(&mut self) -> Result<u16, EventLogError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, EventLogError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    // Firmware may pad the log area after the last event
+    fn at_end(&self) -> bool {
+        self.buf.iter().all(|b| *b == 0) || self.buf.iter().all(|b| *b == 0xff)
+    }
+}
+
+/// Reads the digest sizes of each algorithm from the Spec ID event
+fn parse_spec_id_event(r: &mut Reader) -> Result<Vec<(u16, usize)>, EventLogError> {
+    r.u32()?; // PCRIndex
+    if r.u32()? != EventType::NoAction as u32 {
+        return Err(EventLogError::Parse("no Spec ID event".into()));
+    }
+    r.take(SHA1_DIGEST_SIZE)?;
+    let size = r.u32()? as usize;
+    let mut spec_id = Reader { buf: r.take(size)? };
+    if spec_id.take(SPEC_ID_EVENT_SIGNATURE.len())? != SPEC_ID_EVENT_SIGNATURE {
+        return Err(EventLogError::Parse("not a crypto agile log".into()));
+    }
+    spec_id.take(8)?; // platformClass, spec version and uintnSize
+    (0..spec_id.u32()?)
+        .map(|_| Ok((spec_id.u16()?, spec_id.u16()? as usize)))
+        .collect()
+}
+
+/// Parses a crypto agile binary event log into its entries, skipping the
+/// Spec ID event
+pub fn parse_event_log(log: &[u8]) -> Result<Vec<EventLogEntry>, EventLogError> {
+    let mut r = Reader { buf: log };
+    let digest_sizes = parse_spec_id_event(&mut r)?;
+
+    let mut entries = vec![];
+    while !r.at_end() {
+        let pcr = r.u32()?;
+        let event_type = r.u32()?;
+        let mut digest = None;
+        for _ in 0..r.u32()? {
+            let alg = r.u16()?;
+            let (_, size) =
+                digest_sizes
+                    .iter()
+                    .find(|(id, _)| *id == alg)
+                    .ok_or(EventLogError::Parse(format!(
+                        "unknown algorithm {alg:#06x}"
+                    )))?;
+            let value = r.take(*size)?;
+            if alg == TPM_ALG_SHA256 {
+                digest = Some(value.to_vec());
+            }
+        }
+        let size = r.u32()? as usize;
+        entries.push(EventLogEntry {
+            pcr,
+            event_type,
+            digest,
+            data: r.take(size)?.to_vec(),
+        });
+    }
+    Ok(entries)
+}
+
+/// Parses the events of a crypto agile binary event log.
+///
+/// Events are expected in the order the TPM logged them, though events of
+/// different PCRs can be interleaved. Events of PCRs that are not computed
+/// by this crate are skipped.
+pub fn from_event_log(log: &[u8]) -> Result<Vec<TPMEvent>, EventLogError> {
+    let mut matcher = EventMatcher::default();
+    let mut events = vec![];
+
+    // Event 0 is the Spec ID event
+    for (num, entry) in parse_event_log(log)?
+        .into_iter()
+        .enumerate()
+        .map(|(i, e)| (i + 1, e))
+    {
+        let Some(pcr) = u8::try_from(entry.pcr)
+            .ok()
+            .filter(|p| EventMatcher::is_computed_pcr(*p))
+        else {
+            continue;
+        };
+        if entry.event_type == EventType::NoAction as u32 {
+            continue;
+        }
+        let hash = entry.digest.ok_or(EventLogError::MissingDigest(num))?;
+        let (id, model) = matcher
+            .next_match(pcr, entry.event_type, &entry.data)
+            .ok_or(EventLogError::UnknownEvent(num))?;
+        events.push(TPMEvent {
+            event_type: model.event_type,
+            pcr,
+            // Only keep the event data when it is what was hashed
+            data: (Sha256::digest(&entry.data).as_slice() == hash.as_slice()).then_some(entry.data),
+            hash,
+            id,
+        });
+    }
+
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,199 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+use super::*;
+use crate::pcrs::compile_pcrs;
+use crate::tpmevents::{EventType, TPMEventID};
+use crate::uefi::UEFIVariableData;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+const EFI_CALLING_EFI_APPLICATION_DATA: &[u8] = b"Calling EFI Application from Boot Option";
+
+struct LoggedEvent {
+    pcr: u32,
+    event_type: u32,
+    digest: Vec<u8>,
+    data: Vec<u8>,
+}
+
+fn u32_at(log: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(log[offset..offset + 4].try_into().unwrap())
+}
+
+/// Walks a sha256-only crypto agile log, skipping the Spec ID event
+fn parse(log: &[u8]) -> Vec<LoggedEvent> {
+    let spec_id_len = u32_at(log, 28) as usize;
+    assert_eq!(&log[32..48], SPEC_ID_EVENT_SIGNATURE);
+
+    let mut offset = 32 + spec_id_len;
+    let mut events = vec![];
+    while offset < log.len() {
+        assert_eq!(u32_at(log, offset + 8), 1);
+        assert_eq!(&log[offset + 12..offset + 14], TPM_ALG_SHA256.to_le_bytes());
+        let data_len = u32_at(log, offset + 46) as usize;
+        events.push(LoggedEvent {
+            pcr: u32_at(log, offset),
+            event_type: u32_at(log, offset + 4),
+            digest: log[offset + 14..offset + 46].to_vec(),
+            data: log[offset + 50..offset + 50 + data_len].to_vec(),
+        });
+        offset += 50 + data_len;
+    }
+    events
+}
+
+/// Event hashing the data. UEFI variable events hash a UEFI_VARIABLE_DATA
+/// holding it instead, and carry it as their event data.
+fn event(pcr: u8, hash: &[u8], id: TPMEventID) -> TPMEvent {
+    let data = EventModel::of(&id)
+        .unwrap()
+        .variable
+        .map(|(vendor, name)| UEFIVariableData::new(vendor, name, hash.to_vec()).encode());
+    TPMEvent {
+        event_type: id.event_type(),
+        pcr,
+        hash: Sha256::digest(data.as_deref().unwrap_or(hash)).to_vec(),
+        id,
+        data,
+    }
+}
+
+#[test]
+fn test_spec_id_event() {
+    let log = event_log(&[]).unwrap();
+
+    assert_eq!(log.len(), 32 + 33);
+    assert_eq!(u32_at(&log, 0), 0);
+    assert_eq!(u32_at(&log, 4), EventType::NoAction as u32);
+    assert_eq!(&log[8..28], [0; 20]);
+    // Spec version 2.0 errata 0, UINTN is 64 bits and sha256 is 32 bytes
+    assert_eq!(&log[52..56], [0, 2, 0, 2]);
+    assert_eq!(&log[56..64], [1, 0, 0, 0, 0x0b, 0, 32, 0]);
+}
+
+#[test]
+fn test_event_log_replay() {
+    let events = vec![
+        event(
+            4,
+            b"Calling EFI Application from Boot Option",
+            TPMEventID::Pcr4EfiCall,
+        ),
+        event(7, b"secureboot", TPMEventID::Pcr7SecureBoot),
+        event(4, &[0; 4], TPMEventID::Pcr4Separator),
+        event(4, b"shim", TPMEventID::Pcr4Shim),
+        event(7, &[0; 4], TPMEventID::Pcr7Separator),
+        event(7, b"sbatlevel", TPMEventID::Pcr7SbatLevel),
+        event(11, b".linux\0", TPMEventID::Pcr11Linux),
+        event(11, b"vmlinuz", TPMEventID::Pcr11LinuxContent),
+        event(14, b"MokList", TPMEventID::Pcr14MokList),
+    ];
+    let mut replayed: BTreeMap<u32, Vec<u8>> = BTreeMap::new();
+    for logged in parse(&event_log(&events).unwrap()) {
+        let pcr = replayed.entry(logged.pcr).or_insert(vec![0; 32]);
+        *pcr = Sha256::digest([pcr.as_slice(), &logged.digest].concat()).to_vec();
+    }
+    let expected: BTreeMap<u32, Vec<u8>> = compile_pcrs(&events)
+        .into_iter()
+        .map(|p| (p.id as u32, p.value))
+        .collect();
+
+    assert_eq!(replayed, expected);
+}
+
+#[test]
+fn test_event_log_data() {
+    let events = vec![
+        event(4, b"efi call", TPMEventID::Pcr4EfiCall),
+        event(4, b"shim", TPMEventID::Pcr4Shim),
+        event(7, b"sbatlevel", TPMEventID::Pcr7SbatLevel),
+        event(11, b"vmlinuz", TPMEventID::Pcr11LinuxContent),
+        event(14, b"MokListX", TPMEventID::Pcr14MokListX),
+    ];
+    let logged = parse(&event_log(&events).unwrap());
+
+    assert_eq!(
+        logged.iter().map(|e| e.event_type).collect::<Vec<_>>(),
+        vec![
+            EventType::EfiAction as u32,
+            EventType::EfiBootServicesApplication as u32,
+            EventType::EfiVariableAuthority as u32,
+            EventType::Ipl as u32,
+            EventType::Ipl as u32,
+        ]
+    );
+    assert_eq!(logged[0].data, b"Calling EFI Application from Boot Option");
+    assert_eq!(logged[1].data, [0; 32]);
+    let sbatlevel = UEFIVariableData::decode(&logged[2].data).unwrap();
+    assert_eq!(sbatlevel.unicode_name(), "SbatLevel");
+    // Variable events hash what they log
+    assert_eq!(Sha256::digest(&logged[2].data).to_vec(), logged[2].digest);
+    assert_eq!(logged[3].data, b".linux\0");
+    assert_eq!(logged[4].data, b"MokListX\0");
+}
+
+#[test]
+fn test_from_event_log() {
+    let events = vec![
+        event(4, EFI_CALLING_EFI_APPLICATION_DATA, TPMEventID::Pcr4EfiCall),
+        event(7, b"secureboot", TPMEventID::Pcr7SecureBoot),
+        event(4, &[0; 4], TPMEventID::Pcr4Separator),
+        event(14, b"MokList", TPMEventID::Pcr14MokList),
+    ];
+    let mut log = event_log(&events).unwrap();
+    // Padding after the last event
+    log.extend([0xff; 16]);
+
+    let parsed = from_event_log(&log).unwrap();
+    assert_eq!(
+        parsed.iter().map(|e| e.id.clone()).collect::<Vec<_>>(),
+        events.iter().map(|e| e.id.clone()).collect::<Vec<_>>()
+    );
+    assert_eq!(parsed[2].hash, events[2].hash);
+    // Only events whose data is what was hashed keep it
+    assert_eq!(
+        parsed[0].data.as_deref(),
+        Some(EFI_CALLING_EFI_APPLICATION_DATA)
+    );
+    assert_eq!(parsed[1].data, events[1].data);
+    assert_eq!(parsed[2].data.as_deref(), Some([0; 4].as_slice()));
+}
+
+#[test]
+fn test_event_log_errors() {
+    let mut secureboot = event(7, &[1], TPMEventID::Pcr7SecureBoot);
+    secureboot.data = None;
+    assert_eq!(
+        event_log(&[secureboot]),
+        Err(EventLogError::MissingEventData(TPMEventID::Pcr7SecureBoot))
+    );
+
+    let mut root = event(4, &[0; 4], TPMEventID::Pcr4Separator);
+    root.id = TPMEventID::PcrRootNodeEvent;
+    assert_eq!(
+        event_log(&[root]),
+        Err(EventLogError::UnloggableEvent(TPMEventID::PcrRootNodeEvent))
+    );
+}
+
+#[test]
+fn test_parse_event_log_errors() {
+    let log = event_log(&[event(4, &[0; 4], TPMEventID::Pcr4Separator)]).unwrap();
+
+    assert!(matches!(
+        parse_event_log(&log[..log.len() - 2]),
+        Err(EventLogError::Parse(_))
+    ));
+    assert!(matches!(
+        parse_event_log(&log[..40]),
+        Err(EventLogError::Parse(_))
+    ));
+    // PCR 4 events cannot be EV_IPL ones
+    let mut unknown = log.clone();
+    unknown[65 + 4..65 + 8].copy_from_slice(&(EventType::Ipl as u32).to_le_bytes());
+    assert_eq!(
+        from_event_log(&unknown),
+        Err(EventLogError::UnknownEvent(1))
+    );
+}
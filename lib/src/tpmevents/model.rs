@@ -0,0 +1,163 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::tpmevents::{EventType, TPMEvent, TPMEventID};
+use crate::uefi::{GUID_GLOBAL_VARIABLE, GUID_SECURITY_DATABASE, GUID_SHIM_LOCK, UEFIVariableData};
+
+pub(crate) const EFI_CALLING_EFI_APPLICATION: &str = "Calling EFI Application from Boot Option";
+/// UEFI_IMAGE_LOAD_EVENT of PE binaries: ImageLocationInMemory,
+/// ImageLengthInMemory, ImageLinkTimeAddress and LengthOfDevicePath, all
+/// unknown, and no device path
+pub(crate) const UEFI_IMAGE_LOAD_EVENT: [u8; 32] = [0; 32];
+
+/// How the compute module logs the event of each TPMEventID
+pub(crate) struct EventModel {
+    pub pcr: u8,
+    pub event_type: EventType,
+    // Vendor GUID and name of the UEFI variable in the event data of
+    // EV_EFI_VARIABLE_* events
+    pub variable: Option<(Uuid, &'static str)>,
+    // Name of the UKI section measured by systemd-stub
+    section: Option<&'static str>,
+}
+
+impl EventModel {
+    pub fn of(id: &TPMEventID) -> Option<EventModel> {
+        let (pcr, variable, section) = match id {
+            TPMEventID::PcrRootNodeEvent | TPMEventID::PcrLastNodeEvent => return None,
+            TPMEventID::Pcr4EfiCall
+            | TPMEventID::Pcr4Separator
+            | TPMEventID::Pcr4Shim
+            | TPMEventID::Pcr4Grub
+            | TPMEventID::Pcr4Vmlinuz => (4, None, None),
+            TPMEventID::Pcr7SecureBoot => (7, Some((GUID_GLOBAL_VARIABLE, "SecureBoot")), None),
+            TPMEventID::Pcr7Pk => (7, Some((GUID_GLOBAL_VARIABLE, "PK")), None),
+            TPMEventID::Pcr7Kek => (7, Some((GUID_GLOBAL_VARIABLE, "KEK")), None),
+            TPMEventID::Pcr7Db
+            | TPMEventID::Pcr7ShimCert
+            | TPMEventID::Pcr7GrubDbCert
+            | TPMEventID::Pcr7KernelDbCert => (7, Some((GUID_SECURITY_DATABASE, "db")), None),
+            TPMEventID::Pcr7Dbx => (7, Some((GUID_SECURITY_DATABASE, "dbx")), None),
+            TPMEventID::Pcr7Separator => (7, None, None),
+            TPMEventID::Pcr7SbatLevel => (7, Some((GUID_SHIM_LOCK, "SbatLevel")), None),
+            TPMEventID::Pcr7GrubVendorDbCert | TPMEventID::Pcr7KernelVendorDbCert => {
+                (7, Some((GUID_SECURITY_DATABASE, "vendor_db")), None)
+            }
+            TPMEventID::Pcr7GrubMokListCert | TPMEventID::Pcr7KernelMokListCert => {
+                (7, Some((GUID_SHIM_LOCK, "MokListRT")), None)
+            }
+            TPMEventID::Pcr11Linux | TPMEventID::Pcr11LinuxContent => (11, None, Some(".linux")),
+            TPMEventID::Pcr11Osrel | TPMEventID::Pcr11OsrelContent => (11, None, Some(".osrel")),
+            TPMEventID::Pcr11Cmdline | TPMEventID::Pcr11CmdlineContent => {
+                (11, None, Some(".cmdline"))
+            }
+            TPMEventID::Pcr11Initrd | TPMEventID::Pcr11InitrdContent => (11, None, Some(".initrd")),
+            TPMEventID::Pcr11Uname | TPMEventID::Pcr11UnameContent => (11, None, Some(".uname")),
+            TPMEventID::Pcr11Sbat | TPMEventID::Pcr11SbatContent => (11, None, Some(".sbat")),
+            TPMEventID::Pcr14MokList
+            | TPMEventID::Pcr14MokListX
+            | TPMEventID::Pcr14MokListTrusted => (14, None, None),
+        };
+        Some(EventModel {
+            pcr,
+            event_type: id.event_type(),
+            variable,
+            section,
+        })
+    }
+
+    pub fn matches(&self, pcr: u8, event_type: EventType, event_data: &[u8]) -> bool {
+        if self.pcr != pcr || self.event_type != event_type {
+            return false;
+        }
+        // Event data is optional in exported logs, only use it when it can
+        // be decoded
+        match (self.variable, UEFIVariableData::decode(event_data)) {
+            (Some((_, variable)), Some(data)) => data.unicode_name() == variable,
+            _ => true,
+        }
+    }
+
+    /// Event data the firmware, shim or systemd-stub log along the event.
+    ///
+    /// UEFI_VARIABLE_DATA bodies only name the variable when the event does
+    /// not carry the data that was hashed, and UEFI_IMAGE_LOAD_EVENT bodies
+    /// hold no image location nor device path.
+    pub fn event_data(&self, event: &TPMEvent) -> Vec<u8> {
+        if let Some((vendor, name)) = self.variable {
+            return event
+                .data
+                .clone()
+                .unwrap_or_else(|| UEFIVariableData::new(vendor, name, vec![]).encode());
+        }
+        // systemd-stub logs the name of the UKI section for both its name and
+        // content measurements
+        if let Some(section) = self.section {
+            return format!("{section}\0").into();
+        }
+        match event.id {
+            TPMEventID::Pcr4EfiCall => EFI_CALLING_EFI_APPLICATION.into(),
+            TPMEventID::Pcr14MokList => b"MokList\0".into(),
+            TPMEventID::Pcr14MokListX => b"MokListX\0".into(),
+            TPMEventID::Pcr14MokListTrusted => b"MokListTrusted\0".into(),
+            TPMEventID::Pcr4Shim | TPMEventID::Pcr4Grub | TPMEventID::Pcr4Vmlinuz => {
+                UEFI_IMAGE_LOAD_EVENT.to_vec()
+            }
+            // Separators
+            _ => vec![0; 4],
+        }
+    }
+}
+
+/// Infers the TPMEventID of logged events.
+///
+/// Events are expected in the order the TPM logged them, though events of
+/// different PCRs can be interleaved. Each event is matched against the
+/// events that can follow the last event matched on its PCR.
+#[derive(Default)]
+pub(crate) struct EventMatcher {
+    last_matched: HashMap<u8, TPMEventID>,
+}
+
+impl EventMatcher {
+    /// Whether the PCR has events computed by this crate
+    pub fn is_computed_pcr(pcr: u8) -> bool {
+        let mut id = TPMEventID::PcrRootNodeEvent.next();
+        while let Some(current) = id {
+            if EventModel::of(&current).is_some_and(|m| m.pcr == pcr) {
+                return true;
+            }
+            id = current.next();
+        }
+        false
+    }
+
+    /// Returns the id of the next event of the PCR matching the logged
+    /// event, if any
+    pub fn next_match(
+        &mut self,
+        pcr: u8,
+        event_type: u32,
+        event_data: &[u8],
+    ) -> Option<(TPMEventID, EventModel)> {
+        let event_type = EventType::from_repr(event_type)?;
+        let mut candidate = self
+            .last_matched
+            .get(&pcr)
+            .unwrap_or(&TPMEventID::PcrRootNodeEvent)
+            .next();
+        while let Some(id) = candidate {
+            if let Some(model) = EventModel::of(&id)
+                && model.matches(pcr, event_type, event_data)
+            {
+                self.last_matched.insert(pcr, id.clone());
+                return Some((id, model));
+            }
+            candidate = id.next();
+        }
+        None
+    }
+}
@@ -30,13 +30,14 @@ fn test_tpmevevent_next_some() {
 #[test]
 fn test_part_serialization() {
     let input = TPMEvent {
-        name: "foo".into(),
+        event_type: EventType::Ipl,
         pcr: 11,
         hash: vec![1, 0, 2, 3, 255],
         id: TPMEventID::Pcr11UnameContent,
+        data: None,
     };
     let expected = String::from(
-        "{\"name\":\"foo\",\"pcr\":11,\"hash\":\"01000203ff\",\"id\":\"Pcr11UnameContent\"}",
+        "{\"name\":\".uname_CONTENT\",\"pcr\":11,\"hash\":\"01000203ff\",\"id\":\"Pcr11UnameContent\"}",
     );
 
     assert_eq!(serde_json::to_string(&input).unwrap(), expected);
@@ -47,12 +48,113 @@ fn test_tpmevent_deserialization() {
     let input =
         String::from("{\"name\":\"bar\",\"pcr\":7,\"hash\":\"0f0300\",\"id\":\"Pcr7Separator\"}");
     let expected = TPMEvent {
-        name: "bar".into(),
+        event_type: EventType::Separator,
         pcr: 7,
         hash: vec![15, 3, 0],
         id: TPMEventID::Pcr7Separator,
+        data: None,
     };
     let deserialized: TPMEvent = serde_json::from_str(&input).unwrap();
 
     assert_eq!(deserialized, expected);
 }
+
+#[test]
+fn test_event_type() {
+    assert_eq!(
+        EventType::from_repr(0x800000e0),
+        Some(EventType::EfiVariableAuthority)
+    );
+    assert_eq!(EventType::EfiAction as u32, 0x80000007);
+    assert_eq!(EventType::from_repr(0x2), None);
+    assert_eq!(
+        serde_json::to_string(&EventType::EfiVariableDriverConfig).unwrap(),
+        "\"EV_EFI_VARIABLE_DRIVER_CONFIG\""
+    );
+    assert_eq!(
+        TPMEventID::Pcr7GrubVendorDbCert.event_type(),
+        EventType::EfiVariableAuthority
+    );
+}
+
+#[test]
+fn test_tpmevent_data_serialization() {
+    let input = TPMEvent {
+        event_type: EventType::Separator,
+        pcr: 7,
+        hash: vec![15, 3, 0],
+        id: TPMEventID::Pcr7Separator,
+        data: Some(vec![0, 0, 0, 0]),
+    };
+    let serialized = serde_json::to_string(&input).unwrap();
+
+    assert!(serialized.ends_with(",\"data\":\"00000000\"}"));
+    assert_eq!(
+        serde_json::from_str::<TPMEvent>(&serialized).unwrap(),
+        input
+    );
+}
+
+#[test]
+fn test_tpmevent_eq_ignores_data() {
+    use std::collections::HashSet;
+
+    let with_data = TPMEvent {
+        event_type: EventType::Separator,
+        pcr: 7,
+        hash: vec![15, 3, 0],
+        id: TPMEventID::Pcr7Separator,
+        data: Some(vec![0, 0, 0, 0]),
+    };
+    let without_data = TPMEvent {
+        data: None,
+        ..with_data.clone()
+    };
+    assert_eq!(with_data, without_data);
+    assert_eq!(HashSet::from([with_data, without_data]).len(), 1);
+}
+
+#[test]
+fn test_tpmevent_name_serialization() {
+    let event = |event_type, pcr, id| TPMEvent {
+        event_type,
+        pcr,
+        hash: vec![0],
+        id,
+        data: None,
+    };
+    let name = |event: &TPMEvent| serde_json::to_value(event).unwrap()["name"].clone();
+
+    assert_eq!(
+        name(&event(EventType::Separator, 7, TPMEventID::Pcr7Separator)),
+        "EV_SEPARATOR"
+    );
+    assert_eq!(
+        name(&event(EventType::Ipl, 14, TPMEventID::Pcr14MokList)),
+        "EV_IPL"
+    );
+    // PCR 11 events keep being named after the UKI section they measure
+    assert_eq!(
+        name(&event(EventType::Ipl, 11, TPMEventID::Pcr11Linux)),
+        ".linux"
+    );
+    assert_eq!(
+        name(&event(EventType::Ipl, 11, TPMEventID::Pcr11LinuxContent)),
+        ".linux_CONTENT"
+    );
+
+    // Event types are read from the name, or from the id for other names
+    let read = |json: &str| serde_json::from_str::<TPMEvent>(json).unwrap().event_type;
+    assert_eq!(
+        read("{\"name\":\"EV_EFI_ACTION\",\"pcr\":7,\"hash\":\"00\",\"id\":\"Pcr7Separator\"}"),
+        EventType::EfiAction
+    );
+    assert_eq!(
+        read("{\"name\":\".osrel\",\"pcr\":11,\"hash\":\"00\",\"id\":\"Pcr11Osrel\"}"),
+        EventType::Ipl
+    );
+    assert_eq!(
+        read("{\"name\":\"FOOBAR\",\"pcr\":4,\"hash\":\"00\",\"id\":\"Pcr4Shim\"}"),
+        EventType::EfiBootServicesApplication
+    );
+}
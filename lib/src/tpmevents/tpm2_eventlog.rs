@@ -0,0 +1,189 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+
+//! Import of the YAML output of `tpm2_eventlog`.
+//!
+//! tpm2-tools prints binary event logs as YAML documents with an `events`
+//! list, where each event has its PCRIndex, EventType, Digests and a parsed
+//! Event body. As for CEL logs, the TPMEventID of each event is inferred
+//! from its PCR, its event type and, for UEFI variable events, the variable
+//! name. Events of PCRs that are not computed by this crate are skipped.
+use serde::Deserialize;
+use serde_norway::Value;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use uuid::Uuid;
+
+use crate::tpmevents::model::EventMatcher;
+use crate::tpmevents::{EventType, TPMEvent};
+use crate::uefi::UEFIVariableData;
+
+const ALGORITHM_ID_SHA256: &str = "sha256";
+/// Event types whose body tpm2_eventlog prints as text. The bodies of other
+/// events it does not parse are printed as hex.
+const TEXT_EVENT_TYPES: [EventType; 2] = [EventType::Action, EventType::EfiAction];
+
+#[derive(Deserialize)]
+struct EventLog {
+    #[serde(default)]
+    events: Vec<LoggedEvent>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct LoggedEvent {
+    event_num: u64,
+    #[serde(rename = "PCRIndex")]
+    pcr_index: u32,
+    event_type: Value,
+    #[serde(default)]
+    digests: Vec<LoggedDigest>,
+    #[serde(default)]
+    event: Option<Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct LoggedDigest {
+    algorithm_id: String,
+    digest: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Tpm2EventLogError {
+    /// The document is not valid tpm2_eventlog YAML
+    Decode(String),
+    /// The event has an event type that is not a TCG event type
+    UnknownEventType(u64),
+    /// The event has no valid sha256 digest
+    MissingDigest(u64),
+    /// The event does not match any event expected at its position
+    UnknownEvent(u64),
+    /// The event body has a field printed as hex that is not valid hex
+    InvalidEvent(u64),
+}
+
+impl fmt::Display for Tpm2EventLogError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Tpm2EventLogError::Decode(e) => write!(f, "Error decoding tpm2_eventlog YAML: {e}"),
+            Tpm2EventLogError::UnknownEventType(num) => {
+                write!(f, "Event {num} has an unknown event type")
+            }
+            Tpm2EventLogError::MissingDigest(num) => {
+                write!(f, "Event {num} has no sha256 digest")
+            }
+            Tpm2EventLogError::UnknownEvent(num) => {
+                write!(f, "Event {num} does not match any expected event")
+            }
+            Tpm2EventLogError::InvalidEvent(num) => {
+                write!(f, "Event {num} has a body that is not valid hex")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Tpm2EventLogError {}
+
+/// EventType is printed by name, or by value for types tpm2-tools does not
+/// know about
+fn event_type(value: &Value) -> Option<u32> {
+    match value {
+        Value::Number(n) => n.as_u64().and_then(|n| u32::try_from(n).ok()),
+        Value::String(s) => match s.strip_prefix("0x") {
+            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+            None => serde_norway::from_value::<EventType>(value.clone())
+                .ok()
+                .map(|t| t as u32),
+        },
+        _ => None,
+    }
+}
+
+/// Rebuilds the event data from the parsed Event body. Only the parts the
+/// event matching and hashing rely on are recovered: the variable name and
+/// data of UEFI variable events, the string of EV_IPL events, and the raw
+/// body of events tpm2-tools prints as hex or as text. Returns None if a
+/// body printed as hex is not valid hex.
+fn event_data(event: &Value, event_type: u32) -> Option<Vec<u8>> {
+    match event {
+        Value::String(s) if TEXT_EVENT_TYPES.iter().any(|t| *t as u32 == event_type) => {
+            Some(s.clone().into_bytes())
+        }
+        Value::String(s) => hex::decode(s).ok(),
+        Value::Mapping(body) => {
+            if let Some(Value::String(name)) = body.get("UnicodeName")
+                && let Some(vendor) = body
+                    .get("VariableName")
+                    .and_then(Value::as_str)
+                    .and_then(|v| Uuid::parse_str(v).ok())
+            {
+                // Signature lists and boot options are printed parsed, their
+                // raw data cannot be recovered
+                let data = match body.get("VariableData") {
+                    Some(Value::String(d)) => hex::decode(d).ok()?,
+                    _ => vec![],
+                };
+                return Some(UEFIVariableData::new(vendor, name, data).encode());
+            }
+            match body.get("String") {
+                Some(Value::String(s)) => Some(s.clone().into_bytes()),
+                _ => Some(vec![]),
+            }
+        }
+        _ => Some(vec![]),
+    }
+}
+
+/// Parses the events of a `tpm2_eventlog` YAML document.
+///
+/// Events are expected in the order the TPM logged them, though events of
+/// different PCRs can be interleaved.
+pub fn from_tpm2_eventlog(log: &str) -> Result<Vec<TPMEvent>, Tpm2EventLogError> {
+    let log: EventLog =
+        serde_norway::from_str(log).map_err(|e| Tpm2EventLogError::Decode(e.to_string()))?;
+    let mut matcher = EventMatcher::default();
+    let mut events = vec![];
+
+    for logged in log.events {
+        let Some(pcr) = u8::try_from(logged.pcr_index)
+            .ok()
+            .filter(|p| EventMatcher::is_computed_pcr(*p))
+        else {
+            continue;
+        };
+        let event_type = event_type(&logged.event_type)
+            .ok_or(Tpm2EventLogError::UnknownEventType(logged.event_num))?;
+        if event_type == EventType::NoAction as u32 {
+            continue;
+        }
+        let hash = logged
+            .digests
+            .iter()
+            .find(|d| d.algorithm_id == ALGORITHM_ID_SHA256)
+            .and_then(|d| hex::decode(&d.digest).ok())
+            .ok_or(Tpm2EventLogError::MissingDigest(logged.event_num))?;
+        let event_data = match &logged.event {
+            Some(event) => event_data(event, event_type)
+                .ok_or(Tpm2EventLogError::InvalidEvent(logged.event_num))?,
+            None => vec![],
+        };
+
+        let (id, model) = matcher
+            .next_match(pcr, event_type, &event_data)
+            .ok_or(Tpm2EventLogError::UnknownEvent(logged.event_num))?;
+        events.push(TPMEvent {
+            event_type: model.event_type,
+            pcr,
+            data: (Sha256::digest(&event_data).as_slice() == hash.as_slice()).then_some(event_data),
+            hash,
+            id,
+        });
+    }
+
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests;
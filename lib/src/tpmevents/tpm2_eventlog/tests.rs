@@ -0,0 +1,157 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+use super::*;
+use crate::tpmevents::TPMEventID;
+
+fn sha256(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// tpm2_eventlog output of a log with a few PCR 0, 4 and 7 events
+fn eventlog_yaml() -> String {
+    let secureboot =
+        UEFIVariableData::new(crate::uefi::GUID_GLOBAL_VARIABLE, "SecureBoot", vec![1]);
+    format!(
+        r#"---
+version: 1
+events:
+- EventNum: 0
+  PCRIndex: 0
+  EventType: EV_NO_ACTION
+  Digest: "0000000000000000000000000000000000000000"
+  EventSize: 37
+  SpecID:
+  - Signature: Spec ID Event03
+    numberOfAlgorithms: 1
+- EventNum: 1
+  PCRIndex: 0
+  EventType: EV_S_CRTM_VERSION
+  DigestCount: 1
+  Digests:
+  - AlgorithmId: sha256
+    Digest: "{crtm}"
+  EventSize: 2
+  Event: "0000"
+- EventNum: 2
+  PCRIndex: 7
+  EventType: EV_EFI_VARIABLE_DRIVER_CONFIG
+  DigestCount: 2
+  Digests:
+  - AlgorithmId: sha1
+    Digest: "d4fdd1f14d4041494deb8fc990c45343d2277d08"
+  - AlgorithmId: sha256
+    Digest: "{secureboot}"
+  EventSize: 53
+  Event:
+    VariableName: 8be4df61-93ca-11d2-aa0d-00e098032b8c
+    UnicodeNameLength: 10
+    VariableDataLength: 1
+    UnicodeName: SecureBoot
+    VariableData: "01"
+- EventNum: 3
+  PCRIndex: 4
+  EventType: EV_EFI_ACTION
+  DigestCount: 1
+  Digests:
+  - AlgorithmId: sha256
+    Digest: "{efi_call}"
+  EventSize: 40
+  Event: |-
+    Calling EFI Application from Boot Option
+- EventNum: 4
+  PCRIndex: 4
+  EventType: EV_SEPARATOR
+  DigestCount: 1
+  Digests:
+  - AlgorithmId: sha256
+    Digest: "{separator}"
+  EventSize: 4
+  Event: "00000000"
+- EventNum: 5
+  PCRIndex: 4
+  EventType: EV_EFI_BOOT_SERVICES_APPLICATION
+  DigestCount: 1
+  Digests:
+  - AlgorithmId: sha256
+    Digest: "{shim}"
+  EventSize: 32
+  Event:
+    ImageLocationInMemory: 0x7dd71018
+    ImageLengthInMemory: 955072
+    ImageLinkTimeAddress: 0x0
+    LengthOfDevicePath: 0
+    DevicePath: ''
+"#,
+        crtm = sha256(&[0, 0]),
+        secureboot = sha256(&secureboot.encode()),
+        efi_call = sha256(b"Calling EFI Application from Boot Option"),
+        separator = sha256(&[0; 4]),
+        shim = sha256(b"shim"),
+    )
+}
+
+#[test]
+fn test_from_tpm2_eventlog() {
+    let events = from_tpm2_eventlog(&eventlog_yaml()).unwrap();
+
+    assert_eq!(
+        events.iter().map(|e| e.id.clone()).collect::<Vec<_>>(),
+        vec![
+            TPMEventID::Pcr7SecureBoot,
+            TPMEventID::Pcr4EfiCall,
+            TPMEventID::Pcr4Separator,
+            TPMEventID::Pcr4Shim,
+        ]
+    );
+    assert_eq!(events[0].event_type, EventType::EfiVariableDriverConfig);
+    assert_eq!(events[3].hash, Sha256::digest(b"shim").to_vec());
+    // Event data is kept only when it is what was hashed
+    assert!(events[0].data.is_some());
+    assert_eq!(
+        events[1].data.as_deref(),
+        Some(b"Calling EFI Application from Boot Option".as_slice())
+    );
+    assert_eq!(events[2].data.as_deref(), Some([0; 4].as_slice()));
+    assert_eq!(events[3].data, None);
+}
+
+#[test]
+fn test_from_tpm2_eventlog_text_bodies() {
+    // An action string that happens to be valid hex is not decoded
+    let log = eventlog_yaml()
+        .replace("Calling EFI Application from Boot Option", "cafe")
+        .replace(
+            &sha256(b"Calling EFI Application from Boot Option"),
+            &sha256(b"cafe"),
+        );
+    let events = from_tpm2_eventlog(&log).unwrap();
+    assert_eq!(events[1].data.as_deref(), Some(b"cafe".as_slice()));
+}
+
+#[test]
+fn test_from_tpm2_eventlog_errors() {
+    let log = eventlog_yaml();
+
+    assert!(matches!(
+        from_tpm2_eventlog("events: 3"),
+        Err(Tpm2EventLogError::Decode(_))
+    ));
+    assert_eq!(
+        from_tpm2_eventlog(&log.replace("- AlgorithmId: sha256", "- AlgorithmId: sha384")),
+        Err(Tpm2EventLogError::MissingDigest(2))
+    );
+    assert_eq!(
+        from_tpm2_eventlog(&log.replace("EventType: EV_EFI_ACTION", "EventType: EV_BOGUS")),
+        Err(Tpm2EventLogError::UnknownEventType(3))
+    );
+    // No EV_IPL events are expected in PCR 4
+    assert_eq!(
+        from_tpm2_eventlog(&log.replace("EventType: EV_SEPARATOR", "EventType: 0xd")),
+        Err(Tpm2EventLogError::UnknownEvent(4))
+    );
+    assert_eq!(
+        from_tpm2_eventlog(&log.replace("Event: \"00000000\"", "Event: \"not hex\"")),
+        Err(Tpm2EventLogError::InvalidEvent(4))
+    );
+}
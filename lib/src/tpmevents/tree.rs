@@ -6,7 +6,7 @@ const MAX_EXPECTED_CHILDREN: usize = 2;
 #[cfg(test)]
 mod tests;
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct Node<T> {
     event: T,
     children: Vec<Node<T>>,
@@ -22,30 +22,153 @@ impl<T: Clone> Node<T> {
         }
     }
 
-    pub fn add_child(&mut self, mut child: Node<T>) {
-        child.root = false;
-        self.children.push(child);
-    }
-
     pub fn is_leaf(&self) -> bool {
         self.children.is_empty()
     }
+}
+
+impl<T> Node<T> {
+    /// Renders the statements of a Graphviz graph drawing the tree. Node
+    /// names are prefixed with prefix, and attrs gives the attribute list of
+    /// each node, e.g. `label="foo"`.
+    pub fn dot_statements(&self, prefix: &str, attrs: &impl Fn(&T) -> String) -> String {
+        let mut out = String::new();
+        self.write_dot(prefix, &mut 0, attrs, &mut out);
+        out
+    }
+
+    fn write_dot(
+        &self,
+        prefix: &str,
+        next_id: &mut usize,
+        attrs: &impl Fn(&T) -> String,
+        out: &mut String,
+    ) -> String {
+        let name = format!("{prefix}_{next_id}");
+        *next_id += 1;
+        out.push_str(&format!("{name} [{}];\n", attrs(&self.event)));
+        for child in &self.children {
+            let child_name = child.write_dot(prefix, next_id, attrs, out);
+            out.push_str(&format!("{name} -> {child_name};\n"));
+        }
+        name
+    }
+
+    /// Renders the tree as a Graphviz digraph
+    pub fn to_dot(&self, attrs: impl Fn(&T) -> String) -> String {
+        format!("digraph {{\n{}}}\n", self.dot_statements("n", &attrs))
+    }
+
+    /// Renders the tree as text, one node per line
+    pub fn to_text(&self, label: impl Fn(&T) -> String) -> String {
+        let mut out = format!("{}\n", label(&self.event));
+        self.write_text_children("", &label, &mut out);
+        out
+    }
+
+    fn write_text_children(&self, indent: &str, label: &impl Fn(&T) -> String, out: &mut String) {
+        for (i, child) in self.children.iter().enumerate() {
+            let last = i + 1 == self.children.len();
+            let (branch, next_indent) = if last {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            out.push_str(&format!("{indent}{branch}{}\n", label(&child.event)));
+            child.write_text_children(&format!("{indent}{next_indent}"), label, out);
+        }
+    }
+}
+
+impl<T: Clone + PartialEq> Node<T> {
+    /// Adds a child to the node. A child holding the same event as an
+    /// existing one is merged into it instead, so that siblings are unique
+    /// and the branches they share are only walked once.
+    pub fn add_child(&mut self, mut child: Node<T>) {
+        match self.children.iter_mut().find(|c| c.event == child.event) {
+            Some(sibling) => {
+                for grandchild in child.children {
+                    sibling.add_child(grandchild);
+                }
+            }
+            None => {
+                child.root = false;
+                self.children.push(child);
+            }
+        }
+    }
 
     pub fn branches(&self) -> Vec<Vec<T>> {
-        if self.is_leaf() {
-            return vec![vec![self.event.clone()]];
+        self.branches_iter().collect()
+    }
+
+    /// Lazily walks the tree, yielding one root to leaf branch at a time.
+    /// Only the path to the current leaf is kept in memory.
+    pub fn branches_iter(&self) -> Branches<'_, T> {
+        Branches::new(self, |_| true)
+    }
+}
+
+/// Depth-first iterator over the branches of a tree.
+/// Nodes for which `keep` returns false are pruned along with their subtree.
+pub struct Branches<'a, T> {
+    path: Vec<(&'a Node<T>, usize)>,
+    root: Option<&'a Node<T>>,
+    keep: fn(&T) -> bool,
+}
+
+impl<'a, T: Clone + PartialEq> Branches<'a, T> {
+    fn new(root: &'a Node<T>, keep: fn(&T) -> bool) -> Branches<'a, T> {
+        Branches {
+            path: Vec::new(),
+            root: Some(root),
+            keep,
         }
+    }
 
-        let mut ret = vec![];
-        for child in &self.children {
-            for child_branch in &child.branches() {
-                let mut branch = vec![self.event.clone()];
-                branch.append(&mut child_branch.clone());
-                ret.push(branch);
+    /// Returns the index of the first child of node, starting from `from`,
+    /// that has to be visited
+    fn next_child(&self, node: &Node<T>, from: usize) -> Option<usize> {
+        (from..node.children.len()).find(|&i| (self.keep)(&node.children[i].event))
+    }
+}
+
+impl<T: Clone + PartialEq> Iterator for Branches<'_, T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(root) = self.root.take() {
+            if !(self.keep)(&root.event) {
+                return None;
+            }
+            self.path.push((root, 0));
+        } else {
+            // Backtrack from the previously yielded leaf
+            self.path.pop();
+            if let Some((_, next)) = self.path.last_mut() {
+                *next += 1;
             }
         }
 
-        ret
+        loop {
+            let (node, from) = *self.path.last()?;
+            if node.is_leaf() {
+                return Some(self.path.iter().map(|(n, _)| n.event.clone()).collect());
+            }
+            match self.next_child(node, from) {
+                Some(i) => {
+                    self.path.last_mut()?.1 = i;
+                    self.path.push((&node.children[i], 0));
+                }
+                None => {
+                    // Dead end, all the children were pruned or visited
+                    self.path.pop();
+                    if let Some((_, next)) = self.path.last_mut() {
+                        *next += 1;
+                    }
+                }
+            }
+        }
     }
 }
 
@@ -63,12 +186,21 @@ where
     pub fn new_err(err: E) -> ResultNode<T, E> {
         Node::new(Err(err))
     }
+}
 
+impl<T, E> ResultNode<T, E>
+where
+    T: Clone + PartialEq,
+    E: Clone + PartialEq,
+{
     /// Returns those branches that do not contain errors
     pub fn valid_branches(&self) -> Vec<Vec<T>> {
-        self.branches()
-            .iter()
-            .filter_map(|v| v.iter().map(|e| e.clone().ok()).collect())
-            .collect()
+        self.valid_branches_iter().collect()
+    }
+
+    /// Lazy version of valid_branches. Subtrees hanging from error nodes are
+    /// never walked.
+    pub fn valid_branches_iter(&self) -> impl Iterator<Item = Vec<T>> + '_ {
+        Branches::new(self, Result::is_ok).filter_map(|v| v.into_iter().map(Result::ok).collect())
     }
 }
@@ -14,7 +14,7 @@ impl PartialEq<u32> for MockData {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 struct MockError {}
 
 static PARENT_DATA: MockData = MockData { a: 0 };
@@ -138,3 +138,99 @@ fn test_valid_branches() {
     let branches = tree.valid_branches();
     assert_eq!(branches, vec![vec![0, 10, 101], vec![0, 11, 110],]);
 }
+
+#[test]
+fn test_branches_iter_lazy() {
+    let root = build_example();
+    let mut branches = root.branches_iter();
+    assert_eq!(branches.next().unwrap(), vec![0, 10, 100, 1000]);
+    assert_eq!(branches.next().unwrap(), vec![0, 10, 101]);
+    assert_eq!(branches.next().unwrap(), vec![0, 11, 110]);
+    assert!(branches.next().is_none());
+    assert!(branches.next().is_none());
+}
+
+#[test]
+fn test_branches_iter_duplicated_siblings() {
+    let mut root = build_example();
+    let mut dup = Node::<MockData>::new(CHILD1_DATA.clone());
+    dup.add_child(Node::<MockData>::new(CHILD10_DATA.clone()));
+    root.add_child(dup);
+    // Same event, different subtree
+    let mut not_dup = Node::<MockData>::new(CHILD1_DATA.clone());
+    not_dup.add_child(Node::<MockData>::new(CHILD01_DATA.clone()));
+    root.add_child(not_dup);
+
+    // Both were merged into the existing sibling
+    assert_eq!(root.children.len(), 2);
+    assert_eq!(
+        root.branches(),
+        vec![
+            vec![0, 10, 100, 1000],
+            vec![0, 10, 101],
+            vec![0, 11, 110],
+            vec![0, 11, 101],
+        ]
+    );
+}
+
+#[test]
+fn test_valid_branches_dead_end() {
+    let mut root = ResultNode::<MockData, MockError>::new_ok(PARENT_DATA.clone());
+    let mut child0 = ResultNode::<MockData, MockError>::new_ok(CHILD0_DATA.clone());
+    let child1 = ResultNode::<MockData, MockError>::new_ok(CHILD1_DATA.clone());
+    let mut child00 = ResultNode::<MockData, MockError>::new_err(MockError {});
+    child00.add_child(ResultNode::<MockData, MockError>::new_ok(
+        CHILD000_DATA.clone(),
+    ));
+    child0.add_child(child00);
+    root.add_child(child0);
+    root.add_child(child1);
+
+    assert_eq!(root.valid_branches(), vec![vec![0, 11]]);
+}
+
+#[test]
+fn test_valid_branches_err_root() {
+    let mut root = ResultNode::<MockData, MockError>::new_err(MockError {});
+    root.add_child(ResultNode::<MockData, MockError>::new_ok(
+        CHILD0_DATA.clone(),
+    ));
+    assert_eq!(root.valid_branches_iter().count(), 0);
+}
+
+#[test]
+fn test_to_text() {
+    let root = build_example();
+    assert_eq!(
+        root.to_text(|d| d.a.to_string()),
+        "0\n\
+         ├── 10\n\
+         │   ├── 100\n\
+         │   │   └── 1000\n\
+         │   └── 101\n\
+         └── 11\n    \
+             └── 110\n"
+    );
+}
+
+#[test]
+fn test_to_dot() {
+    let mut root = Node::<MockData>::new(PARENT_DATA.clone());
+    let mut child0 = Node::<MockData>::new(CHILD0_DATA.clone());
+    child0.add_child(Node::<MockData>::new(CHILD00_DATA.clone()));
+    root.add_child(child0);
+    root.add_child(Node::<MockData>::new(CHILD1_DATA.clone()));
+    assert_eq!(
+        root.to_dot(|d| format!("label=\"{}\"", d.a)),
+        "digraph {\n\
+         n_0 [label=\"0\"];\n\
+         n_1 [label=\"10\"];\n\
+         n_2 [label=\"100\"];\n\
+         n_1 -> n_2;\n\
+         n_0 -> n_1;\n\
+         n_3 [label=\"11\"];\n\
+         n_0 -> n_3;\n\
+         }\n"
+    );
+}
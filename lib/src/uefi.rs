@@ -6,13 +6,18 @@
 use sha2::{Digest, Sha256};
 use uuid::{Uuid, uuid};
 
+pub mod authvar;
 pub mod efivars;
+pub mod recover;
+pub mod sigdb;
 
 pub const GUID_GLOBAL_VARIABLE: Uuid = uuid!("8be4df61-93ca-11d2-aa0d-00e098032b8c");
 pub const GUID_SECURITY_DATABASE: Uuid = uuid!("d719b2cb-3d3a-4596-a3bc-dad00e67656f");
 pub const GUID_SHIM_LOCK: Uuid = uuid!("605dab50-e046-4300-abb6-3dd810dd8b23");
 
 pub const EFI_CERT_TYPE_X509_GUID: Uuid = uuid!("a5c059a1-94e4-4aa7-87b5-ab155c2bf072");
+pub const EFI_CERT_SHA256_GUID: Uuid = uuid!("c1c41626-504c-4092-aca9-41f936934328");
+pub const EFI_CERT_X509_SHA256_GUID: Uuid = uuid!("3bd2a492-96c0-4079-b420-fcf98ef103ed");
 
 // Generates the little endian representation of a GUID variable
 // name.
@@ -56,11 +61,35 @@ impl UEFIVariableData {
         }
     }
 
+    // Decode a UEFI_VARIABLE_DATA buffer, as found in the event data of
+    // EV_EFI_VARIABLE_* events. Returns None if the buffer is malformed.
+    pub fn decode(buf: &[u8]) -> Option<UEFIVariableData> {
+        let guid: [u8; 16] = buf.get(0..16)?.try_into().ok()?;
+        let unicode_name_len = u64::from_le_bytes(buf.get(16..24)?.try_into().ok()?);
+        let data_len = u64::from_le_bytes(buf.get(24..32)?.try_into().ok()?);
+        let name_end =
+            32usize.checked_add(usize::try_from(unicode_name_len).ok()?.checked_mul(2)?)?;
+        let data_end = name_end.checked_add(usize::try_from(data_len).ok()?)?;
+        let unicode_name = buf
+            .get(32..name_end)?
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect();
+
+        Some(UEFIVariableData {
+            variable_name: Uuid::from_bytes_le(guid),
+            unicode_name_len,
+            data_len,
+            unicode_name,
+            variable_data: buf.get(name_end..data_end)?.to_vec(),
+        })
+    }
+
     // Encode the UEFIVariableData struct into a u8 vec/buffer that can be
     // hashed.
     // This method returns the content that is hashed to obtain the event hash
     // that extends the TPM
-    fn encode(&self) -> Vec<u8> {
+    pub(crate) fn encode(&self) -> Vec<u8> {
         // Make a u8 buffer from the char16 representation of the unicode name
         let unicode_name_u8: Vec<u8> = self
             .unicode_name
@@ -84,9 +113,17 @@ impl UEFIVariableData {
         Sha256::digest(self.encode()).to_vec()
     }
 
+    pub fn variable_name(&self) -> Uuid {
+        self.variable_name
+    }
+
     pub fn data(&self) -> &[u8] {
         &self.variable_data
     }
+
+    pub fn unicode_name(&self) -> String {
+        String::from_utf16_lossy(&self.unicode_name)
+    }
 }
 
 #[cfg(test)]
@@ -106,4 +143,16 @@ mod tests {
             hex!("ccfc4bb32888a345bc8aeadaba552b627d99348c767681ab3141f5b01e40a40e").to_vec()
         )
     }
+
+    #[test]
+    fn decode() {
+        let uefivar = UEFIVariableData::new(GUID_SHIM_LOCK, "SbatLevel", b"sbat,1\n".to_vec());
+        let decoded = UEFIVariableData::decode(&uefivar.encode()).unwrap();
+
+        assert_eq!(decoded.variable_name, GUID_SHIM_LOCK);
+        assert_eq!(decoded.unicode_name(), "SbatLevel");
+        assert_eq!(decoded.data(), b"sbat,1\n");
+        assert_eq!(decoded.hash(), uefivar.hash());
+        assert!(UEFIVariableData::decode(&uefivar.encode()[..40]).is_none());
+    }
 }
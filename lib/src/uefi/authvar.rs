@@ -0,0 +1,231 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+
+//! Authenticated updates of the Secure Boot variables, as distributed in
+//! `.auth` files: an EFI_VARIABLE_AUTHENTICATION_2 header followed by the new
+//! variable data.
+//!
+//! The header holds a timestamp and a PKCS#7 SignedData over the variable
+//! name, vendor GUID, attributes, timestamp and new data. As firmware does,
+//! PK and KEK updates must be signed by a PK certificate, and db and dbx
+//! updates by a KEK or PK certificate. Updates are not verified while no PK
+//! is enrolled (setup mode). Timestamps are not checked against the ones of
+//! the current variables, as efivarfs does not expose them.
+use std::fmt;
+use uuid::Uuid;
+
+use super::UEFIVariableData;
+use super::efivars::{SECURE_BOOT_VAR_ATTRIBUTES, SECURE_BOOT_VARIABLES, SecureBootVariables};
+use super::sigdb::{SignatureDatabase, SignatureDatabaseError, SignatureType};
+use crate::authenticode;
+use crate::certs;
+use crate::der::{self, Der, TAG_CONTEXT_0, TAG_OID, TAG_SEQUENCE};
+
+const EFI_TIME_SIZE: usize = 16;
+// dwLength, wRevision, wCertificateType and CertType
+const WIN_CERTIFICATE_UEFI_GUID_HEADER_SIZE: usize = 24;
+const WIN_CERT_REVISION: u16 = 0x0200;
+const WIN_CERT_TYPE_EFI_GUID: u16 = 0x0ef1;
+const EFI_VARIABLE_APPEND_WRITE: u32 = 0x40;
+// DER encoded OID of the PKCS#7 signedData content type
+const OID_PKCS7_SIGNED_DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x02];
+const PK: &str = "PK";
+const KEK: &str = "KEK";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AuthVariableError {
+    /// The .auth payload is malformed
+    Parse(String),
+    /// The variable is not a Secure Boot variable
+    UnknownVariable(String),
+    /// The current or the new variable data is not a signature database
+    Database(SignatureDatabaseError),
+    /// No certificate allowed to update the variable verifies the update
+    Unauthorized(String),
+}
+
+impl fmt::Display for AuthVariableError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AuthVariableError::Parse(e) => write!(f, "Error parsing authenticated variable: {e}"),
+            AuthVariableError::UnknownVariable(name) => {
+                write!(f, "{name} is not a Secure Boot variable")
+            }
+            AuthVariableError::Database(e) => write!(f, "{e}"),
+            AuthVariableError::Unauthorized(name) => {
+                write!(
+                    f,
+                    "The {name} update is not signed by an allowed certificate"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for AuthVariableError {}
+
+impl From<SignatureDatabaseError> for AuthVariableError {
+    fn from(e: SignatureDatabaseError) -> Self {
+        AuthVariableError::Database(e)
+    }
+}
+
+/// How the update data is written into the variable
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UpdateMode {
+    /// EFI_VARIABLE_APPEND_WRITE: the signatures are added to the database
+    Append,
+    /// The data replaces the database
+    Replace,
+}
+
+impl UpdateMode {
+    fn attributes(&self) -> u32 {
+        match self {
+            UpdateMode::Append => SECURE_BOOT_VAR_ATTRIBUTES | EFI_VARIABLE_APPEND_WRITE,
+            UpdateMode::Replace => SECURE_BOOT_VAR_ATTRIBUTES,
+        }
+    }
+}
+
+/// An EFI_VARIABLE_AUTHENTICATION_2 payload
+#[derive(Clone, Debug)]
+pub struct AuthenticatedUpdate {
+    pub timestamp: [u8; EFI_TIME_SIZE],
+    /// PKCS#7 ContentInfo holding the SignedData
+    pub signature: Vec<u8>,
+    pub data: Vec<u8>,
+}
+
+/// Wraps a bare SignedData into a ContentInfo. Tools such as sign-efi-sig-list
+/// and the Microsoft updates only carry the SignedData, which EDK2 accepts.
+fn wrap_signed_data(cert_data: &[u8]) -> Vec<u8> {
+    let is_content_info = Der::new(cert_data)
+        .next_if(TAG_SEQUENCE)
+        .and_then(|seq| seq.children().next_if(TAG_OID))
+        .is_some_and(|oid| oid.content == OID_PKCS7_SIGNED_DATA);
+    if is_content_info {
+        return cert_data.to_vec();
+    }
+    der::encode(
+        TAG_SEQUENCE,
+        &[
+            der::encode(TAG_OID, OID_PKCS7_SIGNED_DATA),
+            der::encode(TAG_CONTEXT_0, cert_data),
+        ]
+        .concat(),
+    )
+}
+
+impl AuthenticatedUpdate {
+    pub fn parse(auth: &[u8]) -> Result<AuthenticatedUpdate, AuthVariableError> {
+        let err = |msg: &str| AuthVariableError::Parse(msg.into());
+        let u16_at = |at: usize| {
+            auth.get(at..at + 2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        };
+        let timestamp = auth
+            .get(..EFI_TIME_SIZE)
+            .ok_or_else(|| err("truncated timestamp"))?;
+        let length = auth
+            .get(EFI_TIME_SIZE..EFI_TIME_SIZE + 4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()) as usize)
+            .ok_or_else(|| err("truncated WIN_CERTIFICATE"))?;
+        if u16_at(EFI_TIME_SIZE + 4) != Some(WIN_CERT_REVISION)
+            || u16_at(EFI_TIME_SIZE + 6) != Some(WIN_CERT_TYPE_EFI_GUID)
+        {
+            return Err(err("not a WIN_CERTIFICATE_UEFI_GUID"));
+        }
+        let cert_type = auth
+            .get(EFI_TIME_SIZE + 8..EFI_TIME_SIZE + WIN_CERTIFICATE_UEFI_GUID_HEADER_SIZE)
+            .map(|b| Uuid::from_bytes_le(b.try_into().unwrap()));
+        if cert_type != Some(SignatureType::Pkcs7.guid()) {
+            return Err(err("not a PKCS#7 certificate"));
+        }
+        if length < WIN_CERTIFICATE_UEFI_GUID_HEADER_SIZE || EFI_TIME_SIZE + length > auth.len() {
+            return Err(err("invalid WIN_CERTIFICATE length"));
+        }
+
+        Ok(AuthenticatedUpdate {
+            timestamp: timestamp.try_into().unwrap(),
+            signature: wrap_signed_data(
+                &auth
+                    [EFI_TIME_SIZE + WIN_CERTIFICATE_UEFI_GUID_HEADER_SIZE..EFI_TIME_SIZE + length],
+            ),
+            data: auth[EFI_TIME_SIZE + length..].to_vec(),
+        })
+    }
+
+    /// The data the update signature is made over
+    pub fn signed_content(&self, name: &str, vendor: &Uuid, mode: UpdateMode) -> Vec<u8> {
+        let name: Vec<u8> = name.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        [
+            name.as_slice(),
+            &vendor.to_bytes_le(),
+            &mode.attributes().to_le_bytes(),
+            &self.timestamp,
+            &self.data,
+        ]
+        .concat()
+    }
+}
+
+impl SecureBootVariables {
+    /// Certificates of the variable, in the order they are tried
+    fn certificates(&self, name: &str) -> Result<Vec<certs::X509Cert>, AuthVariableError> {
+        certs::get_db_certs(self.data(name)).map_err(|e| AuthVariableError::Parse(e.to_string()))
+    }
+
+    /// Verifies and applies an update of the variable
+    pub fn apply(
+        &mut self,
+        name: &str,
+        update: &AuthenticatedUpdate,
+        mode: UpdateMode,
+    ) -> Result<(), AuthVariableError> {
+        let (index, (name, vendor)) = SECURE_BOOT_VARIABLES
+            .iter()
+            .enumerate()
+            .find(|(_, (n, _))| *n == name)
+            .ok_or_else(|| AuthVariableError::UnknownVariable(name.into()))?;
+
+        if !self.data(PK).is_empty() {
+            let mut authorities = vec![];
+            if *name != PK && *name != KEK {
+                authorities.extend(self.certificates(KEK)?);
+            }
+            authorities.extend(self.certificates(PK)?);
+            let content = update.signed_content(name, vendor, mode);
+            let mut verified = false;
+            for cert in &authorities {
+                if authenticode::verify_detached(&update.signature, &content, &cert.cert)
+                    .map_err(|e| AuthVariableError::Parse(e.to_string()))?
+                {
+                    verified = true;
+                    break;
+                }
+            }
+            if !verified {
+                return Err(AuthVariableError::Unauthorized(name.to_string()));
+            }
+        }
+
+        let data = match mode {
+            UpdateMode::Append => {
+                let mut db = SignatureDatabase::decode(self.variables[index].data())?;
+                db.append(SignatureDatabase::decode(&update.data)?);
+                db.encode()?
+            }
+            UpdateMode::Replace => {
+                SignatureDatabase::decode(&update.data)?;
+                update.data.clone()
+            }
+        };
+        self.variables[index] = UEFIVariableData::new(*vendor, name, data);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests;
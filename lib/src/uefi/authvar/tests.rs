@@ -0,0 +1,211 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+use super::*;
+use crate::test_support::{cert, key, signature_list, temp_dir};
+use crate::uefi::{GUID_GLOBAL_VARIABLE, GUID_SECURITY_DATABASE};
+use openssl::pkcs7::{Pkcs7, Pkcs7Flags};
+use openssl::pkey::{PKey, Private};
+use openssl::stack::Stack;
+use openssl::x509::X509;
+
+const TIMESTAMP: [u8; 16] = [0xea, 0x07, 10, 18, 12, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+fn x509_database(cert: &X509) -> Vec<u8> {
+    signature_list(SignatureType::X509, &[cert.to_der().unwrap()])
+}
+
+/// .auth payload updating the variable with the data, signed with the key
+/// and carrying a bare SignedData, as sign-efi-sig-list does
+fn auth(name: &str, data: &[u8], mode: UpdateMode, signer: &X509, key: &PKey<Private>) -> Vec<u8> {
+    let vendor = SECURE_BOOT_VARIABLES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .unwrap()
+        .1;
+    let unsigned = AuthenticatedUpdate {
+        timestamp: TIMESTAMP,
+        signature: vec![],
+        data: data.to_vec(),
+    };
+    let content = unsigned.signed_content(name, &vendor, mode);
+    let certs = Stack::new().unwrap();
+    let content_info = Pkcs7::sign(
+        signer,
+        key,
+        &certs,
+        &content,
+        Pkcs7Flags::BINARY | Pkcs7Flags::DETACHED,
+    )
+    .unwrap()
+    .to_der()
+    .unwrap();
+    let mut content_info = Der::new(&content_info).next().unwrap().children();
+    content_info.next();
+    let signed_data = content_info.next().unwrap().children().next().unwrap().raw;
+
+    let length = (WIN_CERTIFICATE_UEFI_GUID_HEADER_SIZE + signed_data.len()) as u32;
+    [
+        TIMESTAMP.as_slice(),
+        &length.to_le_bytes(),
+        &WIN_CERT_REVISION.to_le_bytes(),
+        &WIN_CERT_TYPE_EFI_GUID.to_le_bytes(),
+        &SignatureType::Pkcs7.guid().to_bytes_le(),
+        signed_data,
+        data,
+    ]
+    .concat()
+}
+
+struct Keys {
+    pk: (X509, PKey<Private>),
+    kek: (X509, PKey<Private>),
+    other: (X509, PKey<Private>),
+}
+
+fn keys() -> Keys {
+    let pair = |cn: &str| {
+        let key = key();
+        (cert(cn, &key, None), key)
+    };
+    Keys {
+        pk: pair("Platform Key"),
+        kek: pair("Key Exchange Key"),
+        other: pair("Platform Key"),
+    }
+}
+
+fn variables(keys: &Keys, db: Vec<u8>) -> SecureBootVariables {
+    SecureBootVariables {
+        variables: vec![
+            UEFIVariableData::new(GUID_GLOBAL_VARIABLE, "PK", x509_database(&keys.pk.0)),
+            UEFIVariableData::new(GUID_GLOBAL_VARIABLE, "KEK", x509_database(&keys.kek.0)),
+            UEFIVariableData::new(GUID_SECURITY_DATABASE, "db", db),
+            UEFIVariableData::new(GUID_SECURITY_DATABASE, "dbx", vec![]),
+        ],
+    }
+}
+
+#[test]
+fn test_parse_authenticated_update() {
+    let keys = keys();
+    let data = signature_list(SignatureType::Sha256, &[vec![1; 32]]);
+    let payload = auth("db", &data, UpdateMode::Append, &keys.kek.0, &keys.kek.1);
+
+    let update = AuthenticatedUpdate::parse(&payload).unwrap();
+    assert_eq!(update.timestamp, TIMESTAMP);
+    assert_eq!(update.data, data);
+    // The bare SignedData is wrapped into a ContentInfo, and is left as is
+    // when it already is one
+    assert!(Pkcs7::from_der(&update.signature).is_ok());
+    assert_eq!(wrap_signed_data(&update.signature), update.signature);
+
+    assert!(matches!(
+        AuthenticatedUpdate::parse(&payload[..20]),
+        Err(AuthVariableError::Parse(_))
+    ));
+    let mut bad_type = payload.clone();
+    bad_type[EFI_TIME_SIZE + 6] = 0x02;
+    assert!(matches!(
+        AuthenticatedUpdate::parse(&bad_type),
+        Err(AuthVariableError::Parse(_))
+    ));
+    let mut bad_length = payload.clone();
+    bad_length[EFI_TIME_SIZE..EFI_TIME_SIZE + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(matches!(
+        AuthenticatedUpdate::parse(&bad_length),
+        Err(AuthVariableError::Parse(_))
+    ));
+}
+
+#[test]
+fn test_apply_append() {
+    let keys = keys();
+    let db = signature_list(SignatureType::Sha256, &[vec![1; 32]]);
+    let mut vars = variables(&keys, db.clone());
+    let data = signature_list(SignatureType::Sha256, &[vec![1; 32], vec![2; 32]]);
+
+    let update = |signer: &(X509, PKey<Private>)| {
+        AuthenticatedUpdate::parse(&auth("db", &data, UpdateMode::Append, &signer.0, &signer.1))
+            .unwrap()
+    };
+    assert_eq!(
+        vars.apply("db", &update(&keys.other), UpdateMode::Append),
+        Err(AuthVariableError::Unauthorized("db".into()))
+    );
+    // Signed for an append, applied as a replacement
+    assert_eq!(
+        vars.apply("db", &update(&keys.kek), UpdateMode::Replace),
+        Err(AuthVariableError::Unauthorized("db".into()))
+    );
+    assert_eq!(vars.data("db"), db);
+
+    vars.apply("db", &update(&keys.kek), UpdateMode::Append)
+        .unwrap();
+    let expected = [db, signature_list(SignatureType::Sha256, &[vec![2; 32]])].concat();
+    assert_eq!(vars.data("db"), expected);
+    // Signatures already in the database are not appended again
+    vars.apply("db", &update(&keys.pk), UpdateMode::Append)
+        .unwrap();
+    assert_eq!(vars.data("db"), expected);
+}
+
+#[test]
+fn test_apply_replace() {
+    let keys = keys();
+    let mut vars = variables(&keys, vec![]);
+    let new_kek = x509_database(&keys.other.0);
+    let update = |name: &str, signer: &(X509, PKey<Private>)| {
+        AuthenticatedUpdate::parse(&auth(
+            name,
+            &new_kek,
+            UpdateMode::Replace,
+            &signer.0,
+            &signer.1,
+        ))
+        .unwrap()
+    };
+
+    // KEK can only be updated by PK
+    assert_eq!(
+        vars.apply("KEK", &update("KEK", &keys.kek), UpdateMode::Replace),
+        Err(AuthVariableError::Unauthorized("KEK".into()))
+    );
+    // The variable name is signed
+    assert_eq!(
+        vars.apply("KEK", &update("db", &keys.pk), UpdateMode::Replace),
+        Err(AuthVariableError::Unauthorized("KEK".into()))
+    );
+    vars.apply("KEK", &update("KEK", &keys.pk), UpdateMode::Replace)
+        .unwrap();
+    assert_eq!(vars.data("KEK"), new_kek);
+
+    assert_eq!(
+        vars.apply("MokList", &update("KEK", &keys.pk), UpdateMode::Replace),
+        Err(AuthVariableError::UnknownVariable("MokList".into()))
+    );
+}
+
+#[test]
+fn test_setup_mode() {
+    let keys = keys();
+    let mut vars = variables(&keys, vec![]);
+    vars.variables[0] = UEFIVariableData::new(GUID_GLOBAL_VARIABLE, "PK", vec![]);
+    let pk = x509_database(&keys.pk.0);
+    let update = auth("PK", &pk, UpdateMode::Replace, &keys.other.0, &keys.other.1);
+
+    vars.apply(
+        "PK",
+        &AuthenticatedUpdate::parse(&update).unwrap(),
+        UpdateMode::Replace,
+    )
+    .unwrap();
+    assert_eq!(vars.data("PK"), pk);
+
+    let dir = temp_dir();
+    vars.write(dir.path()).unwrap();
+    let loaded = SecureBootVariables::load(dir.path().to_str().unwrap());
+    for (loaded, var) in loaded.variables().iter().zip(vars.variables()) {
+        assert_eq!(loaded.hash(), var.hash());
+    }
+}
@@ -3,8 +3,12 @@
 //
 // SPDX-License-Identifier: MIT
 
-use super::{GUID_GLOBAL_VARIABLE, GUID_SECURITY_DATABASE, UEFIVariableData};
+use super::sigdb::{
+    SignatureData, SignatureDatabase, SignatureDatabaseError, SignatureList, SignatureType,
+};
+use super::{GUID_GLOBAL_VARIABLE, GUID_SECURITY_DATABASE, GUID_SHIM_LOCK, UEFIVariableData};
 use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
@@ -12,11 +16,19 @@ const EFI_VAR_ID_PK: (&str, Uuid) = ("PK", GUID_GLOBAL_VARIABLE);
 const EFI_VAR_ID_KEK: (&str, Uuid) = ("KEK", GUID_GLOBAL_VARIABLE);
 const EFI_VAR_ID_DB: (&str, Uuid) = ("db", GUID_SECURITY_DATABASE);
 const EFI_VAR_ID_DBX: (&str, Uuid) = ("dbx", GUID_SECURITY_DATABASE);
+// Runtime copy of SbatLevel, which shim makes readable from the OS
+const EFI_VAR_ID_SBAT_LEVEL_RT: (&str, Uuid) = ("SbatLevelRT", GUID_SHIM_LOCK);
+// SbatLevel policy shim applies on next boot
+const EFI_VAR_ID_SBAT_POLICY: (&str, Uuid) = ("SbatPolicy", GUID_SHIM_LOCK);
 
-const SECURE_BOOT_VARIABLES: [(&str, Uuid); 4] =
+pub(crate) const SECURE_BOOT_VARIABLES: [(&str, Uuid); 4] =
     [EFI_VAR_ID_PK, EFI_VAR_ID_KEK, EFI_VAR_ID_DB, EFI_VAR_ID_DBX];
 
 pub const SECURE_BOOT_ATTR_HEADER_LENGTH: usize = 4;
+// NON_VOLATILE | BOOTSERVICE_ACCESS | RUNTIME_ACCESS |
+// TIME_BASED_AUTHENTICATED_WRITE_ACCESS
+pub(crate) const SECURE_BOOT_VAR_ATTRIBUTES: u32 = 0x27;
+const SHA256_SIZE: usize = 32;
 
 #[derive(Debug, Clone)]
 pub struct EFIVarsLoader {
@@ -43,6 +55,21 @@ impl EFIVarsLoader {
         let (var, guid) = EFI_VAR_ID_DB;
         load_uefi_var_data(&self.path, var, &guid, self.attribute_header)
     }
+
+    pub fn secureboot_dbx(&self) -> Vec<u8> {
+        let (var, guid) = EFI_VAR_ID_DBX;
+        load_uefi_var_data(&self.path, var, &guid, self.attribute_header)
+    }
+
+    pub fn sbat_level(&self) -> Vec<u8> {
+        let (var, guid) = EFI_VAR_ID_SBAT_LEVEL_RT;
+        load_uefi_var_data(&self.path, var, &guid, self.attribute_header)
+    }
+
+    pub fn sbat_policy(&self) -> Vec<u8> {
+        let (var, guid) = EFI_VAR_ID_SBAT_POLICY;
+        load_uefi_var_data(&self.path, var, &guid, self.attribute_header)
+    }
 }
 
 impl Iterator for EFIVarsLoader {
@@ -55,6 +82,105 @@ impl Iterator for EFIVarsLoader {
     }
 }
 
+/// PK, KEK, db and dbx, as a whole Secure Boot key set
+#[derive(Debug)]
+pub struct SecureBootVariables {
+    /// In the order of SECURE_BOOT_VARIABLES
+    pub(super) variables: Vec<UEFIVariableData>,
+}
+
+impl SecureBootVariables {
+    /// Loads the variables from a directory of efivarfs files
+    pub fn load(efivars_path: &str) -> SecureBootVariables {
+        SecureBootVariables {
+            variables: EFIVarsLoader::new(efivars_path, SECURE_BOOT_ATTR_HEADER_LENGTH).collect(),
+        }
+    }
+
+    /// Builds the variables of a key set, as cert-to-efi-sig-list and
+    /// hash-to-efi-sig-list would: PK holds the single platform key, KEK and
+    /// db an EFI_CERT_X509 list per DER encoded certificate, and dbx an
+    /// EFI_CERT_SHA256 list of the forbidden image hashes. Every signature is
+    /// owned by the given GUID.
+    pub fn from_keys(
+        owner: Uuid,
+        pk: &[u8],
+        kek: &[Vec<u8>],
+        db: &[Vec<u8>],
+        dbx_hashes: &[Vec<u8>],
+    ) -> Result<SecureBootVariables, SignatureDatabaseError> {
+        let certificates = |certs: &[Vec<u8>]| SignatureDatabase {
+            lists: certs
+                .iter()
+                .map(|cert| {
+                    let mut list = SignatureList::new(SignatureType::X509, cert.len());
+                    list.signatures
+                        .push(SignatureData::new(owner, cert.clone()));
+                    list
+                })
+                .collect(),
+        };
+        let mut dbx = SignatureDatabase::default();
+        if !dbx_hashes.is_empty() {
+            let mut list = SignatureList::new(SignatureType::Sha256, SHA256_SIZE);
+            list.signatures = dbx_hashes
+                .iter()
+                .map(|hash| SignatureData::new(owner, hash.clone()))
+                .collect();
+            dbx.lists.push(list);
+        }
+
+        let data = [
+            certificates(&[pk.to_vec()]),
+            certificates(kek),
+            certificates(db),
+            dbx,
+        ];
+        Ok(SecureBootVariables {
+            variables: SECURE_BOOT_VARIABLES
+                .iter()
+                .zip(data)
+                .map(|((name, guid), db)| Ok(UEFIVariableData::new(*guid, name, db.encode()?)))
+                .collect::<Result<_, SignatureDatabaseError>>()?,
+        })
+    }
+
+    pub fn variables(&self) -> &[UEFIVariableData] {
+        &self.variables
+    }
+
+    pub(super) fn data(&self, name: &str) -> &[u8] {
+        self.variables
+            .iter()
+            .find(|v| v.unicode_name() == name)
+            .map_or(&[], |v| v.data())
+    }
+
+    /// Writes the variables as efivarfs files, with the attribute header
+    /// EFIVarsLoader expects. Empty variables do not exist, so their files
+    /// are removed instead.
+    pub fn write(&self, efivars_dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(efivars_dir)?;
+        for var in &self.variables {
+            let file_name = format!("{}-{}", var.unicode_name(), var.variable_name());
+            if !var.data().is_empty() {
+                write_efivar(
+                    efivars_dir,
+                    &file_name,
+                    SECURE_BOOT_VAR_ATTRIBUTES,
+                    var.data(),
+                )?;
+                continue;
+            }
+            match fs::remove_file(efivars_dir.join(file_name)) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
 pub fn get_secure_boot_targets() -> Vec<(String, Uuid)> {
     SECURE_BOOT_VARIABLES
         .map(|(var, guid)| (var.into(), guid))
@@ -82,3 +208,36 @@ fn load_uefi_var_data(path: &Path, var: &str, guid: &Uuid, attribute_header: usi
     }
     data
 }
+
+/// Writes an efivarfs file: the attributes of the variable followed by its
+/// data
+pub(crate) fn write_efivar(
+    dir: &Path,
+    file_name: &str,
+    attributes: u32,
+    data: &[u8],
+) -> io::Result<()> {
+    let header = attributes.to_le_bytes();
+    debug_assert_eq!(header.len(), SECURE_BOOT_ATTR_HEADER_LENGTH);
+    fs::write(dir.join(file_name), [header.as_slice(), data].concat())
+}
+
+/// Copies the efivarfs files of a directory into another, so variables
+/// other than the Secure Boot ones (SbatLevelRT, MokListRT...) carry over
+/// when SecureBootVariables::write targets a new directory.
+pub fn copy_efivars(from: &Path, to: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(to)?;
+    if fs::canonicalize(from)? == fs::canonicalize(to)? {
+        return Ok(());
+    }
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            fs::copy(entry.path(), to.join(entry.file_name()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests;
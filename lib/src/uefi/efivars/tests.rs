@@ -0,0 +1,96 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+use super::*;
+use crate::certs;
+use crate::test_support::{OWNER, cert, key, temp_dir};
+use crate::uefi::GUID_SHIM_LOCK;
+
+#[test]
+fn test_secure_boot_variables_from_keys() {
+    let pk = cert("Platform Key", &key(), None);
+    let kek = cert("Key Exchange Key", &key(), None);
+    let db = [cert("db 1", &key(), None), cert("db 2", &key(), None)];
+    let pem = [db[0].to_pem().unwrap(), db[1].to_pem().unwrap()].concat();
+    let db = certs::read_certificates(&pem).unwrap();
+    assert_eq!(db.len(), 2);
+
+    let vars = SecureBootVariables::from_keys(
+        OWNER,
+        &pk.to_der().unwrap(),
+        &[kek.to_der().unwrap()],
+        &db,
+        &[vec![1; 32], vec![2; 32]],
+    )
+    .unwrap();
+
+    let dir = temp_dir();
+    vars.write(dir.path()).unwrap();
+    let loaded: Vec<_> =
+        EFIVarsLoader::new(dir.path().to_str().unwrap(), SECURE_BOOT_ATTR_HEADER_LENGTH).collect();
+    assert_eq!(loaded.len(), 4);
+    for (loaded, var) in loaded.iter().zip(vars.variables()) {
+        assert_eq!(loaded.hash(), var.hash());
+    }
+
+    let db_certs = certs::get_db_certs(vars.data("db")).unwrap();
+    assert_eq!(
+        db_certs
+            .iter()
+            .map(|c| c.subject.as_str())
+            .collect::<Vec<_>>(),
+        ["CN=db 1", "CN=db 2"]
+    );
+    assert_eq!(
+        db_certs[0].raw,
+        [OWNER.to_bytes_le().as_slice(), &db[0]].concat()
+    );
+    let dbx = SignatureDatabase::decode(vars.data("dbx")).unwrap();
+    assert_eq!(dbx.lists.len(), 1);
+    assert_eq!(dbx.lists[0].signature_type, SignatureType::Sha256);
+    assert_eq!(dbx.lists[0].signatures.len(), 2);
+}
+
+#[test]
+fn test_secure_boot_variables_from_keys_errors() {
+    let pk = cert("Platform Key", &key(), None).to_der().unwrap();
+    assert!(SecureBootVariables::from_keys(OWNER, &pk, &[], &[], &[]).is_ok());
+    assert_eq!(
+        SecureBootVariables::from_keys(OWNER, &pk, &[], &[], &[vec![1; 20]]).err(),
+        Some(SignatureDatabaseError::SignatureSizeMismatch {
+            expected: 48,
+            found: 36
+        })
+    );
+    assert!(certs::read_certificates(b"not a certificate").is_err());
+}
+
+#[test]
+fn test_copy_efivars() {
+    let pk = cert("Platform Key", &key(), None).to_der().unwrap();
+    let vars = SecureBootVariables::from_keys(OWNER, &pk, &[], &[], &[]).unwrap();
+    let from = temp_dir();
+    vars.write(from.path()).unwrap();
+    let sbat_level = format!("SbatLevelRT-{GUID_SHIM_LOCK}");
+    fs::write(from.path().join(&sbat_level), b"sbat,1,2021030218\n").unwrap();
+
+    let to = temp_dir();
+    copy_efivars(from.path(), to.path()).unwrap();
+    let pk_file = format!("PK-{GUID_GLOBAL_VARIABLE}");
+    assert!(to.path().join(&pk_file).exists());
+    assert_eq!(
+        fs::read(to.path().join(&sbat_level)).unwrap(),
+        b"sbat,1,2021030218\n"
+    );
+    copy_efivars(to.path(), to.path()).unwrap();
+
+    let empty = SecureBootVariables {
+        variables: SECURE_BOOT_VARIABLES
+            .iter()
+            .map(|(name, guid)| UEFIVariableData::new(*guid, name, vec![]))
+            .collect(),
+    };
+    empty.write(to.path()).unwrap();
+    assert!(!to.path().join(&pk_file).exists());
+    assert!(to.path().join(&sbat_level).exists());
+}
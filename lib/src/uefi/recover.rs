@@ -0,0 +1,150 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+
+//! Recovery of the UEFI variables PCR 7 and PCR 14 are computed from, out
+//! of an event log recorded on the target platform.
+//!
+//! The firmware logs the whole UEFI_VARIABLE_DATA of PK, KEK, db and dbx in
+//! their PCR 7 EV_EFI_VARIABLE_DRIVER_CONFIG events, and shim does the same
+//! for SbatLevel. shim only logs the names of the MOK variables it measures
+//! into PCR 14, so their contents can only be recovered from events that
+//! carry the data that was hashed, or when the variable was empty.
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::efivars::{SECURE_BOOT_VAR_ATTRIBUTES, SECURE_BOOT_VARIABLES, write_efivar};
+use super::{GUID_SHIM_LOCK, UEFIVariableData};
+use crate::mok::{MOK_EVENTS_PCR14, mok_event_to_file_name};
+use crate::tpmevents::EventType;
+use crate::tpmevents::eventlog::EventLogEntry;
+
+// BOOTSERVICE_ACCESS | RUNTIME_ACCESS, of the runtime copy shim makes of
+// SbatLevel
+const SBAT_LEVEL_RT_ATTRIBUTES: u32 = 0x06;
+const SBAT_LEVEL: &str = "SbatLevel";
+
+/// UEFI variables recovered from an event log
+#[derive(Debug, Default)]
+pub struct RecoveredVariables {
+    /// PK, KEK, db and dbx, in the order they are measured
+    pub secure_boot: Vec<UEFIVariableData>,
+    pub sbat_level: Option<Vec<u8>>,
+    /// Contents of MokList, MokListX and MokListTrusted, when recoverable
+    pub mok: Vec<(String, Option<Vec<u8>>)>,
+}
+
+impl RecoveredVariables {
+    /// Names of the variables that could not be recovered from the log
+    pub fn missing(&self) -> Vec<String> {
+        let secure_boot = SECURE_BOOT_VARIABLES
+            .iter()
+            .filter(|(var, _)| !self.secure_boot.iter().any(|v| v.unicode_name() == *var))
+            .map(|(var, _)| var.to_string());
+        let sbat_level = self.sbat_level.is_none().then(|| SBAT_LEVEL.to_string());
+        let mok = MOK_EVENTS_PCR14
+            .iter()
+            .filter(|var| !self.mok.iter().any(|(n, d)| n == *var && d.is_some()))
+            .map(|var| var.to_string());
+        secure_boot.chain(sbat_level).chain(mok).collect()
+    }
+
+    /// Writes the variables in the layouts the efivars and mok-variables
+    /// directories of the CLI expect: efivarfs files for the Secure Boot
+    /// variables and SbatLevelRT, raw MokList{,X,Trusted}RT files for the
+    /// MOK variables. Empty Secure Boot variables are not written, as the
+    /// firmware does not create them.
+    pub fn write(&self, efivars_dir: &Path, mok_dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(efivars_dir)?;
+        fs::create_dir_all(mok_dir)?;
+
+        for var in self.secure_boot.iter().filter(|v| !v.data().is_empty()) {
+            write_efivar(
+                efivars_dir,
+                &format!("{}-{}", var.unicode_name(), var.variable_name()),
+                SECURE_BOOT_VAR_ATTRIBUTES,
+                var.data(),
+            )?;
+        }
+        if let Some(sbat_level) = &self.sbat_level {
+            write_efivar(
+                efivars_dir,
+                &format!("{SBAT_LEVEL}RT-{GUID_SHIM_LOCK}"),
+                SBAT_LEVEL_RT_ATTRIBUTES,
+                sbat_level,
+            )?;
+        }
+        for (name, data) in &self.mok {
+            if let Some(data) = data {
+                fs::write(mok_dir.join(mok_event_to_file_name(name)), data)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// UEFI_VARIABLE_DATA of an entry, if its event data is what was hashed
+fn hashed_variable(entry: &EventLogEntry) -> Option<UEFIVariableData> {
+    let digest = entry.digest.as_ref()?;
+    if Sha256::digest(&entry.data).as_slice() != digest.as_slice() {
+        return None;
+    }
+    UEFIVariableData::decode(&entry.data)
+}
+
+/// Contents a PCR 14 entry was measured from: its event data if that is what
+/// was hashed, or nothing if the digest is the one of an empty variable
+fn mok_contents(entry: &EventLogEntry) -> Option<Vec<u8>> {
+    let digest = entry.digest.as_ref()?;
+    if Sha256::digest(&entry.data).as_slice() == digest.as_slice() {
+        Some(entry.data.clone())
+    } else if Sha256::digest([]).as_slice() == digest.as_slice() {
+        Some(vec![])
+    } else {
+        None
+    }
+}
+
+/// Extracts the Secure Boot variables, SbatLevel and the MOK variables from
+/// the entries of a recorded event log
+pub fn recover_variables(entries: &[EventLogEntry]) -> RecoveredVariables {
+    let mut recovered = RecoveredVariables::default();
+
+    for entry in entries.iter().filter(|e| e.pcr == 7) {
+        let Some(var) = hashed_variable(entry) else {
+            continue;
+        };
+        let name = var.unicode_name();
+        if entry.event_type == EventType::EfiVariableDriverConfig as u32
+            && SECURE_BOOT_VARIABLES
+                .iter()
+                .any(|(n, guid)| *n == name && *guid == var.variable_name())
+            && !recovered
+                .secure_boot
+                .iter()
+                .any(|v| v.unicode_name() == name)
+        {
+            recovered.secure_boot.push(var);
+        } else if entry.event_type == EventType::EfiVariableAuthority as u32
+            && name == SBAT_LEVEL
+            && var.variable_name() == GUID_SHIM_LOCK
+        {
+            recovered.sbat_level = Some(var.data().to_vec());
+        }
+    }
+
+    // shim measures the MOK variables in a fixed order
+    recovered.mok = entries
+        .iter()
+        .filter(|e| e.pcr == 14 && e.event_type == EventType::Ipl as u32)
+        .zip(MOK_EVENTS_PCR14)
+        .map(|(entry, name)| (name.to_string(), mok_contents(entry)))
+        .collect();
+
+    recovered
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,148 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+use super::*;
+use crate::mok::MokEventData;
+use crate::test_support::temp_dir;
+use crate::uefi::efivars::{EFIVarsLoader, SECURE_BOOT_ATTR_HEADER_LENGTH};
+use crate::uefi::{GUID_GLOBAL_VARIABLE, GUID_SECURITY_DATABASE};
+
+fn variable_entry(event_type: EventType, var: &UEFIVariableData) -> EventLogEntry {
+    EventLogEntry {
+        pcr: 7,
+        event_type: event_type as u32,
+        digest: Some(var.hash()),
+        data: var.encode(),
+    }
+}
+
+fn mok_entry(name: &str, contents: &[u8]) -> EventLogEntry {
+    EventLogEntry {
+        pcr: 14,
+        event_type: EventType::Ipl as u32,
+        digest: Some(Sha256::digest(contents).to_vec()),
+        data: format!("{name}\0").into_bytes(),
+    }
+}
+
+fn entries() -> Vec<EventLogEntry> {
+    let driver_config = EventType::EfiVariableDriverConfig;
+    vec![
+        variable_entry(
+            driver_config,
+            &UEFIVariableData::new(GUID_GLOBAL_VARIABLE, "SecureBoot", vec![1]),
+        ),
+        variable_entry(
+            driver_config,
+            &UEFIVariableData::new(GUID_GLOBAL_VARIABLE, "PK", b"pk".to_vec()),
+        ),
+        variable_entry(
+            driver_config,
+            &UEFIVariableData::new(GUID_GLOBAL_VARIABLE, "KEK", b"kek".to_vec()),
+        ),
+        variable_entry(
+            driver_config,
+            &UEFIVariableData::new(GUID_SECURITY_DATABASE, "db", b"db".to_vec()),
+        ),
+        variable_entry(
+            driver_config,
+            &UEFIVariableData::new(GUID_SECURITY_DATABASE, "dbx", vec![]),
+        ),
+        // The certificate that verified shim is not the db variable
+        variable_entry(
+            EventType::EfiVariableAuthority,
+            &UEFIVariableData::new(GUID_SECURITY_DATABASE, "db", b"cert".to_vec()),
+        ),
+        variable_entry(
+            EventType::EfiVariableAuthority,
+            &UEFIVariableData::new(GUID_SHIM_LOCK, "SbatLevel", b"sbat,1\n".to_vec()),
+        ),
+        mok_entry("MokList", b"moklist"),
+        mok_entry("MokListX", b""),
+        mok_entry("MokListTrusted", &[1]),
+    ]
+}
+
+#[test]
+fn test_recover_variables() {
+    let recovered = recover_variables(&entries());
+
+    assert_eq!(
+        recovered
+            .secure_boot
+            .iter()
+            .map(|v| (v.unicode_name(), v.data().to_vec()))
+            .collect::<Vec<_>>(),
+        vec![
+            ("PK".to_string(), b"pk".to_vec()),
+            ("KEK".to_string(), b"kek".to_vec()),
+            ("db".to_string(), b"db".to_vec()),
+            ("dbx".to_string(), vec![]),
+        ]
+    );
+    assert_eq!(
+        recovered.sbat_level.as_deref(),
+        Some(b"sbat,1\n".as_slice())
+    );
+    // Only the empty MokListX can be recovered from shim's event data
+    assert_eq!(
+        recovered.mok,
+        vec![
+            ("MokList".to_string(), None),
+            ("MokListX".to_string(), Some(vec![])),
+            ("MokListTrusted".to_string(), None),
+        ]
+    );
+    assert_eq!(recovered.missing(), vec!["MokList", "MokListTrusted"]);
+    assert_eq!(
+        recover_variables(&[]).missing(),
+        vec![
+            "PK",
+            "KEK",
+            "db",
+            "dbx",
+            "SbatLevel",
+            "MokList",
+            "MokListX",
+            "MokListTrusted"
+        ]
+    );
+}
+
+#[test]
+fn test_write_recovered_variables() {
+    let dir = temp_dir();
+    let (efivars, mok) = (dir.path().join("efivars"), dir.path().join("mok"));
+    let mut recovered = recover_variables(&entries());
+    recovered.mok = MOK_EVENTS_PCR14
+        .iter()
+        .map(|n| (n.to_string(), Some(n.as_bytes().to_vec())))
+        .collect();
+    recovered.write(&efivars, &mok).unwrap();
+
+    let loaded: Vec<_> =
+        EFIVarsLoader::new(efivars.to_str().unwrap(), SECURE_BOOT_ATTR_HEADER_LENGTH)
+            .map(|v| v.data().to_vec())
+            .collect();
+    assert_eq!(
+        loaded,
+        vec![b"pk".to_vec(), b"kek".to_vec(), b"db".to_vec(), vec![]]
+    );
+    assert!(
+        !efivars
+            .join(format!("dbx-{GUID_SECURITY_DATABASE}"))
+            .exists()
+    );
+    assert_eq!(
+        fs::read(efivars.join(format!("SbatLevelRT-{GUID_SHIM_LOCK}"))).unwrap(),
+        b"\x06\0\0\0sbat,1\n"
+    );
+    assert_eq!(
+        MokEventData::new(mok.to_str().unwrap()).collect::<Vec<_>>(),
+        vec![
+            b"MokList".to_vec(),
+            b"MokListX".to_vec(),
+            b"MokListTrusted".to_vec()
+        ]
+    );
+}
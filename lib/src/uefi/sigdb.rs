@@ -0,0 +1,323 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+
+//! Signature databases, as stored in db, dbx, KEK, PK, the MOK variables and
+//! shim's vendor_db and vendor_dbx: a sequence of EFI_SIGNATURE_LISTs, each
+//! holding EFI_SIGNATURE_DATA entries of a single signature type and size.
+//!
+//! Databases are kept as they were read, including unknown signature types
+//! and list headers, so encoding a parsed database gives back the same bytes.
+use std::fmt;
+use uuid::{Uuid, uuid};
+
+// SignatureType, SignatureListSize, SignatureHeaderSize and SignatureSize
+const SIGNATURE_LIST_HEADER_SIZE: usize = 28;
+const SIGNATURE_OWNER_SIZE: usize = 16;
+
+/// Signature types of the UEFI specification
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignatureType {
+    Sha1,
+    Sha224,
+    Sha256,
+    Sha384,
+    Sha512,
+    Rsa2048,
+    Rsa2048Sha1,
+    Rsa2048Sha256,
+    X509,
+    X509Sha256,
+    X509Sha384,
+    X509Sha512,
+    Pkcs7,
+    Unknown(Uuid),
+}
+
+const SIGNATURE_TYPES: [(SignatureType, Uuid); 13] = [
+    (
+        SignatureType::Sha1,
+        uuid!("826ca512-cf10-4ac9-b187-be01496631bd"),
+    ),
+    (
+        SignatureType::Sha224,
+        uuid!("0b6e5233-a65c-44c9-9407-d9ab83bfc8bd"),
+    ),
+    (SignatureType::Sha256, super::EFI_CERT_SHA256_GUID),
+    (
+        SignatureType::Sha384,
+        uuid!("ff3e5307-9fd0-48c9-85f1-8ad56c701e01"),
+    ),
+    (
+        SignatureType::Sha512,
+        uuid!("093e0fae-a6c4-4f50-9f1b-d41e2b89c19a"),
+    ),
+    (
+        SignatureType::Rsa2048,
+        uuid!("3c5766e8-269c-4e34-aa14-ed776e85b3b6"),
+    ),
+    (
+        SignatureType::Rsa2048Sha1,
+        uuid!("67f8444f-8743-48f1-a328-1eaab8736080"),
+    ),
+    (
+        SignatureType::Rsa2048Sha256,
+        uuid!("e2b36190-879b-4a3d-ad8d-f2e7bba32784"),
+    ),
+    (SignatureType::X509, super::EFI_CERT_TYPE_X509_GUID),
+    (SignatureType::X509Sha256, super::EFI_CERT_X509_SHA256_GUID),
+    (
+        SignatureType::X509Sha384,
+        uuid!("7076876e-80c2-4ee6-aad2-28b349a6865b"),
+    ),
+    (
+        SignatureType::X509Sha512,
+        uuid!("446dbf63-2502-4cda-bcfa-2465d2b0fe9d"),
+    ),
+    (
+        SignatureType::Pkcs7,
+        uuid!("4aafd29d-68df-49ee-8aa9-347d375665a7"),
+    ),
+];
+
+impl SignatureType {
+    pub fn from_guid(guid: Uuid) -> SignatureType {
+        SIGNATURE_TYPES
+            .iter()
+            .find(|(_, g)| *g == guid)
+            .map_or(SignatureType::Unknown(guid), |(t, _)| *t)
+    }
+
+    pub fn guid(&self) -> Uuid {
+        match self {
+            SignatureType::Unknown(guid) => *guid,
+            known => SIGNATURE_TYPES
+                .iter()
+                .find(|(t, _)| t == known)
+                .map(|(_, g)| *g)
+                .expect("Every known signature type has a GUID"),
+        }
+    }
+
+    /// Size of the signature data of the type, without the owner, for types
+    /// that have a fixed size. The X509 hash types are followed by the
+    /// EFI_TIME of the revocation, and the RSA-2048 signature types hold the
+    /// 256 byte signature of the hash, not the hash itself.
+    pub fn data_size(&self) -> Option<usize> {
+        match self {
+            SignatureType::Sha1 => Some(20),
+            SignatureType::Sha224 => Some(28),
+            SignatureType::Sha256 => Some(32),
+            SignatureType::Sha384 => Some(48),
+            SignatureType::Sha512 => Some(64),
+            SignatureType::Rsa2048 | SignatureType::Rsa2048Sha1 | SignatureType::Rsa2048Sha256 => {
+                Some(256)
+            }
+            SignatureType::X509Sha256 => Some(32 + 16),
+            SignatureType::X509Sha384 => Some(48 + 16),
+            SignatureType::X509Sha512 => Some(64 + 16),
+            SignatureType::X509 | SignatureType::Pkcs7 | SignatureType::Unknown(_) => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SignatureDatabaseError {
+    /// The list at this offset does not fit its header
+    TruncatedList(usize),
+    /// The list at this offset has a size that does not fit its contents
+    InvalidListSize(usize),
+    /// The list at this offset has a signature size that does not divide
+    /// its contents, or that is smaller than the signature owner
+    InvalidSignatureSize(usize),
+    /// A signature does not have the size of the other signatures of its
+    /// list
+    SignatureSizeMismatch { expected: usize, found: usize },
+}
+
+impl fmt::Display for SignatureDatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SignatureDatabaseError::TruncatedList(offset) => {
+                write!(f, "Truncated signature list at offset {offset}")
+            }
+            SignatureDatabaseError::InvalidListSize(offset) => {
+                write!(f, "Invalid signature list size at offset {offset}")
+            }
+            SignatureDatabaseError::InvalidSignatureSize(offset) => {
+                write!(f, "Invalid signature size in the list at offset {offset}")
+            }
+            SignatureDatabaseError::SignatureSizeMismatch { expected, found } => write!(
+                f,
+                "Signature of {found} bytes in a list of {expected} byte signatures"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SignatureDatabaseError {}
+
+/// An EFI_SIGNATURE_DATA entry
+#[derive(Clone, Debug, PartialEq)]
+pub struct SignatureData {
+    pub owner: Uuid,
+    pub data: Vec<u8>,
+}
+
+impl SignatureData {
+    pub fn new(owner: Uuid, data: Vec<u8>) -> SignatureData {
+        SignatureData { owner, data }
+    }
+
+    /// The whole EFI_SIGNATURE_DATA, as firmware and shim log the entries
+    /// that authorize an image
+    pub fn encode(&self) -> Vec<u8> {
+        [self.owner.to_bytes_le().as_slice(), &self.data].concat()
+    }
+}
+
+/// An EFI_SIGNATURE_LIST
+#[derive(Clone, Debug, PartialEq)]
+pub struct SignatureList {
+    pub signature_type: SignatureType,
+    /// The SignatureHeader, which no signature type of the specification
+    /// uses
+    pub header: Vec<u8>,
+    /// Size of each EFI_SIGNATURE_DATA, including the owner
+    pub signature_size: usize,
+    pub signatures: Vec<SignatureData>,
+}
+
+impl SignatureList {
+    /// Creates an empty list for signatures of the given data size
+    pub fn new(signature_type: SignatureType, data_size: usize) -> SignatureList {
+        SignatureList {
+            signature_type,
+            header: vec![],
+            signature_size: SIGNATURE_OWNER_SIZE + data_size,
+            signatures: vec![],
+        }
+    }
+
+    /// Parses the list at the offset of the database, returning it and its
+    /// size
+    fn decode(
+        data: &[u8],
+        offset: usize,
+    ) -> Result<(SignatureList, usize), SignatureDatabaseError> {
+        let header = data
+            .get(offset..offset + SIGNATURE_LIST_HEADER_SIZE)
+            .ok_or(SignatureDatabaseError::TruncatedList(offset))?;
+        let u32_at =
+            |at: usize| u32::from_le_bytes(header[at..at + 4].try_into().unwrap()) as usize;
+        let signature_type =
+            SignatureType::from_guid(Uuid::from_bytes_le(header[..16].try_into().unwrap()));
+        let (list_size, header_size, signature_size) = (u32_at(16), u32_at(20), u32_at(24));
+
+        let list = list_size
+            .checked_sub(SIGNATURE_LIST_HEADER_SIZE + header_size)
+            .and_then(|_| data.get(offset..offset.checked_add(list_size)?))
+            .ok_or(SignatureDatabaseError::InvalidListSize(offset))?;
+        let (header, signatures) = list[SIGNATURE_LIST_HEADER_SIZE..].split_at(header_size);
+        if signature_size < SIGNATURE_OWNER_SIZE || !signatures.len().is_multiple_of(signature_size)
+        {
+            return Err(SignatureDatabaseError::InvalidSignatureSize(offset));
+        }
+
+        let signatures = signatures
+            .chunks_exact(signature_size)
+            .map(|s| {
+                let (owner, data) = s.split_at(SIGNATURE_OWNER_SIZE);
+                SignatureData::new(Uuid::from_bytes_le(owner.try_into().unwrap()), data.into())
+            })
+            .collect();
+        Ok((
+            SignatureList {
+                signature_type,
+                header: header.into(),
+                signature_size,
+                signatures,
+            },
+            list_size,
+        ))
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>, SignatureDatabaseError> {
+        let list_size = SIGNATURE_LIST_HEADER_SIZE
+            + self.header.len()
+            + self.signature_size * self.signatures.len();
+        let mut list = self.signature_type.guid().to_bytes_le().to_vec();
+        list.extend((list_size as u32).to_le_bytes());
+        list.extend((self.header.len() as u32).to_le_bytes());
+        list.extend((self.signature_size as u32).to_le_bytes());
+        list.extend(&self.header);
+        for signature in &self.signatures {
+            let encoded = signature.encode();
+            if encoded.len() != self.signature_size {
+                return Err(SignatureDatabaseError::SignatureSizeMismatch {
+                    expected: self.signature_size,
+                    found: encoded.len(),
+                });
+            }
+            list.extend(encoded);
+        }
+        Ok(list)
+    }
+}
+
+/// The contents of a signature database variable
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SignatureDatabase {
+    pub lists: Vec<SignatureList>,
+}
+
+impl SignatureDatabase {
+    pub fn decode(data: &[u8]) -> Result<SignatureDatabase, SignatureDatabaseError> {
+        let mut lists = vec![];
+        let mut offset = 0;
+        while offset < data.len() {
+            let (list, size) = SignatureList::decode(data, offset)?;
+            lists.push(list);
+            offset += size;
+        }
+        Ok(SignatureDatabase { lists })
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>, SignatureDatabaseError> {
+        Ok(self
+            .lists
+            .iter()
+            .map(SignatureList::encode)
+            .collect::<Result<Vec<_>, _>>()?
+            .concat())
+    }
+
+    /// Every signature of the database, along with the type of its list
+    pub fn signatures(&self) -> impl Iterator<Item = (SignatureType, &SignatureData)> {
+        self.lists
+            .iter()
+            .flat_map(|l| l.signatures.iter().map(|s| (l.signature_type, s)))
+    }
+
+    /// Appends the lists of another database, as firmware does for
+    /// EFI_VARIABLE_APPEND_WRITE updates: signatures already present in a
+    /// list of the same type and size are dropped, and lists left empty are
+    /// not appended
+    pub fn append(&mut self, other: SignatureDatabase) {
+        for mut list in other.lists {
+            list.signatures.retain(|signature| {
+                !self.lists.iter().any(|l| {
+                    l.signature_type == list.signature_type
+                        && l.signature_size == list.signature_size
+                        && l.signatures.contains(signature)
+                })
+            });
+            if !list.signatures.is_empty() {
+                self.lists.push(list);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,167 @@
+// SPDX-FileCopyrightText: Beñat Gartzia Arruabarrena <bgartzia@redhat.com>
+//
+// SPDX-License-Identifier: MIT
+use super::*;
+use crate::uefi::{EFI_CERT_SHA256_GUID, EFI_CERT_TYPE_X509_GUID, EFI_CERT_X509_SHA256_GUID};
+
+const OWNER: Uuid = uuid!("77fa9abd-0359-4d32-bd60-28f4e78f784b");
+const UNKNOWN_TYPE: Uuid = uuid!("00000000-1111-2222-3333-444444444444");
+
+fn list_header(guid: Uuid, list_size: u32, header_size: u32, signature_size: u32) -> Vec<u8> {
+    [
+        guid.to_bytes_le().as_slice(),
+        &list_size.to_le_bytes(),
+        &header_size.to_le_bytes(),
+        &signature_size.to_le_bytes(),
+    ]
+    .concat()
+}
+
+fn database() -> Vec<u8> {
+    [
+        // Two sha256 hashes
+        list_header(EFI_CERT_SHA256_GUID, 28 + 2 * 48, 0, 48),
+        OWNER.to_bytes_le().to_vec(),
+        vec![1; 32],
+        OWNER.to_bytes_le().to_vec(),
+        vec![2; 32],
+        // An unknown signature type with a header
+        list_header(UNKNOWN_TYPE, 28 + 3 + 20, 3, 20),
+        vec![9; 3],
+        OWNER.to_bytes_le().to_vec(),
+        vec![3; 4],
+        // An empty list
+        list_header(EFI_CERT_TYPE_X509_GUID, 28, 0, 1000),
+    ]
+    .concat()
+}
+
+#[test]
+fn test_signature_database_round_trip() {
+    let data = database();
+    let db = SignatureDatabase::decode(&data).unwrap();
+
+    assert_eq!(db.lists.len(), 3);
+    assert_eq!(db.lists[0].signature_type, SignatureType::Sha256);
+    assert_eq!(
+        db.lists[0].signatures,
+        [
+            SignatureData::new(OWNER, vec![1; 32]),
+            SignatureData::new(OWNER, vec![2; 32])
+        ]
+    );
+    assert_eq!(
+        db.lists[1].signature_type,
+        SignatureType::Unknown(UNKNOWN_TYPE)
+    );
+    assert_eq!(db.lists[1].header, [9; 3]);
+    assert_eq!(db.lists[2].signature_type, SignatureType::X509);
+    assert_eq!(db.lists[2].signature_size, 1000);
+    assert!(db.lists[2].signatures.is_empty());
+    assert_eq!(db.signatures().count(), 3);
+    assert_eq!(db.encode().unwrap(), data);
+
+    assert_eq!(SignatureDatabase::decode(&[]).unwrap(), Default::default());
+}
+
+#[test]
+fn test_signature_database_edit() {
+    let mut db = SignatureDatabase::decode(&database()).unwrap();
+    db.lists[0].signatures.remove(0);
+    let mut list = SignatureList::new(SignatureType::X509Sha256, 48);
+    list.signatures.push(SignatureData::new(OWNER, vec![4; 48]));
+    db.lists.push(list);
+
+    let edited = SignatureDatabase::decode(&db.encode().unwrap()).unwrap();
+    assert_eq!(edited, db);
+    assert_eq!(edited.lists[0].signatures.len(), 1);
+    assert_eq!(
+        edited.lists[3].signature_type.guid(),
+        EFI_CERT_X509_SHA256_GUID
+    );
+
+    db.lists[3]
+        .signatures
+        .push(SignatureData::new(OWNER, vec![5; 32]));
+    assert_eq!(
+        db.encode(),
+        Err(SignatureDatabaseError::SignatureSizeMismatch {
+            expected: 64,
+            found: 48
+        })
+    );
+}
+
+#[test]
+fn test_signature_database_append() {
+    let mut db = SignatureDatabase::decode(&database()).unwrap();
+    let mut update = SignatureDatabase::default();
+    let mut hashes = SignatureList::new(SignatureType::Sha256, 32);
+    hashes.signatures = vec![
+        SignatureData::new(OWNER, vec![2; 32]),
+        SignatureData::new(OWNER, vec![5; 32]),
+        // Same hash, another owner
+        SignatureData::new(Uuid::nil(), vec![1; 32]),
+    ];
+    update.lists.push(hashes);
+    let mut known = SignatureList::new(SignatureType::Unknown(UNKNOWN_TYPE), 4);
+    known.signatures = vec![SignatureData::new(OWNER, vec![3; 4])];
+    update.lists.push(known);
+
+    db.append(update);
+    assert_eq!(db.lists.len(), 4);
+    assert_eq!(
+        db.lists[3].signatures,
+        [
+            SignatureData::new(OWNER, vec![5; 32]),
+            SignatureData::new(Uuid::nil(), vec![1; 32])
+        ]
+    );
+    assert!(
+        SignatureDatabase::decode(&db.encode().unwrap())
+            .unwrap()
+            .signatures()
+            .any(|(_, s)| s.data == [5; 32])
+    );
+}
+
+#[test]
+fn test_signature_database_errors() {
+    let data = database();
+    let second_list = 28 + 2 * 48;
+
+    assert_eq!(
+        SignatureDatabase::decode(&data[..second_list + 10]),
+        Err(SignatureDatabaseError::TruncatedList(second_list))
+    );
+    assert_eq!(
+        SignatureDatabase::decode(&data[..data.len() - 29]),
+        Err(SignatureDatabaseError::InvalidListSize(second_list))
+    );
+    let mut bad_signature_size = data.clone();
+    bad_signature_size[24] = 47;
+    assert_eq!(
+        SignatureDatabase::decode(&bad_signature_size),
+        Err(SignatureDatabaseError::InvalidSignatureSize(0))
+    );
+    let mut short_list = data.clone();
+    short_list[16] = 27;
+    assert_eq!(
+        SignatureDatabase::decode(&short_list),
+        Err(SignatureDatabaseError::InvalidListSize(0))
+    );
+}
+
+#[test]
+fn test_signature_type_guids() {
+    for (signature_type, guid) in SIGNATURE_TYPES {
+        assert_eq!(SignatureType::from_guid(guid), signature_type);
+        assert_eq!(signature_type.guid(), guid);
+    }
+    assert_eq!(SignatureType::Sha256.data_size(), Some(32));
+    assert_eq!(SignatureType::X509Sha256.data_size(), Some(48));
+    assert_eq!(SignatureType::Rsa2048.data_size(), Some(256));
+    assert_eq!(SignatureType::Rsa2048Sha1.data_size(), Some(256));
+    assert_eq!(SignatureType::Rsa2048Sha256.data_size(), Some(256));
+    assert_eq!(SignatureType::X509.data_size(), None);
+}